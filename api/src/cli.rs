@@ -0,0 +1,128 @@
+use crate::dependency::translation::Translator;
+use crate::router::csv::{parse_csv, CsvQuery, CsvRouteError};
+use crate::router::po::{translate_catalog, PoQuery, PoRouteError};
+use std::fs;
+use std::io::{self, Read};
+use std::str::FromStr;
+use thiserror::Error;
+use translation::Language;
+
+const USAGE: &str = "Usage:
+  translation-api translate-po --target <language> [--source <language>] [file]
+  translation-api translate-csv --column <name> --targets <language,...> [--source <language>] [file]";
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}\n{USAGE}")]
+    InvalidArguments(String),
+    #[error("IoError {0}")]
+    IoError(#[from] io::Error),
+    #[error("PoError {0}")]
+    PoError(#[from] PoRouteError),
+    #[error("CsvError {0}")]
+    CsvError(#[from] CsvRouteError),
+}
+
+fn parse_language(value: Option<&String>, name: &str) -> Result<Language, CliError> {
+    let value = value.ok_or(CliError::InvalidArguments(format!("Missing {name}.")))?;
+    Language::from_str(value)
+        .map_err(|_| CliError::InvalidArguments(format!("Invalid {name} {value}.")))
+}
+
+fn read_document(path: Option<&String>) -> Result<String, CliError> {
+    match path {
+        Some(path) => Ok(fs::read_to_string(path)?),
+        None => {
+            let mut document = String::new();
+            io::stdin().read_to_string(&mut document)?;
+            Ok(document)
+        }
+    }
+}
+
+/// Fill in the empty translations of a POT or PO catalog, read from a file or stdin, and
+/// write the catalog to stdout.
+async fn translate_po(arguments: &[String], translator: &Translator) -> Result<(), CliError> {
+    let mut source = None;
+    let mut target = None;
+    let mut path = None;
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--source" => source = Some(parse_language(arguments.next(), "source")?),
+            "--target" => target = Some(parse_language(arguments.next(), "target")?),
+            _ if path.is_none() && !argument.starts_with("--") => path = Some(argument),
+            _ => {
+                return Err(CliError::InvalidArguments(format!(
+                    "Unexpected argument {argument}."
+                )))
+            }
+        }
+    }
+    let target = target.ok_or(CliError::InvalidArguments("Missing target.".to_owned()))?;
+
+    let document = read_document(path)?;
+    let catalog = translate_catalog(translator, &document, PoQuery { source, target }).await?;
+    print!("{catalog}");
+    Ok(())
+}
+
+/// Translate a column of a CSV file, read from a file or stdin, into each target language
+/// and write the file with the translated columns to stdout as its rows are translated.
+async fn translate_csv(arguments: &[String], translator: &Translator) -> Result<(), CliError> {
+    let mut column = None;
+    let mut source = None;
+    let mut targets = None;
+    let mut path = None;
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--column" => column = arguments.next().cloned(),
+            "--source" => source = Some(parse_language(arguments.next(), "source")?),
+            "--targets" => targets = arguments.next().cloned(),
+            _ if path.is_none() && !argument.starts_with("--") => path = Some(argument),
+            _ => {
+                return Err(CliError::InvalidArguments(format!(
+                    "Unexpected argument {argument}."
+                )))
+            }
+        }
+    }
+    let column = column.ok_or(CliError::InvalidArguments("Missing column.".to_owned()))?;
+    let targets = targets.ok_or(CliError::InvalidArguments("Missing targets.".to_owned()))?;
+
+    let document = read_document(path)?;
+    let translation = parse_csv(
+        &document,
+        CsvQuery {
+            column,
+            source,
+            targets,
+        },
+    )?;
+    print!("{}", translation.header().map_err(CsvRouteError::from)?);
+    for chunk in 0..translation.chunk_count() {
+        let rows = translation
+            .translate_chunk(translator, chunk)
+            .await
+            .map_err(CsvRouteError::from)?;
+        print!("{rows}");
+    }
+    Ok(())
+}
+
+/// Run the command named by the first argument.
+pub async fn run(arguments: &[String], translator: &Translator) -> Result<(), CliError> {
+    match arguments.split_first() {
+        Some((command, arguments)) if command == "translate-po" => {
+            translate_po(arguments, translator).await
+        }
+        Some((command, arguments)) if command == "translate-csv" => {
+            translate_csv(arguments, translator).await
+        }
+        Some((command, _)) => Err(CliError::InvalidArguments(format!(
+            "Unknown command {command}."
+        ))),
+        None => Err(CliError::InvalidArguments("Missing command.".to_owned())),
+    }
+}
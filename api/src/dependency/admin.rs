@@ -0,0 +1,10 @@
+use env::require_env_var;
+
+pub const ADMIN_TOKEN: &str = "ADMIN_TOKEN";
+
+/// The token admin endpoints require, which are disabled without one.
+pub fn maybe_read_admin_token() -> Option<String> {
+    require_env_var(ADMIN_TOKEN)
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+}
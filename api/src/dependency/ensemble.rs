@@ -0,0 +1,34 @@
+use crate::dependency::translation::{Provider, Providers};
+use env::{env_var_or_default, require_env_var};
+use itertools::Itertools;
+use translation::ensemble::Ensemble;
+
+pub const ENSEMBLE_PROVIDERS: &str = "ENSEMBLE_PROVIDERS";
+pub const ENSEMBLE_JUDGE_PROVIDER: &str = "ENSEMBLE_JUDGE_PROVIDER";
+
+pub fn maybe_create_ensemble(providers: &Providers) -> Option<Ensemble<Provider>> {
+    let members = require_env_var(ENSEMBLE_PROVIDERS)
+        .ok()?
+        .split(',')
+        .map(str::trim)
+        .filter(|member| !member.is_empty())
+        .map(|member| {
+            let provider = providers
+                .get(member)
+                .expect("Ensemble provider not configured.");
+            (member.to_owned(), provider)
+        })
+        .collect_vec();
+    let judge_provider = env_var_or_default(
+        ENSEMBLE_JUDGE_PROVIDER,
+        members
+            .last()
+            .expect("Invalid ensemble providers.")
+            .0
+            .clone(),
+    );
+    let judge = providers
+        .get(&judge_provider)
+        .expect("Ensemble judge provider not configured.");
+    Some(Ensemble::new(members, judge).expect("Ensemble judge provider can't judge."))
+}
@@ -0,0 +1,63 @@
+use crate::job::JobQueue;
+use crate::webhook::WebhookClient;
+use env::{env_var_or_default, require_env_var};
+use std::path::Path;
+use std::time::Duration;
+use translation::job::JobStore;
+
+pub const JOB_DIRECTORY: &str = "JOB_DIRECTORY";
+pub const JOB_WORKERS: &str = "JOB_WORKERS";
+pub const JOB_CHUNK_SIZE: &str = "JOB_CHUNK_SIZE";
+pub const JOB_RETENTION_SECONDS: &str = "JOB_RETENTION_SECONDS";
+pub const JOB_WEBHOOK_SECRET: &str = "JOB_WEBHOOK_SECRET";
+pub const JOB_WEBHOOK_MAX_RETRIES: &str = "JOB_WEBHOOK_MAX_RETRIES";
+pub const JOB_WEBHOOK_ALLOWED_HOSTS: &str = "JOB_WEBHOOK_ALLOWED_HOSTS";
+
+const WEBHOOK_MIN_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+fn maybe_create_webhook_client() -> Option<WebhookClient> {
+    let secret = require_env_var(JOB_WEBHOOK_SECRET).ok()?;
+    let max_retries = env_var_or_default(JOB_WEBHOOK_MAX_RETRIES, "5".to_owned())
+        .parse::<u32>()
+        .expect("Invalid job webhook max retries.");
+    let allowed_hosts = env_var_or_default(JOB_WEBHOOK_ALLOWED_HOSTS, String::new())
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(str::to_owned)
+        .collect();
+    Some(
+        WebhookClient::try_new(
+            secret,
+            max_retries,
+            WEBHOOK_MIN_RETRY_INTERVAL,
+            allowed_hosts,
+        )
+        .expect("Failed to create job webhook client."),
+    )
+}
+
+pub fn create_job_queue() -> JobQueue {
+    let directory = require_env_var(JOB_DIRECTORY).ok();
+    // without workers, jobs would stay pending forever
+    let workers = env_var_or_default(JOB_WORKERS, "2".to_owned())
+        .parse::<usize>()
+        .ok()
+        .filter(|workers| *workers > 0)
+        .expect("Invalid job workers.");
+    let chunk_size = env_var_or_default(JOB_CHUNK_SIZE, "50".to_owned())
+        .parse::<usize>()
+        .expect("Invalid job chunk size.");
+
+    let retention = require_env_var(JOB_RETENTION_SECONDS).ok().map(|seconds| {
+        Duration::from_secs(
+            seconds
+                .parse::<u64>()
+                .expect("Invalid job retention seconds."),
+        )
+    });
+
+    let store = JobStore::open(directory.as_deref().map(Path::new), chunk_size, retention)
+        .expect("Failed to open jobs.");
+    JobQueue::new(store, maybe_create_webhook_client(), workers)
+}
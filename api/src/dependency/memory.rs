@@ -0,0 +1,29 @@
+use env::{env_var_or_default, require_env_var};
+use std::path::Path;
+use translation::memory::{FuzzyMatchMode, TranslationMemory};
+
+pub const TRANSLATION_MEMORY_PATH: &str = "TRANSLATION_MEMORY_PATH";
+pub const TRANSLATION_MEMORY_FUZZY_THRESHOLD: &str = "TRANSLATION_MEMORY_FUZZY_THRESHOLD";
+pub const TRANSLATION_MEMORY_FUZZY_MATCHES: &str = "TRANSLATION_MEMORY_FUZZY_MATCHES";
+
+/// The memory, when `TRANSLATION_MEMORY_PATH` is set.
+pub fn maybe_create_translation_memory() -> Option<TranslationMemory> {
+    let path = require_env_var(TRANSLATION_MEMORY_PATH).ok()?;
+    let fuzzy_threshold = env_var_or_default(TRANSLATION_MEMORY_FUZZY_THRESHOLD, "0.8".to_owned())
+        .parse::<f32>()
+        .ok()
+        .filter(|threshold| (0.0..=1.0).contains(threshold))
+        .expect("Invalid translation memory fuzzy threshold.");
+    let fuzzy_match_mode =
+        match env_var_or_default(TRANSLATION_MEMORY_FUZZY_MATCHES, "reference".to_owned()).as_str()
+        {
+            "return" => FuzzyMatchMode::Return,
+            "reference" => FuzzyMatchMode::Reference,
+            _ => panic!("Invalid translation memory fuzzy matches."),
+        };
+
+    Some(
+        TranslationMemory::open(Path::new(&path), fuzzy_threshold, fuzzy_match_mode)
+            .unwrap_or_else(|error| panic!("Failed to open translation memory {path}: {error}")),
+    )
+}
@@ -1 +1,7 @@
+pub mod admin;
+pub mod ensemble;
+pub mod job;
+pub mod memory;
+pub mod quality;
+pub mod segment;
 pub mod translation;
@@ -0,0 +1,39 @@
+use crate::dependency::translation::{Provider, Providers};
+use env::{env_var_or_default, require_env_var};
+use std::str::FromStr;
+use translation::quality::{QualityEstimationMethod, QualityEstimator};
+
+pub const QUALITY_ESTIMATION: &str = "QUALITY_ESTIMATION";
+pub const QUALITY_ESTIMATION_PROVIDER: &str = "QUALITY_ESTIMATION_PROVIDER";
+pub const QUALITY_ESTIMATION_THRESHOLD: &str = "QUALITY_ESTIMATION_THRESHOLD";
+
+/// The estimator, when `QUALITY_ESTIMATION` is set. Its provider mustn't be the translation
+/// provider, which would judge its own translations.
+pub fn maybe_create_quality_estimator(
+    providers: &Providers,
+    translation_provider: &Provider,
+) -> Option<QualityEstimator<Provider>> {
+    let method = QualityEstimationMethod::from_str(&require_env_var(QUALITY_ESTIMATION).ok()?)
+        .expect("Invalid quality estimation method.");
+    let provider_name =
+        require_env_var(QUALITY_ESTIMATION_PROVIDER).expect("Missing quality estimation provider.");
+    let vendor = provider_name.split(':').next().unwrap_or_default();
+    assert_ne!(
+        vendor,
+        translation_provider.name(),
+        "Quality estimation provider must differ from the translation provider."
+    );
+    let threshold = env_var_or_default(QUALITY_ESTIMATION_THRESHOLD, "70".to_owned())
+        .parse::<u8>()
+        .ok()
+        .filter(|threshold| *threshold <= 100)
+        .expect("Invalid quality estimation threshold.");
+
+    let provider = providers
+        .get(&provider_name)
+        .expect("Quality estimation provider not configured.");
+    Some(
+        QualityEstimator::new(provider, method, threshold)
+            .expect("Quality estimation provider can't judge."),
+    )
+}
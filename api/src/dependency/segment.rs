@@ -0,0 +1,16 @@
+use env::env_var_or_default;
+use translation::segment::{Segmenter, DEFAULT_MAX_SEGMENT_CHARACTERS};
+
+pub const MAX_SEGMENT_CHARACTERS: &str = "MAX_SEGMENT_CHARACTERS";
+
+pub fn create_segmenter() -> Segmenter {
+    let max_characters = env_var_or_default(
+        MAX_SEGMENT_CHARACTERS,
+        DEFAULT_MAX_SEGMENT_CHARACTERS.to_string(),
+    )
+    .parse::<usize>()
+    .ok()
+    .filter(|max_characters| *max_characters > 0)
+    .expect("Invalid max segment characters.");
+    Segmenter::new(max_characters)
+}
@@ -0,0 +1,62 @@
+use crate::dependency::translation::{Provider, Providers};
+use env::{env_var_or_default, require_env_var};
+use itertools::Itertools;
+use std::str::FromStr;
+use translation::cascade::{Cascade, CascadeCheck};
+use translation::quality::{QualityEstimationMethod, QualityEstimator};
+
+pub const CASCADE_TIERS: &str = "CASCADE_TIERS";
+pub const CASCADE_CHECKS: &str = "CASCADE_CHECKS";
+pub const CASCADE_MAX_LENGTH_RATIO: &str = "CASCADE_MAX_LENGTH_RATIO";
+pub const CASCADE_JUDGE_PROVIDER: &str = "CASCADE_JUDGE_PROVIDER";
+pub const CASCADE_JUDGE_THRESHOLD: &str = "CASCADE_JUDGE_THRESHOLD";
+
+/// The cascade, when `CASCADE_TIERS` is set.
+pub fn maybe_create_cascade(providers: &Providers) -> Option<Cascade<Provider>> {
+    let tiers = require_env_var(CASCADE_TIERS)
+        .ok()?
+        .split(',')
+        .map(str::trim)
+        .filter(|tier| !tier.is_empty())
+        .map(|tier| {
+            let provider = providers
+                .get(tier)
+                .unwrap_or_else(|| panic!("Cascade tier {tier} isn't a configured provider."));
+            (tier.to_owned(), provider)
+        })
+        .collect_vec();
+    assert!(!tiers.is_empty(), "Missing cascade tiers.");
+    let checks = env_var_or_default(
+        CASCADE_CHECKS,
+        "placeholders,tags,language,length".to_owned(),
+    )
+    .split(',')
+    .map(str::trim)
+    .filter(|check| !check.is_empty())
+    .map(CascadeCheck::from_str)
+    .collect::<Result<Vec<_>, _>>()
+    .expect("Invalid cascade checks.");
+    let max_length_ratio = env_var_or_default(CASCADE_MAX_LENGTH_RATIO, "3".to_owned())
+        .parse::<f32>()
+        .ok()
+        .filter(|ratio| *ratio >= 1.0)
+        .expect("Invalid cascade max length ratio.");
+
+    // the most capable tier judges unless another provider is given
+    let judge_provider =
+        env_var_or_default(CASCADE_JUDGE_PROVIDER, tiers[tiers.len() - 1].0.clone());
+    let cascade = Cascade::new(tiers, checks.clone(), max_length_ratio);
+    if !checks.contains(&CascadeCheck::Quality) {
+        return Some(cascade);
+    }
+    let threshold = env_var_or_default(CASCADE_JUDGE_THRESHOLD, "70".to_owned())
+        .parse::<u8>()
+        .ok()
+        .filter(|threshold| *threshold <= 100)
+        .expect("Invalid cascade judge threshold.");
+    let judge = providers
+        .get(&judge_provider)
+        .and_then(|judge| QualityEstimator::new(judge, QualityEstimationMethod::Judge, threshold))
+        .unwrap_or_else(|| panic!("Cascade judge {judge_provider} can't judge quality."));
+    Some(cascade.with_judge(judge))
+}
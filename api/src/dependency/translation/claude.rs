@@ -8,8 +8,15 @@ pub const CLAUDE_API_KEY: &str = "CLAUDE_API_KEY";
 pub const CLAUDE_API_VERSION: &str = "CLAUDE_API_VERSION";
 pub const CLAUDE_MAX_PARALLEL_REQUESTS: &str = "CLAUDE_MAX_PARALLEL_REQUESTS";
 
-pub fn maybe_create_claude_translation_provider() -> Option<ClaudeTranslationProvider> {
-    let model = ClaudeModel::try_from(require_env_var(CLAUDE_MODEL).ok()?).ok()?;
+/// Uses `model` instead of the configured model when it's given, such as for a cascade tier.
+pub fn maybe_create_claude_translation_provider(
+    model: Option<&str>,
+) -> Option<ClaudeTranslationProvider> {
+    let model = ClaudeModel::try_from(match model {
+        Some(model) => model.to_owned(),
+        None => require_env_var(CLAUDE_MODEL).ok()?,
+    })
+    .ok()?;
     let api_key = require_env_var(CLAUDE_API_KEY).ok()?;
     let api_version = require_env_var(CLAUDE_API_VERSION).ok()?;
     let max_parallel_requests = require_env_var(CLAUDE_MAX_PARALLEL_REQUESTS)
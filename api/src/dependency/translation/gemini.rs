@@ -0,0 +1,31 @@
+use env::{env_var_or_default, require_env_var};
+use gemini::client::GeminiClient;
+use gemini::model::GeminiModel;
+use translation::gemini::GeminiTranslationProvider;
+
+pub const GEMINI_API: &str = "GEMINI_API";
+pub const GEMINI_MODEL: &str = "GEMINI_MODEL";
+pub const GEMINI_API_KEY: &str = "GEMINI_API_KEY";
+pub const GEMINI_MAX_PARALLEL_REQUESTS: &str = "GEMINI_MAX_PARALLEL_REQUESTS";
+
+const DEFAULT_GEMINI_API: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Uses `model` instead of the configured model when it's given, such as for a cascade tier.
+pub fn maybe_create_gemini_translation_provider(
+    model: Option<&str>,
+) -> Option<GeminiTranslationProvider> {
+    let api = env_var_or_default(GEMINI_API, DEFAULT_GEMINI_API.to_owned());
+    let model = GeminiModel::try_from(match model {
+        Some(model) => model.to_owned(),
+        None => require_env_var(GEMINI_MODEL).ok()?,
+    })
+    .ok()?;
+    let api_key = require_env_var(GEMINI_API_KEY).ok()?;
+    let max_parallel_requests = require_env_var(GEMINI_MAX_PARALLEL_REQUESTS)
+        .ok()?
+        .parse::<usize>()
+        .ok()?;
+
+    let client = GeminiClient::try_new(api, model, api_key, max_parallel_requests).ok()?;
+    Some(GeminiTranslationProvider::new(client))
+}
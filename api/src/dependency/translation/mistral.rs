@@ -0,0 +1,31 @@
+use env::{env_var_or_default, require_env_var};
+use mistral::client::MistralClient;
+use mistral::model::MistralModel;
+use translation::mistral::MistralTranslationProvider;
+
+pub const MISTRAL_API: &str = "MISTRAL_API";
+pub const MISTRAL_MODEL: &str = "MISTRAL_MODEL";
+pub const MISTRAL_API_KEY: &str = "MISTRAL_API_KEY";
+pub const MISTRAL_MAX_PARALLEL_REQUESTS: &str = "MISTRAL_MAX_PARALLEL_REQUESTS";
+
+const DEFAULT_MISTRAL_API: &str = "https://api.mistral.ai/v1";
+
+/// Uses `model` instead of the configured model when it's given, such as for a cascade tier.
+pub fn maybe_create_mistral_translation_provider(
+    model: Option<&str>,
+) -> Option<MistralTranslationProvider> {
+    let api = env_var_or_default(MISTRAL_API, DEFAULT_MISTRAL_API.to_owned());
+    let model = MistralModel::try_from(match model {
+        Some(model) => model.to_owned(),
+        None => require_env_var(MISTRAL_MODEL).ok()?,
+    })
+    .ok()?;
+    let api_key = require_env_var(MISTRAL_API_KEY).ok()?;
+    let max_parallel_requests = require_env_var(MISTRAL_MAX_PARALLEL_REQUESTS)
+        .ok()?
+        .parse::<usize>()
+        .ok()?;
+
+    let client = MistralClient::try_new(api, model, api_key, max_parallel_requests).ok()?;
+    Some(MistralTranslationProvider::new(client))
+}
@@ -0,0 +1,53 @@
+use env::{env_var_or_default, require_env_var};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use translation::mock::{MockFault, MockFaultInjection, MockMode, MockTranslationProvider};
+
+pub const MOCK_MODE: &str = "MOCK_MODE";
+pub const MOCK_DICTIONARY_PATH: &str = "MOCK_DICTIONARY_PATH";
+pub const MOCK_LATENCY_MILLISECONDS: &str = "MOCK_LATENCY_MILLISECONDS";
+pub const MOCK_ERROR_RATE: &str = "MOCK_ERROR_RATE";
+pub const MOCK_ERRORS: &str = "MOCK_ERRORS";
+pub const MOCK_HEALTHY: &str = "MOCK_HEALTHY";
+
+/// The mock, when `MOCK_MODE` is set. Its config is never silently ignored, as that would
+/// fall through to a paid provider.
+pub fn maybe_create_mock_translation_provider() -> Option<MockTranslationProvider> {
+    let mode = match require_env_var(MOCK_MODE).ok()?.as_str() {
+        "echo" => MockMode::Echo,
+        "pseudolocalize" => MockMode::Pseudolocalize,
+        "dictionary" => {
+            let path =
+                require_env_var(MOCK_DICTIONARY_PATH).expect("Missing mock dictionary path.");
+            MockMode::dictionary_from_file(Path::new(&path)).expect("Invalid mock dictionary.")
+        }
+        _ => panic!("Invalid mock mode."),
+    };
+
+    let latency = env_var_or_default(MOCK_LATENCY_MILLISECONDS, "0".to_owned())
+        .parse::<u64>()
+        .expect("Invalid mock latency.");
+    let error_rate = env_var_or_default(MOCK_ERROR_RATE, "0".to_owned())
+        .parse::<f64>()
+        .ok()
+        .filter(|error_rate| (0.0..=1.0).contains(error_rate))
+        .expect("Invalid mock error rate.");
+    let faults = env_var_or_default(MOCK_ERRORS, "".to_owned())
+        .split(',')
+        .map(str::trim)
+        .filter(|fault| !fault.is_empty())
+        .map(MockFault::from_str)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Invalid mock errors.");
+    let healthy = env_var_or_default(MOCK_HEALTHY, "true".to_owned())
+        .parse::<bool>()
+        .expect("Invalid mock healthy.");
+
+    let fault_injection = MockFaultInjection {
+        latency: Duration::from_millis(latency),
+        error_rate,
+        faults,
+    };
+    Some(MockTranslationProvider::new(mode, fault_injection, healthy))
+}
@@ -1,54 +1,655 @@
-use crate::dependency::translation::claude::maybe_create_claude_translation_provider;
+use crate::dependency::ensemble::maybe_create_ensemble;
+use crate::dependency::memory::maybe_create_translation_memory;
+use crate::dependency::quality::maybe_create_quality_estimator;
+use crate::dependency::segment::create_segmenter;
+use crate::dependency::translation::cascade::maybe_create_cascade;
+use crate::dependency::translation::claude::{
+    maybe_create_claude_translation_provider, CLAUDE_MODEL,
+};
 use crate::dependency::translation::deepl::maybe_create_deepl_translation_provider;
-use crate::dependency::translation::openai::maybe_create_openai_translation_provider;
+use crate::dependency::translation::gemini::{
+    maybe_create_gemini_translation_provider, GEMINI_MODEL,
+};
+use crate::dependency::translation::mistral::{
+    maybe_create_mistral_translation_provider, MISTRAL_MODEL,
+};
+use crate::dependency::translation::mock::maybe_create_mock_translation_provider;
+use crate::dependency::translation::openai::{
+    maybe_create_openai_translation_provider, OPENAI_MODEL,
+};
 use async_trait::async_trait;
+use env::require_env_var;
+use futures::future::{join, join_all};
+use serde::Serialize;
+use std::cell::OnceCell;
+use translation::alternatives::AlternativeTranslation;
+use translation::cascade::Cascade;
 use translation::claude::ClaudeTranslationProvider;
 use translation::deepl::DeepLTranslationProvider;
+use translation::document::translate_document;
+use translation::ensemble::{Ensemble, EnsembleCandidate, EnsembleJudge, EnsembleJudgement};
+use translation::gemini::GeminiTranslationProvider;
+use translation::memory::TranslationMemory;
+use translation::mistral::MistralTranslationProvider;
+use translation::mock::MockTranslationProvider;
+use translation::multi::MultiTargetTranslation;
 use translation::openai::OpenAITranslationProvider;
+use translation::quality::{
+    QualityEstimate, QualityEstimationMethod, QualityEstimator, QualityJudge,
+};
+use translation::segment::Segmenter;
+use translation::stream::{StreamingTranslation, TranslationEvents};
 use translation::{
     HealthCheck, Translation, TranslationError, TranslationInput, TranslationOutput,
     TranslationProvider,
 };
 
+pub mod cascade;
 pub mod claude;
 pub mod deepl;
+pub mod gemini;
+pub mod mistral;
+pub mod mock;
 pub mod openai;
 
 pub fn get_first_configured_translator() -> Option<Translator> {
-    let claude = maybe_create_claude_translation_provider().map(Translator::Claude);
-    let deepl = maybe_create_deepl_translation_provider().map(Translator::DeepL);
-    let openai = maybe_create_openai_translation_provider().map(Translator::OpenAI);
-    claude.or(deepl).or(openai)
+    let providers = Providers::default();
+    let provider = get_first_configured_provider(&providers)?;
+    Some(Translator {
+        quality: maybe_create_quality_estimator(&providers, &provider),
+        provider,
+        memory: maybe_create_translation_memory(),
+        ensemble: maybe_create_ensemble(&providers),
+        segmenter: create_segmenter(),
+    })
+}
+
+fn get_first_configured_provider(providers: &Providers) -> Option<Provider> {
+    // the mock is opt-in and must never fall through to a paid provider
+    if let Some(mock) = maybe_create_mock_translation_provider() {
+        return Some(Provider::Mock(mock));
+    }
+
+    if let Some(cascade) = maybe_create_cascade(providers) {
+        return Some(Provider::Cascade(cascade));
+    }
+
+    ["claude", "deepl", "gemini", "mistral", "openai"]
+        .into_iter()
+        .find_map(|name| providers.get(name))
+}
+
+/// The configured providers, each created once so the translator, its cascade tiers, quality
+/// estimator and ensemble share the limit on parallel requests of a provider.
+#[derive(Default)]
+pub struct Providers {
+    claude: OnceCell<Option<ClaudeTranslationProvider>>,
+    deepl: OnceCell<Option<DeepLTranslationProvider>>,
+    gemini: OnceCell<Option<GeminiTranslationProvider>>,
+    mistral: OnceCell<Option<MistralTranslationProvider>>,
+    openai: OnceCell<Option<OpenAITranslationProvider>>,
+}
+
+impl Providers {
+    /// The provider called `name`, if it's configured. LLM providers can be named along with a
+    /// model to use instead of the configured one, such as `openai:gpt-4o`.
+    pub fn get(&self, name: &str) -> Option<Provider> {
+        let (name, model) = match name.split_once(':') {
+            Some((name, model)) => (name, Some(model)),
+            None => (name, None),
+        };
+        match (name, model) {
+            ("claude", _) => get_with_model(
+                &self.claude,
+                model,
+                CLAUDE_MODEL,
+                maybe_create_claude_translation_provider,
+                ClaudeTranslationProvider::with_model,
+            )
+            .map(Provider::Claude),
+            ("deepl", None) => self
+                .deepl
+                .get_or_init(maybe_create_deepl_translation_provider)
+                .clone()
+                .map(Provider::DeepL),
+            ("gemini", _) => get_with_model(
+                &self.gemini,
+                model,
+                GEMINI_MODEL,
+                maybe_create_gemini_translation_provider,
+                GeminiTranslationProvider::with_model,
+            )
+            .map(Provider::Gemini),
+            ("mistral", _) => get_with_model(
+                &self.mistral,
+                model,
+                MISTRAL_MODEL,
+                maybe_create_mistral_translation_provider,
+                MistralTranslationProvider::with_model,
+            )
+            .map(Provider::Mistral),
+            // the mock has no parallel request limit to share
+            ("mock", None) => maybe_create_mock_translation_provider().map(Provider::Mock),
+            ("openai", _) => get_with_model(
+                &self.openai,
+                model,
+                OPENAI_MODEL,
+                maybe_create_openai_translation_provider,
+                OpenAITranslationProvider::with_model,
+            )
+            .map(Provider::OpenAI),
+            _ => None,
+        }
+    }
+}
+
+/// The LLM provider in `cell` with `model`, or the model configured by `model_var` when none is
+/// given. The cell is only filled once the model is known to be valid, and the model is applied
+/// on each call, so every model shares the cell's limit on parallel requests.
+fn get_with_model<P, M: TryFrom<String>>(
+    cell: &OnceCell<Option<P>>,
+    model: Option<&str>,
+    model_var: &str,
+    create: impl FnOnce(Option<&str>) -> Option<P>,
+    with_model: impl FnOnce(&P, M) -> P,
+) -> Option<P> {
+    let model = match model {
+        Some(model) => model.to_owned(),
+        None => require_env_var(model_var).ok()?,
+    };
+    let parsed = M::try_from(model.clone()).ok()?;
+    let provider = cell.get_or_init(|| create(Some(&model))).as_ref()?;
+    Some(with_model(provider, parsed))
+}
+
+pub struct Translator {
+    pub provider: Provider,
+    pub memory: Option<TranslationMemory>,
+    pub quality: Option<QualityEstimator<Provider>>,
+    pub ensemble: Option<Ensemble<Provider>>,
+    pub segmenter: Segmenter,
+}
+
+impl Translation for Translator {
+    /// Long texts are split into segments, which the memory keeps separately so the unchanged
+    /// paragraphs of an edited document are reused.
+    async fn translate(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let (inputs, segmentation) = self.segmenter.split(inputs);
+        let outputs = match &self.memory {
+            Some(memory) => {
+                memory
+                    .translate_with(&self.provider, self.provider.name(), inputs)
+                    .await
+            }
+            None => self.provider.translate(inputs).await,
+        };
+        segmentation.join(outputs)
+    }
+}
+
+/// What the configured provider can do beyond translating.
+#[derive(Clone, Debug, Serialize)]
+pub struct Capabilities {
+    pub provider: &'static str,
+    pub alternatives: bool,
+    /// The tiers of a cascade, from the cheapest.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality_estimation: Option<QualityEstimationMethod>,
+    /// The providers of an ensemble that can be asked for.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ensemble: Vec<String>,
+}
+
+impl Translator {
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            provider: self.provider.name(),
+            alternatives: self.provider.supports_alternatives(),
+            tiers: match &self.provider {
+                Provider::Cascade(cascade) => cascade
+                    .tier_names()
+                    .into_iter()
+                    .map(str::to_owned)
+                    .collect(),
+                _ => Vec::new(),
+            },
+            quality_estimation: self.quality.as_ref().map(QualityEstimator::method),
+            ensemble: self
+                .ensemble
+                .iter()
+                .flat_map(Ensemble::member_names)
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    /// Translate `inputs` with each provider of the ensemble, choosing between their
+    /// translations with its judge. The memory isn't used as the ensemble is asked for.
+    pub async fn translate_ensemble(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        match &self.ensemble {
+            Some(ensemble) => self.segmenter.translate(ensemble, inputs).await,
+            None => self.translate(inputs).await,
+        }
+    }
+
+    /// Add a quality estimate to each of `outputs`, the translations of `inputs`, when quality
+    /// estimation is configured.
+    pub async fn estimate_quality(
+        &self,
+        inputs: &[TranslationInput],
+        outputs: Vec<TranslationOutput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        match &self.quality {
+            Some(quality) => quality.estimate(inputs, outputs).await,
+            None => Ok(outputs),
+        }
+    }
+
+    /// Translate `inputs` as the consecutive segments of one document, see
+    /// [translate_document].
+    pub async fn translate_document(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        match self.provider {
+            // DeepL doesn't read neighbouring segments, so keep translating them in batches
+            Provider::DeepL(_) => self.translate(inputs).await,
+            _ => translate_document(self, inputs).await,
+        }
+    }
 }
 
-pub enum Translator {
+impl StreamingTranslation for Translator {
+    /// Long texts are translated in segments like with [Translation::translate], and their
+    /// output is sent once the segments are joined.
+    async fn translate_streaming(
+        &self,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Vec<Option<TranslationOutput>> {
+        let count = inputs.len();
+        let (segmented, whole): (Vec<_>, Vec<_>) = inputs
+            .into_iter()
+            .enumerate()
+            .partition(|(_, input)| self.segmenter.splits(input));
+        let (whole_indexes, whole): (Vec<_>, Vec<_>) = whole.into_iter().unzip();
+        let (whole_outputs, segmented_outputs) = join(
+            self.translate_whole_streaming(whole, &events.for_indexes(whole_indexes.clone())),
+            join_all(segmented.into_iter().map(|(index, input)| async move {
+                let output = match self.translate(vec![input]).await {
+                    Ok(mut outputs) => outputs.pop(),
+                    Err(error) => {
+                        events.error(index, &error).await;
+                        None
+                    }
+                };
+                if let Some(output) = &output {
+                    events.output(index, output).await;
+                }
+                (index, output)
+            })),
+        )
+        .await;
+
+        let mut outputs = vec![None; count];
+        for (index, output) in whole_indexes
+            .into_iter()
+            .zip(whole_outputs)
+            .chain(segmented_outputs)
+        {
+            outputs[index] = output;
+        }
+        outputs
+    }
+}
+
+impl Translator {
+    /// Stream the translations of `inputs`, none of which are split into segments.
+    async fn translate_whole_streaming(
+        &self,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Vec<Option<TranslationOutput>> {
+        let Some(memory) = &self.memory else {
+            return self.provider.translate_streaming(inputs, events).await;
+        };
+        let count = inputs.len();
+        match memory
+            .translate_streaming_with(&self.provider, self.provider.name(), inputs, events)
+            .await
+        {
+            Ok(outputs) => outputs,
+            Err(error) => {
+                for index in 0..count {
+                    events.error(index, &error).await;
+                }
+                vec![None; count]
+            }
+        }
+    }
+}
+
+// candidates come straight from the provider as the memory only has one translation of each
+impl AlternativeTranslation for Translator {
+    fn supports_alternatives(&self) -> bool {
+        self.provider.supports_alternatives()
+    }
+
+    /// Long texts are translated in segments, each with its own alternatives, so their joined
+    /// translation has none.
+    async fn translate_alternatives(
+        &self,
+        inputs: Vec<TranslationInput>,
+        count: usize,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let (inputs, segmentation) = self.segmenter.split(inputs);
+        segmentation.join(self.provider.translate_alternatives(inputs, count).await)
+    }
+}
+
+impl MultiTargetTranslation for Translator {
+    async fn translate_targets(
+        &self,
+        groups: Vec<Vec<TranslationInput>>,
+    ) -> Result<Vec<Vec<TranslationOutput>>, TranslationError> {
+        // texts long enough to be split are translated into one language at a time
+        let (segmented, whole): (Vec<_>, Vec<_>) = groups
+            .into_iter()
+            .enumerate()
+            .partition(|(_, inputs)| inputs.iter().any(|input| self.segmenter.splits(input)));
+        let (whole_indexes, whole): (Vec<_>, Vec<_>) = whole.into_iter().unzip();
+        let whole_outputs = match &self.memory {
+            Some(memory) => {
+                memory
+                    .translate_targets_with(&self.provider, self.provider.name(), whole)
+                    .await?
+            }
+            None => self.provider.translate_targets(whole).await?,
+        };
+        let segmented_outputs = join_all(
+            segmented
+                .iter()
+                .map(|(_, inputs)| self.translate(inputs.clone())),
+        )
+        .await;
+
+        let mut outputs = vec![Vec::new(); whole_indexes.len() + segmented.len()];
+        for (index, group_outputs) in whole_indexes.into_iter().zip(whole_outputs) {
+            outputs[index] = group_outputs;
+        }
+        for ((index, _), group_outputs) in segmented.into_iter().zip(segmented_outputs) {
+            outputs[index] = group_outputs?;
+        }
+        Ok(outputs)
+    }
+}
+
+#[async_trait]
+impl HealthCheck for Translator {
+    async fn is_healthy(&self) -> Option<bool> {
+        self.provider.is_healthy().await
+    }
+}
+
+impl TranslationProvider for Translator {}
+
+pub enum Provider {
+    Cascade(Cascade<Provider>),
     Claude(ClaudeTranslationProvider),
     DeepL(DeepLTranslationProvider),
+    Gemini(GeminiTranslationProvider),
+    Mistral(MistralTranslationProvider),
+    Mock(MockTranslationProvider),
     OpenAI(OpenAITranslationProvider),
 }
 
-impl Translation for Translator {
+impl Provider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Provider::Cascade(_) => "cascade",
+            Provider::Claude(_) => "claude",
+            Provider::DeepL(_) => "deepl",
+            Provider::Gemini(_) => "gemini",
+            Provider::Mistral(_) => "mistral",
+            Provider::Mock(_) => "mock",
+            Provider::OpenAI(_) => "openai",
+        }
+    }
+}
+
+impl Translation for Provider {
     async fn translate(
         &self,
         inputs: Vec<TranslationInput>,
     ) -> Result<Vec<TranslationOutput>, TranslationError> {
         match self {
-            Translator::Claude(claude) => claude.translate(inputs).await,
-            Translator::DeepL(deepl) => deepl.translate(inputs).await,
-            Translator::OpenAI(openai) => openai.translate(inputs).await,
+            // the tiers of a cascade are providers themselves
+            Provider::Cascade(cascade) => Box::pin(cascade.translate(inputs)).await,
+            Provider::Claude(claude) => claude.translate(inputs).await,
+            Provider::DeepL(deepl) => deepl.translate(inputs).await,
+            Provider::Gemini(gemini) => gemini.translate(inputs).await,
+            Provider::Mistral(mistral) => mistral.translate(inputs).await,
+            Provider::Mock(mock) => mock.translate(inputs).await,
+            Provider::OpenAI(openai) => openai.translate(inputs).await,
+        }
+    }
+}
+
+impl StreamingTranslation for Provider {
+    async fn translate_streaming(
+        &self,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Vec<Option<TranslationOutput>> {
+        match self {
+            Provider::Cascade(cascade) => cascade.translate_streaming(inputs, events).await,
+            Provider::Claude(claude) => claude.translate_streaming(inputs, events).await,
+            Provider::DeepL(deepl) => deepl.translate_streaming(inputs, events).await,
+            Provider::Gemini(gemini) => gemini.translate_streaming(inputs, events).await,
+            Provider::Mistral(mistral) => mistral.translate_streaming(inputs, events).await,
+            Provider::Mock(mock) => mock.translate_streaming(inputs, events).await,
+            Provider::OpenAI(openai) => openai.translate_streaming(inputs, events).await,
+        }
+    }
+}
+
+impl AlternativeTranslation for Provider {
+    fn supports_alternatives(&self) -> bool {
+        match self {
+            Provider::Cascade(cascade) => cascade.supports_alternatives(),
+            Provider::Claude(claude) => claude.supports_alternatives(),
+            Provider::DeepL(deepl) => deepl.supports_alternatives(),
+            Provider::Gemini(gemini) => gemini.supports_alternatives(),
+            Provider::Mistral(mistral) => mistral.supports_alternatives(),
+            Provider::Mock(mock) => mock.supports_alternatives(),
+            Provider::OpenAI(openai) => openai.supports_alternatives(),
+        }
+    }
+
+    async fn translate_alternatives(
+        &self,
+        inputs: Vec<TranslationInput>,
+        count: usize,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        match self {
+            Provider::Cascade(cascade) => cascade.translate_alternatives(inputs, count).await,
+            Provider::Claude(claude) => claude.translate_alternatives(inputs, count).await,
+            Provider::DeepL(deepl) => deepl.translate_alternatives(inputs, count).await,
+            Provider::Gemini(gemini) => gemini.translate_alternatives(inputs, count).await,
+            Provider::Mistral(mistral) => mistral.translate_alternatives(inputs, count).await,
+            Provider::Mock(mock) => mock.translate_alternatives(inputs, count).await,
+            Provider::OpenAI(openai) => openai.translate_alternatives(inputs, count).await,
+        }
+    }
+}
+
+impl QualityJudge for Provider {
+    fn supports_quality_judging(&self) -> bool {
+        match self {
+            Provider::Cascade(cascade) => cascade.supports_quality_judging(),
+            Provider::Claude(claude) => claude.supports_quality_judging(),
+            Provider::DeepL(deepl) => deepl.supports_quality_judging(),
+            Provider::Gemini(gemini) => gemini.supports_quality_judging(),
+            Provider::Mistral(mistral) => mistral.supports_quality_judging(),
+            Provider::Mock(mock) => mock.supports_quality_judging(),
+            Provider::OpenAI(openai) => openai.supports_quality_judging(),
+        }
+    }
+
+    async fn judge_quality(
+        &self,
+        inputs: &[TranslationInput],
+        outputs: &[TranslationOutput],
+    ) -> Result<Vec<Option<QualityEstimate>>, TranslationError> {
+        match self {
+            Provider::Cascade(cascade) => cascade.judge_quality(inputs, outputs).await,
+            Provider::Claude(claude) => claude.judge_quality(inputs, outputs).await,
+            Provider::DeepL(deepl) => deepl.judge_quality(inputs, outputs).await,
+            Provider::Gemini(gemini) => gemini.judge_quality(inputs, outputs).await,
+            Provider::Mistral(mistral) => mistral.judge_quality(inputs, outputs).await,
+            Provider::Mock(mock) => mock.judge_quality(inputs, outputs).await,
+            Provider::OpenAI(openai) => openai.judge_quality(inputs, outputs).await,
+        }
+    }
+}
+
+impl EnsembleJudge for Provider {
+    fn supports_ensemble_judging(&self) -> bool {
+        match self {
+            Provider::Cascade(cascade) => cascade.supports_ensemble_judging(),
+            Provider::Claude(claude) => claude.supports_ensemble_judging(),
+            Provider::DeepL(deepl) => deepl.supports_ensemble_judging(),
+            Provider::Gemini(gemini) => gemini.supports_ensemble_judging(),
+            Provider::Mistral(mistral) => mistral.supports_ensemble_judging(),
+            Provider::Mock(mock) => mock.supports_ensemble_judging(),
+            Provider::OpenAI(openai) => openai.supports_ensemble_judging(),
+        }
+    }
+
+    async fn judge_candidates(
+        &self,
+        inputs: &[TranslationInput],
+        candidates: &[Vec<EnsembleCandidate>],
+    ) -> Result<Vec<Option<EnsembleJudgement>>, TranslationError> {
+        match self {
+            Provider::Cascade(cascade) => cascade.judge_candidates(inputs, candidates).await,
+            Provider::Claude(claude) => claude.judge_candidates(inputs, candidates).await,
+            Provider::DeepL(deepl) => deepl.judge_candidates(inputs, candidates).await,
+            Provider::Gemini(gemini) => gemini.judge_candidates(inputs, candidates).await,
+            Provider::Mistral(mistral) => mistral.judge_candidates(inputs, candidates).await,
+            Provider::Mock(mock) => mock.judge_candidates(inputs, candidates).await,
+            Provider::OpenAI(openai) => openai.judge_candidates(inputs, candidates).await,
+        }
+    }
+}
+
+impl MultiTargetTranslation for Provider {
+    async fn translate_targets(
+        &self,
+        groups: Vec<Vec<TranslationInput>>,
+    ) -> Result<Vec<Vec<TranslationOutput>>, TranslationError> {
+        match self {
+            Provider::Cascade(cascade) => cascade.translate_targets(groups).await,
+            Provider::Claude(claude) => claude.translate_targets(groups).await,
+            Provider::DeepL(deepl) => deepl.translate_targets(groups).await,
+            Provider::Gemini(gemini) => gemini.translate_targets(groups).await,
+            Provider::Mistral(mistral) => mistral.translate_targets(groups).await,
+            Provider::Mock(mock) => mock.translate_targets(groups).await,
+            Provider::OpenAI(openai) => openai.translate_targets(groups).await,
         }
     }
 }
 
 #[async_trait]
-impl HealthCheck for Translator {
+impl HealthCheck for Provider {
     async fn is_healthy(&self) -> Option<bool> {
         match self {
-            Translator::Claude(claude) => claude.is_healthy().await,
-            Translator::DeepL(deepl) => deepl.is_healthy().await,
-            Translator::OpenAI(openai) => openai.is_healthy().await,
+            Provider::Cascade(cascade) => cascade.is_healthy().await,
+            Provider::Claude(claude) => claude.is_healthy().await,
+            Provider::DeepL(deepl) => deepl.is_healthy().await,
+            Provider::Gemini(gemini) => gemini.is_healthy().await,
+            Provider::Mistral(mistral) => mistral.is_healthy().await,
+            Provider::Mock(mock) => mock.is_healthy().await,
+            Provider::OpenAI(openai) => openai.is_healthy().await,
         }
     }
 }
 
-impl TranslationProvider for Translator {}
+impl TranslationProvider for Provider {}
+
+#[cfg(test)]
+mod tests {
+    use crate::dependency::translation::{Provider, Translator};
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use tokio::sync::mpsc::channel;
+    use translation::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use translation::segment::Segmenter;
+    use translation::stream::{StreamingTranslation, TranslationEvent, TranslationEvents};
+    use translation::{Language, TranslationInput};
+
+    #[actix_rt::test]
+    async fn it_should_stream_long_texts_in_segments() {
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            HashMap::from([
+                ("First one.".to_owned(), "Premier.".to_owned()),
+                ("Second one.".to_owned(), "Deuxième.".to_owned()),
+                ("Short.".to_owned(), "Court.".to_owned()),
+            ]),
+        )]);
+        let translator = Translator {
+            provider: Provider::Mock(MockTranslationProvider::new(
+                MockMode::Dictionary(dictionary),
+                MockFaultInjection::default(),
+                true,
+            )),
+            memory: None,
+            quality: None,
+            ensemble: None,
+            segmenter: Segmenter::new(12),
+        };
+        let inputs = ["First one.\n\nSecond one.", "Short."]
+            .map(|text| {
+                TranslationInput::new(
+                    text.to_owned(),
+                    Some(Language::from_str("en").unwrap()),
+                    Language::from_str("fr").unwrap(),
+                )
+            })
+            .to_vec();
+
+        let (sender, mut receiver) = channel(16);
+        let outputs = translator
+            .translate_streaming(inputs, &TranslationEvents::new(sender))
+            .await;
+
+        let texts = outputs
+            .iter()
+            .map(|output| output.as_ref().unwrap().text())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["Premier.\n\nDeuxième.", "Court."]);
+        let mut events = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|event| match event {
+                TranslationEvent::Output { index, output } => (index, output.text().to_owned()),
+                _ => panic!("Unexpected event {event:?}"),
+            })
+            .collect::<Vec<_>>();
+        events.sort();
+        assert_eq!(
+            events,
+            vec![
+                (0, "Premier.\n\nDeuxième.".to_owned()),
+                (1, "Court.".to_owned())
+            ]
+        );
+    }
+}
@@ -7,8 +7,15 @@ pub const OPENAI_MODEL: &str = "OPENAI_MODEL";
 pub const OPENAI_API_KEY: &str = "OPENAI_API_KEY";
 pub const OPENAI_MAX_PARALLEL_REQUESTS: &str = "OPENAI_MAX_PARALLEL_REQUESTS";
 
-pub fn maybe_create_openai_translation_provider() -> Option<OpenAITranslationProvider> {
-    let model = OpenAIModel::try_from(require_env_var(OPENAI_MODEL).ok()?).ok()?;
+/// Uses `model` instead of the configured model when it's given, such as for a cascade tier.
+pub fn maybe_create_openai_translation_provider(
+    model: Option<&str>,
+) -> Option<OpenAITranslationProvider> {
+    let model = OpenAIModel::try_from(match model {
+        Some(model) => model.to_owned(),
+        None => require_env_var(OPENAI_MODEL).ok()?,
+    })
+    .ok()?;
     let api_key = require_env_var(OPENAI_API_KEY).ok()?;
     let max_parallel_requests = require_env_var(OPENAI_MAX_PARALLEL_REQUESTS)
         .ok()?
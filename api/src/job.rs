@@ -0,0 +1,287 @@
+use crate::dependency::translation::Translator;
+use crate::router::csv::{parse_csv, CsvQuery, CsvRouteError};
+use crate::router::json::JsonResourceRequest;
+use crate::webhook::WebhookClient;
+use formats::android::AndroidStrings;
+use formats::apple::{AppleStringCatalog, AppleStrings, AppleStringsDict};
+use formats::error::FormatError;
+use formats::json::JsonResource;
+use formats::po::PoCatalog;
+use formats::resource::{ResourceFormat, ResourceTranslation, TranslatedResource};
+use formats::spreadsheet::CsvTranslation;
+use formats::subtitles::{SubtitleOptions, Subtitles};
+use formats::xliff::XliffDocument;
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use translation::job::{JobError, JobStatus, JobStore};
+use translation::{Language, TranslationInput, TranslationOutput};
+
+#[derive(Error, Debug)]
+pub enum JobDocumentError {
+    #[error("FormatError: {0}")]
+    FormatError(#[from] FormatError),
+    #[error("SerdeJsonError: {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("CsvError: {0}")]
+    CsvError(#[from] CsvRouteError),
+    #[error("Missing {0}")]
+    MissingParameter(&'static str),
+}
+
+#[derive(Error, Debug)]
+pub enum JobRunError {
+    #[error("JobError: {0}")]
+    JobError(#[from] JobError),
+    #[error("JobDocumentError: {0}")]
+    JobDocumentError(#[from] JobDocumentError),
+}
+
+/// The file formats of the `/translate/...` routes.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobFormat {
+    Xliff,
+    Po,
+    Json,
+    Android,
+    Strings,
+    StringsDict,
+    Xcstrings,
+    Subtitles,
+    Csv,
+}
+
+/// The query of a `POST /jobs` request. Without a format the body is a batch of inputs, like
+/// `POST /translate`, otherwise it's a file with the parameters of the format's route.
+#[derive(Deserialize, Serialize)]
+pub struct JobQuery {
+    pub format: Option<JobFormat>,
+    pub source: Option<Language>,
+    pub target: Option<Language>,
+    pub column: Option<String>,
+    pub targets: Option<String>,
+    pub max_line_length: Option<usize>,
+    /// URL to POST the job to once it completes or fails.
+    pub callback: Option<String>,
+}
+
+/// Everything needed to run a job again after a restart.
+#[derive(Deserialize, Serialize)]
+pub struct JobSpec {
+    pub query: JobQuery,
+    pub body: String,
+}
+
+pub enum JobDocument {
+    Batch(Vec<TranslationInput>),
+    Xliff(XliffDocument),
+    Po(PoCatalog),
+    Json(JsonResource),
+    Android(ResourceTranslation<AndroidStrings>),
+    Strings(ResourceTranslation<AppleStrings>),
+    StringsDict(ResourceTranslation<AppleStringsDict>),
+    StringCatalog(ResourceTranslation<AppleStringCatalog>),
+    Subtitles(Subtitles, SubtitleOptions),
+    Csv(CsvTranslation),
+}
+
+fn resource_result<Format: ResourceFormat>(
+    translation: &ResourceTranslation<Format>,
+    outputs: &[TranslationOutput],
+) -> Value {
+    let TranslatedResource {
+        document,
+        untranslated,
+    } = translation.apply(outputs);
+    json!({ "document": document, "untranslated": untranslated })
+}
+
+impl JobDocument {
+    pub fn parse(spec: &JobSpec) -> Result<Self, JobDocumentError> {
+        let query = &spec.query;
+        let body = &spec.body;
+        let target = || {
+            query
+                .target
+                .clone()
+                .ok_or(JobDocumentError::MissingParameter("target"))
+        };
+        Ok(match query.format {
+            None => JobDocument::Batch(serde_json::from_str(body)?),
+            Some(JobFormat::Xliff) => {
+                JobDocument::Xliff(XliffDocument::parse(body.clone(), target()?)?)
+            }
+            Some(JobFormat::Po) => JobDocument::Po(PoCatalog::parse(body, target()?)?),
+            Some(JobFormat::Json) => {
+                let request = serde_json::from_str::<JsonResourceRequest>(body)?;
+                JobDocument::Json(JsonResource::new(
+                    request.resource,
+                    request.existing,
+                    target()?,
+                ))
+            }
+            Some(JobFormat::Android) => JobDocument::Android(ResourceTranslation::new(
+                AndroidStrings::parse(body)?,
+                target()?,
+            )),
+            Some(JobFormat::Strings) => JobDocument::Strings(ResourceTranslation::new(
+                AppleStrings::parse(body)?,
+                target()?,
+            )),
+            Some(JobFormat::StringsDict) => JobDocument::StringsDict(ResourceTranslation::new(
+                AppleStringsDict::parse(body)?,
+                target()?,
+            )),
+            Some(JobFormat::Xcstrings) => {
+                let target = target()?;
+                JobDocument::StringCatalog(ResourceTranslation::new(
+                    AppleStringCatalog::parse(body, &target)?,
+                    target,
+                ))
+            }
+            Some(JobFormat::Subtitles) => JobDocument::Subtitles(
+                Subtitles::parse(body, target()?)?,
+                SubtitleOptions {
+                    max_line_length: query.max_line_length,
+                },
+            ),
+            Some(JobFormat::Csv) => JobDocument::Csv(parse_csv(
+                body,
+                CsvQuery {
+                    column: query
+                        .column
+                        .clone()
+                        .ok_or(JobDocumentError::MissingParameter("column"))?,
+                    source: query.source.clone(),
+                    targets: query
+                        .targets
+                        .clone()
+                        .ok_or(JobDocumentError::MissingParameter("targets"))?,
+                },
+            )?),
+        })
+    }
+
+    pub fn inputs(&self, source: Option<Language>) -> Vec<TranslationInput> {
+        match self {
+            JobDocument::Batch(inputs) => inputs.clone(),
+            JobDocument::Xliff(document) => document.inputs(source),
+            JobDocument::Po(catalog) => catalog.inputs(source),
+            JobDocument::Json(resource) => resource.inputs(source),
+            JobDocument::Android(translation) => translation.inputs(source),
+            JobDocument::Strings(translation) => translation.inputs(source),
+            JobDocument::StringsDict(translation) => translation.inputs(source),
+            JobDocument::StringCatalog(translation) => translation.inputs(source),
+            JobDocument::Subtitles(subtitles, _) => subtitles.inputs(source),
+            JobDocument::Csv(translation) => translation.inputs(),
+        }
+    }
+
+    /// The result of a job, the outputs of a batch or the translated file and, for formats
+    /// that leave out broken translations, how many were left out.
+    pub fn apply(self, outputs: &[TranslationOutput]) -> Result<Value, JobDocumentError> {
+        Ok(match self {
+            JobDocument::Batch(_) => serde_json::to_value(outputs)?,
+            JobDocument::Xliff(document) => {
+                let translated = document.apply(outputs);
+                json!({ "document": translated.document, "untranslated": translated.untranslated })
+            }
+            JobDocument::Po(catalog) => json!({ "document": catalog.apply(outputs) }),
+            JobDocument::Json(resource) => {
+                let translated = resource.apply(outputs);
+                json!({ "document": translated.resource, "untranslated": translated.untranslated })
+            }
+            JobDocument::Android(translation) => resource_result(&translation, outputs),
+            JobDocument::Strings(translation) => resource_result(&translation, outputs),
+            JobDocument::StringsDict(translation) => resource_result(&translation, outputs),
+            JobDocument::StringCatalog(translation) => resource_result(&translation, outputs),
+            JobDocument::Subtitles(subtitles, options) => {
+                json!({ "document": subtitles.apply(outputs, &options) })
+            }
+            JobDocument::Csv(translation) => json!({ "document": translation.apply(outputs)? }),
+        })
+    }
+}
+
+/// Runs jobs in the background, at most as many at once as there are workers. Jobs share the
+/// translator, and so its limits on parallel requests, with the other routes.
+pub struct JobQueue {
+    pub store: JobStore<JobSpec>,
+    pub webhook: Option<WebhookClient>,
+    workers: Semaphore,
+}
+
+impl JobQueue {
+    pub fn new(store: JobStore<JobSpec>, webhook: Option<WebhookClient>, workers: usize) -> Self {
+        JobQueue {
+            store,
+            webhook,
+            workers: Semaphore::new(workers),
+        }
+    }
+
+    /// Run a job once a worker is free, in the order jobs were queued.
+    pub fn spawn(self: &Arc<Self>, translator: Arc<Translator>, id: String) {
+        let queue = self.clone();
+        actix_web::rt::spawn(async move {
+            {
+                let Ok(_worker) = queue.workers.acquire().await else {
+                    return;
+                };
+                if let Err(run_error) = queue.run(&translator, &id).await {
+                    error!("Job {id} failed: {run_error}");
+                    if let Err(error) = queue.store.fail(&id, run_error.to_string()).await {
+                        error!("Failed to record failure of job {id}: {error}");
+                    }
+                }
+            }
+            queue.notify(&id).await;
+        });
+    }
+
+    async fn run(&self, translator: &Translator, id: &str) -> Result<(), JobRunError> {
+        let spec = self.store.spec(id)?;
+        let document = JobDocument::parse(&spec)?;
+        let inputs = document.inputs(spec.query.source.clone());
+        let Some(outputs) = self.store.translate(id, translator, inputs).await? else {
+            return Ok(());
+        };
+        self.store.complete(id, document.apply(&outputs)?).await?;
+        Ok(())
+    }
+
+    /// POST a completed or failed job to its callback, if it has one.
+    async fn notify(self: &Arc<Self>, id: &str) {
+        let (Some(webhook), Ok(spec), Ok(job)) =
+            (&self.webhook, self.store.spec(id), self.store.get(id))
+        else {
+            return;
+        };
+        let Some(callback) = &spec.query.callback else {
+            return;
+        };
+        if !matches!(job.status, JobStatus::Completed | JobStatus::Failed) {
+            return;
+        }
+
+        let queue = self.clone();
+        let job_id = id.to_owned();
+        // recorded in the background, as attempts are reported while the request is retried
+        let on_attempt = move |attempt| {
+            let queue = queue.clone();
+            let job_id = job_id.clone();
+            actix_web::rt::spawn(async move {
+                if let Err(error) = queue.store.record_delivery(&job_id, attempt).await {
+                    error!("Failed to record delivery of job {job_id}: {error}");
+                }
+            });
+        };
+        if let Err(error) = webhook.deliver(callback, &job, on_attempt).await {
+            error!("Failed to deliver job {id} to {callback}: {error}");
+        }
+    }
+}
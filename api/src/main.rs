@@ -1,46 +1,156 @@
-#![feature(trait_alias)]
-
+mod cli;
 mod dependency;
+mod job;
 mod router;
+mod webhook;
 
+use crate::dependency::admin::maybe_read_admin_token;
+use crate::dependency::job::create_job_queue;
 use crate::dependency::translation::get_first_configured_translator;
+use crate::job::JobQueue;
 use actix_web::middleware::Logger;
 use actix_web::{web, App, HttpServer};
 use env::require_env_var;
+use router::capabilities::get_capabilities;
+use router::chat::translate_chat;
+use router::csv::translate_csv;
 use router::health::get_health;
-use router::translate::translate;
+use router::job::{cancel_job, create_job, get_job, get_job_deliveries};
+use router::json::translate_json;
+use router::memory::{export_tmx, import_tmx};
+use router::mock::put_mock_health;
+use router::po::translate_po;
+use router::pseudolocalize::pseudolocalize;
+use router::resource::{
+    translate_android_strings, translate_apple_string_catalog, translate_apple_strings,
+    translate_apple_strings_dict,
+};
+use router::stream::translate_stream;
+use router::subtitles::translate_subtitles;
+use router::translate::{translate, translate_targets};
+use router::xliff::translate_xliff;
+use std::process::ExitCode;
 use std::sync::Arc;
 use translation::TranslationProvider;
 
+/// Large enough for translation memories and documents in the supported file formats.
+const MAX_PAYLOAD_BYTES: usize = 64 * 1024 * 1024;
+
 pub struct AppState<TP: TranslationProvider> {
     translator: Arc<TP>,
+    jobs: Arc<JobQueue>,
+    /// Required by the admin endpoints, which are disabled without one.
+    admin_token: Option<String>,
 }
 
 #[actix_web::main]
-async fn main() {
+async fn main() -> ExitCode {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    let translator =
+        get_first_configured_translator().expect("No properly configured translation provider.");
+
+    let arguments = std::env::args().skip(1).collect::<Vec<_>>();
+    if !arguments.is_empty() {
+        return match cli::run(&arguments, &translator).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("{error}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
     let port = require_env_var("PORT")
         .expect("Missing port.")
         .parse::<u16>()
         .expect("Invalid port.");
 
-    let translator =
-        get_first_configured_translator().expect("No properly configured translation provider.");
-
     let app_data = web::Data::new(AppState {
         translator: Arc::new(translator),
+        jobs: Arc::new(create_job_queue()),
+        admin_token: maybe_read_admin_token(),
     });
+    // jobs that were queued or running when the service stopped
+    for id in app_data.jobs.store.queued().expect("Failed to read jobs.") {
+        app_data.jobs.spawn(app_data.translator.clone(), id);
+    }
 
     HttpServer::new(move || {
         App::new()
             .app_data(app_data.clone())
+            .app_data(web::PayloadConfig::new(MAX_PAYLOAD_BYTES))
             .wrap(Logger::default())
             .service(translate)
+            .service(translate_targets)
+            .service(translate_stream)
+            .service(translate_chat)
+            .service(pseudolocalize)
             .service(get_health)
+            .service(get_capabilities)
+            .service(put_mock_health)
+            .service(import_tmx)
+            .service(export_tmx)
+            .service(translate_xliff)
+            .service(translate_po)
+            .service(translate_json)
+            .service(translate_android_strings)
+            .service(translate_apple_strings)
+            .service(translate_apple_strings_dict)
+            .service(translate_apple_string_catalog)
+            .service(translate_subtitles)
+            .service(translate_csv)
+            .service(create_job)
+            .service(get_job)
+            .service(get_job_deliveries)
+            .service(cancel_job)
     })
     .bind(("0.0.0.0", port))
     .expect("Failed to start server")
     .run()
     .await
     .expect("Failed to start server");
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dependency::translation::{Provider, Translator};
+    use crate::job::JobQueue;
+    use crate::AppState;
+    use actix_web::web;
+    use std::sync::Arc;
+    use translation::job::JobStore;
+    use translation::memory::TranslationMemory;
+    use translation::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use translation::segment::Segmenter;
+
+    /// A translator that pseudolocalizes, with `memory`.
+    pub fn translator(memory: Option<TranslationMemory>) -> Translator {
+        Translator {
+            provider: Provider::Mock(MockTranslationProvider::new(
+                MockMode::Pseudolocalize,
+                MockFaultInjection::default(),
+                true,
+            )),
+            memory,
+            quality: None,
+            ensemble: None,
+            segmenter: Segmenter::default(),
+        }
+    }
+
+    pub fn app_state(
+        translator: Translator,
+        admin_token: Option<&str>,
+    ) -> web::Data<AppState<Translator>> {
+        web::Data::new(AppState {
+            translator: Arc::new(translator),
+            jobs: Arc::new(JobQueue::new(
+                JobStore::open(None, 50, None).unwrap(),
+                None,
+                1,
+            )),
+            admin_token: admin_token.map(str::to_owned),
+        })
+    }
 }
@@ -0,0 +1,8 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::{get, web, HttpResponse};
+
+#[get("/capabilities")]
+pub async fn get_capabilities(app_state: web::Data<AppState<Translator>>) -> HttpResponse {
+    HttpResponse::Ok().json(app_state.translator.capabilities())
+}
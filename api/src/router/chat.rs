@@ -0,0 +1,230 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, Session};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use translation::memory::TranslationReference;
+use translation::{Language, Translation, TranslationInput, TranslationOutput};
+
+/// How many of a chat's latest messages are given to LLM providers along with a new one.
+const CONVERSATION_CONTEXT_MESSAGES: usize = 10;
+
+#[derive(Deserialize)]
+pub struct ChatQuery {
+    /// Detected from each message when not given.
+    pub source: Option<Language>,
+    pub target: Language,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    id: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ChatReply {
+    Translation {
+        id: String,
+        #[serde(flatten)]
+        output: TranslationOutput,
+    },
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        error: String,
+    },
+}
+
+/// The latest messages of a chat and their translations.
+#[derive(Default)]
+struct Conversation {
+    messages: VecDeque<TranslationReference>,
+}
+
+impl Conversation {
+    fn push(&mut self, source_text: String, target_text: String) {
+        if self.messages.len() == CONVERSATION_CONTEXT_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(TranslationReference {
+            source_text,
+            target_text,
+        });
+    }
+
+    fn messages(&self) -> Vec<TranslationReference> {
+        self.messages.iter().cloned().collect()
+    }
+}
+
+/// How many received messages can wait to be translated before the chat stops reading more.
+const QUEUED_MESSAGES: usize = 32;
+
+struct Chat {
+    translator: Arc<Translator>,
+    query: ChatQuery,
+    conversation: Conversation,
+}
+
+impl Chat {
+    /// `text`, along with the latest messages of the chat and their translations.
+    fn input(&self, text: String) -> TranslationInput {
+        TranslationInput::new(text, self.query.source.clone(), self.query.target.clone())
+            .with_neighbours(self.conversation.messages(), Vec::new())
+    }
+
+    async fn reply(&mut self, message: &str) -> ChatReply {
+        let message = match serde_json::from_str::<ChatMessage>(message) {
+            Ok(message) => message,
+            Err(error) => {
+                return ChatReply::Error {
+                    id: None,
+                    error: error.to_string(),
+                }
+            }
+        };
+        let input = self.input(message.text.clone());
+
+        let outputs = self.translator.translate(vec![input]).await;
+        match outputs.map(|outputs| outputs.into_iter().next()) {
+            Ok(Some(output)) => {
+                self.conversation
+                    .push(message.text, output.text().to_owned());
+                ChatReply::Translation {
+                    id: message.id,
+                    output,
+                }
+            }
+            Ok(None) => ChatReply::Error {
+                id: Some(message.id),
+                error: "Missing translation".to_owned(),
+            },
+            Err(error) => {
+                error!("{}", error);
+                ChatReply::Error {
+                    id: Some(message.id),
+                    error: error.to_string(),
+                }
+            }
+        }
+    }
+
+    /// Translate the messages one at a time, in the order they were received, so each is
+    /// translated with the ones before it.
+    async fn translate(mut self, mut session: Session, mut messages: mpsc::Receiver<String>) {
+        while let Some(message) = messages.recv().await {
+            let reply = match serde_json::to_string(&self.reply(&message).await) {
+                Ok(reply) => reply,
+                Err(error) => {
+                    error!("{}", error);
+                    continue;
+                }
+            };
+            // the client may have gone while the message was being translated
+            if session.text(reply).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+async fn run(mut session: Session, chat: Chat, mut messages: AggregatedMessageStream) {
+    let (sender, receiver) = mpsc::channel(QUEUED_MESSAGES);
+    actix_web::rt::spawn(chat.translate(session.clone(), receiver));
+    while let Some(message) = messages.recv().await {
+        match message {
+            Ok(AggregatedMessage::Text(text)) => {
+                if sender.send(text.to_string()).await.is_err() {
+                    return;
+                }
+            }
+            Ok(AggregatedMessage::Ping(bytes)) => {
+                if session.pong(&bytes).await.is_err() {
+                    return;
+                }
+            }
+            Ok(AggregatedMessage::Close(reason)) => {
+                let _ = session.close(reason).await;
+                return;
+            }
+            Ok(AggregatedMessage::Binary(_) | AggregatedMessage::Pong(_)) => {}
+            Err(error) => {
+                warn!("{}", error);
+                break;
+            }
+        }
+    }
+    let _ = session.close(None).await;
+}
+
+/// Translate a chat over a WebSocket. Each text message is JSON with an `id` and the `text`
+/// to translate, and is answered with its translation or error under the same `id`.
+#[get("/ws/translate")]
+pub async fn translate_chat(
+    (request, body, query, app_state): (
+        HttpRequest,
+        web::Payload,
+        web::Query<ChatQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, session, messages) = actix_ws::handle(&request, body)?;
+    let chat = Chat {
+        translator: app_state.translator.clone(),
+        query: query.into_inner(),
+        conversation: Conversation::default(),
+    };
+    actix_web::rt::spawn(run(session, chat, messages.aggregate_continuations()));
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::chat::{Chat, ChatQuery, ChatReply, Conversation};
+    use crate::test::translator;
+    use std::sync::Arc;
+
+    fn chat() -> Chat {
+        Chat {
+            translator: Arc::new(translator(None)),
+            query: serde_json::from_value::<ChatQuery>(
+                serde_json::json!({ "source": "en", "target": "fr" }),
+            )
+            .unwrap(),
+            conversation: Conversation::default(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn it_should_translate_messages_with_the_ones_before_them() {
+        let mut chat = chat();
+
+        let first = chat.reply(r#"{ "id": "1", "text": "Hello" }"#).await;
+        let second = chat.reply(r#"{ "id": "2", "text": "How are you?" }"#).await;
+        assert!(matches!(first, ChatReply::Translation { id, .. } if id == "1"));
+        assert!(matches!(second, ChatReply::Translation { id, .. } if id == "2"));
+
+        let context = chat
+            .conversation
+            .messages()
+            .into_iter()
+            .map(|message| message.source_text)
+            .collect::<Vec<_>>();
+        assert_eq!(context, vec!["Hello", "How are you?"]);
+    }
+
+    #[actix_rt::test]
+    async fn it_should_not_add_invalid_messages_to_the_conversation() {
+        let mut chat = chat();
+
+        let reply = chat.reply("Hello").await;
+        assert!(matches!(reply, ChatReply::Error { id: None, .. }));
+        assert!(chat.conversation.messages().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::web::Bytes;
+use actix_web::{post, web, HttpResponse, ResponseError};
+use formats::error::FormatError;
+use formats::spreadsheet::CsvTranslation;
+use futures::{stream, StreamExt};
+use log::error;
+use serde::Deserialize;
+use std::rc::Rc;
+use std::str::FromStr;
+use thiserror::Error;
+use translation::Language;
+
+const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+#[derive(Error, Debug)]
+pub enum CsvRouteError {
+    #[error("FormatError: {0}")]
+    FormatError(#[from] FormatError),
+    #[error("Invalid target language {0}")]
+    InvalidTarget(String),
+}
+
+impl ResponseError for CsvRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        HttpResponse::BadRequest().finish()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CsvQuery {
+    pub column: String,
+    pub source: Option<Language>,
+    /// Comma separated target languages.
+    pub targets: String,
+}
+
+pub fn parse_csv(document: &str, query: CsvQuery) -> Result<CsvTranslation, CsvRouteError> {
+    let targets = query
+        .targets
+        .split(',')
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .map(|target| {
+            Language::from_str(target).map_err(|_| CsvRouteError::InvalidTarget(target.to_owned()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if targets.is_empty() {
+        return Err(CsvRouteError::InvalidTarget(query.targets));
+    }
+    Ok(CsvTranslation::parse(
+        document,
+        &query.column,
+        query.source,
+        targets,
+    )?)
+}
+
+/// Translate a column of a CSV file into each target language. The file is streamed back
+/// as its rows are translated, a chunk at a time.
+#[post("/translate/csv")]
+pub async fn translate_csv(
+    (document, query, app_state): (
+        String,
+        web::Query<CsvQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, CsvRouteError> {
+    let translation = Rc::new(parse_csv(&document, query.into_inner())?);
+    let header = translation.header()?;
+    let rows = stream::iter(0..translation.chunk_count()).then(move |chunk| {
+        let translation = translation.clone();
+        let app_state = app_state.clone();
+        async move {
+            translation
+                .translate_chunk(app_state.translator.as_ref(), chunk)
+                .await
+                .map(Bytes::from)
+        }
+    });
+    Ok(HttpResponse::Ok()
+        .content_type(CSV_CONTENT_TYPE)
+        .streaming(stream::once(async { Ok(Bytes::from(header)) }).chain(rows)))
+}
@@ -0,0 +1,88 @@
+use crate::dependency::translation::Translator;
+use crate::job::{JobDocument, JobDocumentError, JobQuery, JobSpec};
+use crate::AppState;
+use actix_web::{delete, get, post, web, HttpResponse, ResponseError};
+use log::error;
+use thiserror::Error;
+use translation::job::JobError;
+
+#[derive(Error, Debug)]
+pub enum JobRouteError {
+    #[error("JobError: {0}")]
+    JobError(#[from] JobError),
+    #[error("JobDocumentError: {0}")]
+    JobDocumentError(#[from] JobDocumentError),
+    #[error("Invalid callback {0}")]
+    InvalidCallback(String),
+    #[error("Job webhooks are not configured")]
+    WebhookNotConfigured,
+}
+
+impl ResponseError for JobRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        match self {
+            JobRouteError::JobError(JobError::NotFound(_)) => HttpResponse::NotFound().finish(),
+            JobRouteError::JobError(JobError::AlreadyFinished(_)) => {
+                HttpResponse::Conflict().finish()
+            }
+            JobRouteError::JobError(_) => HttpResponse::InternalServerError().finish(),
+            JobRouteError::JobDocumentError(_)
+            | JobRouteError::InvalidCallback(_)
+            | JobRouteError::WebhookNotConfigured => HttpResponse::BadRequest().finish(),
+        }
+    }
+}
+
+#[post("/jobs")]
+pub async fn create_job(
+    (body, query, app_state): (
+        String,
+        web::Query<JobQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, JobRouteError> {
+    let spec = JobSpec {
+        query: query.into_inner(),
+        body,
+    };
+    if let Some(callback) = &spec.query.callback {
+        let webhook = app_state
+            .jobs
+            .webhook
+            .as_ref()
+            .ok_or(JobRouteError::WebhookNotConfigured)?;
+        webhook
+            .check_callback(callback)
+            .await
+            .map_err(|error| JobRouteError::InvalidCallback(error.to_string()))?;
+    }
+    // reject anything the job would fail to parse before queueing it
+    JobDocument::parse(&spec)?;
+    let job = app_state.jobs.store.create(spec).await?;
+    app_state
+        .jobs
+        .spawn(app_state.translator.clone(), job.id.clone());
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+#[get("/jobs/{id}")]
+pub async fn get_job(
+    (id, app_state): (web::Path<String>, web::Data<AppState<Translator>>),
+) -> Result<HttpResponse, JobRouteError> {
+    Ok(HttpResponse::Ok().json(app_state.jobs.store.get(&id)?))
+}
+
+#[delete("/jobs/{id}")]
+pub async fn cancel_job(
+    (id, app_state): (web::Path<String>, web::Data<AppState<Translator>>),
+) -> Result<HttpResponse, JobRouteError> {
+    Ok(HttpResponse::Ok().json(app_state.jobs.store.cancel(&id).await?))
+}
+
+#[get("/jobs/{id}/deliveries")]
+pub async fn get_job_deliveries(
+    (id, app_state): (web::Path<String>, web::Data<AppState<Translator>>),
+) -> Result<HttpResponse, JobRouteError> {
+    Ok(HttpResponse::Ok().json(app_state.jobs.store.deliveries(&id)?))
+}
@@ -0,0 +1,58 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::{post, web, HttpResponse, ResponseError};
+use formats::json::JsonResource;
+use log::error;
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+use translation::{Language, Translation, TranslationError};
+
+const UNTRANSLATED_KEYS_HEADER: &str = "X-Untranslated-Keys";
+
+#[derive(Error, Debug)]
+pub enum JsonRouteError {
+    #[error("TranslationError: {0}")]
+    TranslationError(#[from] TranslationError),
+}
+
+impl ResponseError for JsonRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct JsonQuery {
+    source: Option<Language>,
+    target: Language,
+}
+
+#[derive(Deserialize)]
+pub struct JsonResourceRequest {
+    pub resource: Value,
+    /// The current resource in the target language, only keys missing from it are translated.
+    pub existing: Option<Value>,
+}
+
+#[post("/translate/json")]
+pub async fn translate_json(
+    (request, query, app_state): (
+        web::Json<JsonResourceRequest>,
+        web::Query<JsonQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, JsonRouteError> {
+    let request = request.into_inner();
+    let query = query.into_inner();
+    let resource = JsonResource::new(request.resource, request.existing, query.target);
+    let outputs = app_state
+        .translator
+        .translate(resource.inputs(query.source))
+        .await?;
+    let translated = resource.apply(&outputs);
+    Ok(HttpResponse::Ok()
+        .insert_header((UNTRANSLATED_KEYS_HEADER, translated.untranslated))
+        .json(translated.resource))
+}
@@ -0,0 +1,218 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::http::header::ContentType;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, ResponseError};
+use chrono::{Days, NaiveDate};
+use formats::error::FormatError;
+use formats::tmx;
+use log::error;
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+use translation::memory::{now, SegmentFilter, TranslationMemory, TranslationMemoryError};
+use translation::Language;
+
+#[derive(Error, Debug)]
+pub enum MemoryRouteError {
+    #[error("Admin endpoints are disabled without an admin token")]
+    AdminDisabled,
+    #[error("Missing or invalid admin token")]
+    Unauthorized,
+    #[error("Translation memory is not configured")]
+    NotConfigured,
+    #[error("FormatError: {0}")]
+    FormatError(#[from] FormatError),
+    #[error("TranslationMemoryError: {0}")]
+    TranslationMemoryError(#[from] TranslationMemoryError),
+}
+
+impl ResponseError for MemoryRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        match self {
+            MemoryRouteError::AdminDisabled => HttpResponse::NotFound().finish(),
+            MemoryRouteError::Unauthorized => HttpResponse::Unauthorized()
+                .insert_header(("WWW-Authenticate", "Bearer"))
+                .finish(),
+            MemoryRouteError::NotConfigured => HttpResponse::NotFound().finish(),
+            MemoryRouteError::FormatError(_) => HttpResponse::BadRequest().finish(),
+            MemoryRouteError::TranslationMemoryError(_) => {
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// Compare in constant time, so the token can't be guessed from how long a comparison takes.
+fn tokens_equal(first: &[u8], second: &[u8]) -> bool {
+    first.len() == second.len()
+        && first
+            .iter()
+            .zip(second)
+            .fold(0, |difference, (first, second)| {
+                difference | (first ^ second)
+            })
+            == 0
+}
+
+/// Check that `request` has the admin token as its bearer token.
+fn require_admin(
+    request: &HttpRequest,
+    app_state: &AppState<Translator>,
+) -> Result<(), MemoryRouteError> {
+    let admin_token = app_state
+        .admin_token
+        .as_deref()
+        .ok_or(MemoryRouteError::AdminDisabled)?;
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(MemoryRouteError::Unauthorized)?;
+    if tokens_equal(token.trim().as_bytes(), admin_token.as_bytes()) {
+        Ok(())
+    } else {
+        Err(MemoryRouteError::Unauthorized)
+    }
+}
+
+fn require_memory(translator: &Translator) -> Result<&TranslationMemory, MemoryRouteError> {
+    translator
+        .memory
+        .as_ref()
+        .ok_or(MemoryRouteError::NotConfigured)
+}
+
+#[post("/admin/memory/tmx")]
+pub async fn import_tmx(
+    (request, document, app_state): (HttpRequest, String, web::Data<AppState<Translator>>),
+) -> Result<HttpResponse, MemoryRouteError> {
+    require_admin(&request, &app_state)?;
+    let memory = require_memory(&app_state.translator)?;
+    let import = tmx::import(&document, now())?;
+    let imported = import.segments.len();
+    memory.add(import.segments)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "imported": imported,
+        "skipped": import.skipped,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct TmxExportQuery {
+    source: Option<Language>,
+    target: Option<Language>,
+    project: Option<String>,
+    /// Inclusive.
+    from: Option<NaiveDate>,
+    /// Inclusive.
+    to: Option<NaiveDate>,
+}
+
+fn start_of_day(date: NaiveDate) -> u64 {
+    date.and_hms_opt(0, 0, 0)
+        .map(|date| date.and_utc().timestamp())
+        .and_then(|timestamp| u64::try_from(timestamp).ok())
+        .unwrap_or(0)
+}
+
+impl From<TmxExportQuery> for SegmentFilter {
+    fn from(query: TmxExportQuery) -> Self {
+        SegmentFilter {
+            source_language: query.source,
+            target_language: query.target,
+            project: query.project,
+            from: query.from.map(start_of_day),
+            to: query
+                .to
+                .and_then(|to| to.checked_add_days(Days::new(1)))
+                .map(start_of_day),
+        }
+    }
+}
+
+#[get("/admin/memory/tmx")]
+pub async fn export_tmx(
+    (request, query, app_state): (
+        HttpRequest,
+        web::Query<TmxExportQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, MemoryRouteError> {
+    require_admin(&request, &app_state)?;
+    let memory = require_memory(&app_state.translator)?;
+    let segments = memory.segments(&SegmentFilter::from(query.into_inner()))?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::xml())
+        .body(tmx::export(&segments)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::memory::{export_tmx, import_tmx};
+    use crate::test::{app_state, translator};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use translation::memory::{FuzzyMatchMode, TranslationMemory};
+
+    fn memory(name: &str) -> TranslationMemory {
+        let path =
+            std::env::temp_dir().join(format!("admin-memory-{name}-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        TranslationMemory::open(&path, 0.8, FuzzyMatchMode::Return).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn it_should_reject_requests_without_the_admin_token() {
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(
+                    translator(Some(memory("unauthorized"))),
+                    Some("secret"),
+                ))
+                .service(import_tmx)
+                .service(export_tmx),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/admin/memory/tmx")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = test::TestRequest::post()
+            .uri("/admin/memory/tmx")
+            .insert_header(("Authorization", "Bearer wrong"))
+            .set_payload("<tmx version=\"1.4\"><body></body></tmx>")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = test::TestRequest::get()
+            .uri("/admin/memory/tmx")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn it_should_disable_admin_endpoints_without_an_admin_token() {
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(translator(Some(memory("disabled"))), None))
+                .service(export_tmx),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/admin/memory/tmx")
+            .insert_header(("Authorization", "Bearer "))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
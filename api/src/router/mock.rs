@@ -0,0 +1,22 @@
+use crate::dependency::translation::{Provider, Translator};
+use crate::AppState;
+use actix_web::{put, web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct MockHealth {
+    healthy: bool,
+}
+
+#[put("/mock/health")]
+pub async fn put_mock_health(
+    (mock_health, app_state): (web::Json<MockHealth>, web::Data<AppState<Translator>>),
+) -> HttpResponse {
+    match &app_state.translator.provider {
+        Provider::Mock(mock) => {
+            mock.set_healthy(mock_health.healthy);
+            HttpResponse::NoContent().finish()
+        }
+        _ => HttpResponse::NotFound().finish(),
+    }
+}
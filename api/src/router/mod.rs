@@ -1,2 +1,15 @@
+pub mod capabilities;
+pub mod chat;
+pub mod csv;
 pub mod health;
+pub mod job;
+pub mod json;
+pub mod memory;
+pub mod mock;
+pub mod po;
+pub mod pseudolocalize;
+pub mod resource;
+pub mod stream;
+pub mod subtitles;
 pub mod translate;
+pub mod xliff;
@@ -0,0 +1,56 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::{post, web, HttpResponse, ResponseError};
+use formats::error::FormatError;
+use formats::po::PoCatalog;
+use log::error;
+use serde::Deserialize;
+use thiserror::Error;
+use translation::{Language, Translation, TranslationError};
+
+const PO_CONTENT_TYPE: &str = "text/x-gettext-translation; charset=utf-8";
+
+#[derive(Error, Debug)]
+pub enum PoRouteError {
+    #[error("FormatError: {0}")]
+    FormatError(#[from] FormatError),
+    #[error("TranslationError: {0}")]
+    TranslationError(#[from] TranslationError),
+}
+
+impl ResponseError for PoRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        match self {
+            PoRouteError::FormatError(_) => HttpResponse::BadRequest().finish(),
+            PoRouteError::TranslationError(_) => HttpResponse::InternalServerError().finish(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PoQuery {
+    pub source: Option<Language>,
+    pub target: Language,
+}
+
+/// Fill in the empty translations of a POT or PO catalog.
+pub async fn translate_catalog(
+    translator: &Translator,
+    document: &str,
+    query: PoQuery,
+) -> Result<String, PoRouteError> {
+    let catalog = PoCatalog::parse(document, query.target)?;
+    let outputs = translator.translate(catalog.inputs(query.source)).await?;
+    Ok(catalog.apply(&outputs))
+}
+
+#[post("/translate/po")]
+pub async fn translate_po(
+    (document, query, app_state): (String, web::Query<PoQuery>, web::Data<AppState<Translator>>),
+) -> Result<HttpResponse, PoRouteError> {
+    let catalog = translate_catalog(&app_state.translator, &document, query.into_inner()).await?;
+    Ok(HttpResponse::Ok()
+        .content_type(PO_CONTENT_TYPE)
+        .body(catalog))
+}
@@ -0,0 +1,13 @@
+use actix_web::{post, web, HttpResponse};
+use translation::pseudo::{pseudolocalize_inputs, PseudolocalizationOptions};
+use translation::TranslationInput;
+
+#[post("/pseudolocalize")]
+pub async fn pseudolocalize(
+    (translation_input, options): (
+        web::Json<Vec<TranslationInput>>,
+        web::Query<PseudolocalizationOptions>,
+    ),
+) -> HttpResponse {
+    HttpResponse::Ok().json(pseudolocalize_inputs(translation_input.0, &options))
+}
@@ -0,0 +1,122 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::http::header::ContentType;
+use actix_web::{post, web, HttpResponse, ResponseError};
+use formats::android::AndroidStrings;
+use formats::apple::{AppleStringCatalog, AppleStrings, AppleStringsDict};
+use formats::error::FormatError;
+use formats::resource::{ResourceFormat, ResourceTranslation};
+use log::error;
+use serde::Deserialize;
+use thiserror::Error;
+use translation::{Language, Translation, TranslationError};
+
+const UNTRANSLATED_ENTRIES_HEADER: &str = "X-Untranslated-Entries";
+
+#[derive(Error, Debug)]
+pub enum ResourceRouteError {
+    #[error("FormatError: {0}")]
+    FormatError(#[from] FormatError),
+    #[error("TranslationError: {0}")]
+    TranslationError(#[from] TranslationError),
+}
+
+impl ResponseError for ResourceRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        match self {
+            ResourceRouteError::FormatError(_) => HttpResponse::BadRequest().finish(),
+            ResourceRouteError::TranslationError(_) => HttpResponse::InternalServerError().finish(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ResourceQuery {
+    source: Option<Language>,
+    target: Language,
+}
+
+async fn translate_resource<Format: ResourceFormat>(
+    translator: &Translator,
+    format: Format,
+    query: ResourceQuery,
+    content_type: ContentType,
+) -> Result<HttpResponse, ResourceRouteError> {
+    let translation = ResourceTranslation::new(format, query.target);
+    let outputs = translator
+        .translate(translation.inputs(query.source))
+        .await?;
+    let translated = translation.apply(&outputs);
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((UNTRANSLATED_ENTRIES_HEADER, translated.untranslated))
+        .body(translated.document))
+}
+
+#[post("/translate/android")]
+pub async fn translate_android_strings(
+    (document, query, app_state): (
+        String,
+        web::Query<ResourceQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, ResourceRouteError> {
+    let format = AndroidStrings::parse(&document)?;
+    translate_resource(
+        &app_state.translator,
+        format,
+        query.into_inner(),
+        ContentType::xml(),
+    )
+    .await
+}
+
+#[post("/translate/strings")]
+pub async fn translate_apple_strings(
+    (document, query, app_state): (
+        String,
+        web::Query<ResourceQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, ResourceRouteError> {
+    let format = AppleStrings::parse(&document)?;
+    translate_resource(
+        &app_state.translator,
+        format,
+        query.into_inner(),
+        ContentType::plaintext(),
+    )
+    .await
+}
+
+#[post("/translate/stringsdict")]
+pub async fn translate_apple_strings_dict(
+    (document, query, app_state): (
+        String,
+        web::Query<ResourceQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, ResourceRouteError> {
+    let format = AppleStringsDict::parse(&document)?;
+    translate_resource(
+        &app_state.translator,
+        format,
+        query.into_inner(),
+        ContentType::xml(),
+    )
+    .await
+}
+
+#[post("/translate/xcstrings")]
+pub async fn translate_apple_string_catalog(
+    (document, query, app_state): (
+        String,
+        web::Query<ResourceQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, ResourceRouteError> {
+    let query = query.into_inner();
+    let format = AppleStringCatalog::parse(&document, &query.target)?;
+    translate_resource(&app_state.translator, format, query, ContentType::json()).await
+}
@@ -0,0 +1,48 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::web::Bytes;
+use actix_web::{post, web, HttpResponse};
+use futures::stream;
+use tokio::sync::mpsc::channel;
+use translation::stream::{StreamingTranslation, TranslationEvent, TranslationEvents};
+use translation::TranslationInput;
+
+const EVENT_STREAM_CONTENT_TYPE: &str = "text/event-stream";
+
+/// How many events can wait to be sent to a slow client before the translation waits too.
+const BUFFERED_EVENTS: usize = 64;
+
+fn to_server_sent_event(event: &TranslationEvent) -> Result<Bytes, serde_json::Error> {
+    let data = serde_json::to_string(event)?;
+    Ok(Bytes::from(format!(
+        "event: {}\ndata: {data}\n\n",
+        event.name()
+    )))
+}
+
+/// Translate the inputs, sending each output as a server-sent event as soon as it's done,
+/// along with partial translations from providers that can stream them.
+#[post("/translate/stream")]
+pub async fn translate_stream(
+    (translation_input, app_state): (
+        web::Json<Vec<TranslationInput>>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> HttpResponse {
+    let (sender, receiver) = channel(BUFFERED_EVENTS);
+    let translator = app_state.translator.clone();
+    // the stream ends once the translation is done and drops the sender, and the translation
+    // stops once the client has gone and the stream drops the receiver
+    actix_web::rt::spawn(async move {
+        translator
+            .translate_streaming(translation_input.0, &TranslationEvents::new(sender))
+            .await;
+    });
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((to_server_sent_event(&event), receiver))
+    });
+    HttpResponse::Ok()
+        .content_type(EVENT_STREAM_CONTENT_TYPE)
+        .streaming(events)
+}
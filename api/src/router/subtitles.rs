@@ -0,0 +1,65 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::{post, web, HttpResponse, ResponseError};
+use formats::error::FormatError;
+use formats::subtitles::{SubtitleFormat, SubtitleOptions, Subtitles};
+use log::error;
+use serde::Deserialize;
+use thiserror::Error;
+use translation::{Language, Translation, TranslationError};
+
+const SRT_CONTENT_TYPE: &str = "application/x-subrip; charset=utf-8";
+const WEB_VTT_CONTENT_TYPE: &str = "text/vtt; charset=utf-8";
+
+#[derive(Error, Debug)]
+pub enum SubtitlesRouteError {
+    #[error("FormatError: {0}")]
+    FormatError(#[from] FormatError),
+    #[error("TranslationError: {0}")]
+    TranslationError(#[from] TranslationError),
+}
+
+impl ResponseError for SubtitlesRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        match self {
+            SubtitlesRouteError::FormatError(_) => HttpResponse::BadRequest().finish(),
+            SubtitlesRouteError::TranslationError(_) => {
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SubtitlesQuery {
+    source: Option<Language>,
+    target: Language,
+    max_line_length: Option<usize>,
+}
+
+#[post("/translate/subtitles")]
+pub async fn translate_subtitles(
+    (document, query, app_state): (
+        String,
+        web::Query<SubtitlesQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, SubtitlesRouteError> {
+    let query = query.into_inner();
+    let subtitles = Subtitles::parse(&document, query.target)?;
+    let outputs = app_state
+        .translator
+        .translate(subtitles.inputs(query.source))
+        .await?;
+    let options = SubtitleOptions {
+        max_line_length: query.max_line_length,
+    };
+    let content_type = match subtitles.format() {
+        SubtitleFormat::Srt => SRT_CONTENT_TYPE,
+        SubtitleFormat::WebVtt => WEB_VTT_CONTENT_TYPE,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .body(subtitles.apply(&outputs, &options)))
+}
@@ -1,30 +1,173 @@
-use crate::dependency::translation::Translator;
+use crate::dependency::translation::{Capabilities, Translator};
 use crate::AppState;
 use actix_web::{post, web, HttpResponse, ResponseError};
 use log::error;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use translation::{Translation, TranslationError, TranslationInput};
+use translation::alternatives::{AlternativeTranslation, MAX_ALTERNATIVES};
+use translation::multi::{group_by_target, MultiTargetInput, MultiTargetTranslation};
+use translation::{Language, Translation, TranslationError, TranslationInput, TranslationOutput};
 
 #[derive(Error, Debug)]
 pub enum TranslateRouteError {
     #[error("TranslationError: {0}")]
     TranslationError(#[from] TranslationError),
+    #[error("No targets for input {0}")]
+    MissingTargets(usize),
+    #[error("The {} provider doesn't support alternatives", .0.provider)]
+    AlternativesUnsupported(Capabilities),
+    #[error("Between 1 and {MAX_ALTERNATIVES} alternatives can be asked for, not {0}")]
+    InvalidAlternatives(usize),
+    #[error("Alternatives can't be combined with document mode")]
+    AlternativesInDocumentMode,
+    #[error("Ensembles can't be combined with alternatives or document mode")]
+    EnsembleWithOtherModes,
+    #[error("No ensemble is configured")]
+    EnsembleUnconfigured(Capabilities),
+    #[error("Quality estimation isn't configured")]
+    QualityEstimationUnconfigured(Capabilities),
+}
+
+#[derive(Serialize)]
+struct InputError {
+    index: usize,
+    error: String,
+}
+
+/// The outputs of a partial translation, `null` for the inputs that have an error.
+#[derive(Serialize)]
+struct PartialTranslationResponse<'a> {
+    outputs: &'a [Option<TranslationOutput>],
+    errors: Vec<InputError>,
 }
 
 impl ResponseError for TranslateRouteError {
     fn error_response(&self) -> HttpResponse {
         error!("{}", self);
-        HttpResponse::InternalServerError().finish()
+        match self {
+            TranslateRouteError::TranslationError(TranslationError::PartialTranslation {
+                outputs,
+                errors,
+            }) => HttpResponse::UnprocessableEntity().json(PartialTranslationResponse {
+                outputs,
+                errors: errors
+                    .iter()
+                    .map(|error| InputError {
+                        index: error.index,
+                        error: error.error.to_string(),
+                    })
+                    .collect(),
+            }),
+            TranslateRouteError::TranslationError(_) => {
+                HttpResponse::InternalServerError().finish()
+            }
+            TranslateRouteError::MissingTargets(_)
+            | TranslateRouteError::InvalidAlternatives(_)
+            | TranslateRouteError::AlternativesInDocumentMode
+            | TranslateRouteError::EnsembleWithOtherModes => HttpResponse::BadRequest().finish(),
+            TranslateRouteError::AlternativesUnsupported(capabilities)
+            | TranslateRouteError::EnsembleUnconfigured(capabilities)
+            | TranslateRouteError::QualityEstimationUnconfigured(capabilities) => {
+                HttpResponse::UnprocessableEntity().json(capabilities)
+            }
+        }
     }
 }
 
+#[derive(Deserialize)]
+pub struct TranslateQuery {
+    /// Whether the inputs are the consecutive segments of one document.
+    #[serde(default)]
+    pub document: bool,
+    /// How many candidate translations to give for each input, at most [MAX_ALTERNATIVES].
+    pub alternatives: Option<usize>,
+    /// Whether to translate with each provider of the ensemble and have its judge choose.
+    #[serde(default)]
+    pub ensemble: bool,
+    /// Whether to estimate the quality of each translation.
+    #[serde(default)]
+    pub quality: bool,
+}
+
 #[post("/translate")]
 pub async fn translate(
-    (translation_input, app_state): (
+    (translation_input, query, app_state): (
         web::Json<Vec<TranslationInput>>,
+        web::Query<TranslateQuery>,
         web::Data<AppState<Translator>>,
     ),
 ) -> Result<HttpResponse, TranslateRouteError> {
-    let output = app_state.translator.translate(translation_input.0).await?;
+    let translator = &app_state.translator;
+    if query.quality && translator.quality.is_none() {
+        return Err(TranslateRouteError::QualityEstimationUnconfigured(
+            translator.capabilities(),
+        ));
+    }
+    if query.ensemble && translator.ensemble.is_none() {
+        return Err(TranslateRouteError::EnsembleUnconfigured(
+            translator.capabilities(),
+        ));
+    }
+    if let Some(count) = query
+        .alternatives
+        .filter(|count| !(1..=MAX_ALTERNATIVES).contains(count))
+    {
+        return Err(TranslateRouteError::InvalidAlternatives(count));
+    }
+    let alternatives = query.alternatives.filter(|count| *count > 1);
+    if query.ensemble && (query.document || alternatives.is_some()) {
+        return Err(TranslateRouteError::EnsembleWithOtherModes);
+    }
+    let inputs = translation_input.into_inner();
+    let estimated_inputs = query.quality.then(|| inputs.clone());
+
+    let output = match alternatives {
+        None if query.ensemble => translator.translate_ensemble(inputs).await?,
+        Some(_) if query.document => return Err(TranslateRouteError::AlternativesInDocumentMode),
+        Some(_) if !translator.supports_alternatives() => {
+            return Err(TranslateRouteError::AlternativesUnsupported(
+                translator.capabilities(),
+            ))
+        }
+        Some(count) => translator.translate_alternatives(inputs, count).await?,
+        None if query.document => translator.translate_document(inputs).await?,
+        None => translator.translate(inputs).await?,
+    };
+    let output = match estimated_inputs {
+        Some(inputs) => translator.estimate_quality(&inputs, output).await?,
+        None => output,
+    };
     Ok(HttpResponse::Ok().json(output))
 }
+
+#[derive(Deserialize)]
+pub struct MultiTargetRequest {
+    /// For the inputs that don't have their own targets.
+    #[serde(default)]
+    pub targets: Vec<Language>,
+    pub inputs: Vec<MultiTargetInput>,
+}
+
+/// Translate each input into several languages, returning the outputs of each language in
+/// the order of the inputs that asked for it.
+#[post("/translate/targets")]
+pub async fn translate_targets(
+    (request, app_state): (
+        web::Json<MultiTargetRequest>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, TranslateRouteError> {
+    let MultiTargetRequest { targets, inputs } = request.into_inner();
+    let groups = inputs
+        .into_iter()
+        .map(|input| input.into_inputs(&targets))
+        .collect::<Vec<_>>();
+    if let Some(index) = groups.iter().position(Vec::is_empty) {
+        return Err(TranslateRouteError::MissingTargets(index));
+    }
+    let outputs = app_state
+        .translator
+        .translate_targets(groups.clone())
+        .await?;
+    Ok(HttpResponse::Ok().json(group_by_target(&groups, outputs)))
+}
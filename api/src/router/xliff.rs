@@ -0,0 +1,57 @@
+use crate::dependency::translation::Translator;
+use crate::AppState;
+use actix_web::http::header::ContentType;
+use actix_web::{post, web, HttpResponse, ResponseError};
+use formats::error::FormatError;
+use formats::xliff::XliffDocument;
+use log::error;
+use serde::Deserialize;
+use thiserror::Error;
+use translation::{Language, Translation, TranslationError};
+
+const UNTRANSLATED_UNITS_HEADER: &str = "X-Untranslated-Units";
+
+#[derive(Error, Debug)]
+pub enum XliffRouteError {
+    #[error("FormatError: {0}")]
+    FormatError(#[from] FormatError),
+    #[error("TranslationError: {0}")]
+    TranslationError(#[from] TranslationError),
+}
+
+impl ResponseError for XliffRouteError {
+    fn error_response(&self) -> HttpResponse {
+        error!("{}", self);
+        match self {
+            XliffRouteError::FormatError(_) => HttpResponse::BadRequest().finish(),
+            XliffRouteError::TranslationError(_) => HttpResponse::InternalServerError().finish(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct XliffQuery {
+    source: Option<Language>,
+    target: Language,
+}
+
+#[post("/translate/xliff")]
+pub async fn translate_xliff(
+    (document, query, app_state): (
+        String,
+        web::Query<XliffQuery>,
+        web::Data<AppState<Translator>>,
+    ),
+) -> Result<HttpResponse, XliffRouteError> {
+    let query = query.into_inner();
+    let document = XliffDocument::parse(document, query.target)?;
+    let outputs = app_state
+        .translator
+        .translate(document.inputs(query.source))
+        .await?;
+    let translated = document.apply(&outputs);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::xml())
+        .insert_header((UNTRANSLATED_UNITS_HEADER, translated.untranslated))
+        .body(translated.document))
+}
@@ -0,0 +1,368 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::RetryTransientMiddleware;
+use serde::Serialize;
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::net::lookup_host;
+use translation::job::DeliveryAttempt;
+use translation::memory::now;
+
+/// Hex encoded HMAC-SHA256 of the body, prefixed with `sha256=`.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// The longest wait between retries, however many there are.
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("ReqwestError {0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("ReqwestMiddlewareError {0}")]
+    ReqwestMiddlewareError(#[from] reqwest_middleware::Error),
+    #[error("SerdeJsonError {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("Invalid webhook secret config")]
+    InvalidSecretConfig,
+    #[error("Unsuccessful response {0}")]
+    UnsuccessfulResponse(u16),
+    #[error("Invalid callback {0}")]
+    InvalidCallback(String),
+    #[error("Callback {0} resolves to a non-public address")]
+    NonPublicCallback(String),
+    #[error("IoError {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+fn is_public_ipv4(ip: &Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        // including the metadata service of cloud providers at 169.254.169.254
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || first == 0
+        // shared address space, 100.64.0.0/10
+        || (first == 100 && (64..128).contains(&second))
+        // benchmarking, 198.18.0.0/15
+        || (first == 198 && (18..20).contains(&second))
+        // reserved, 240.0.0.0/4
+        || first >= 240)
+}
+
+fn is_public_ipv6(ip: &Ipv6Addr) -> bool {
+    if let Some(ipv4) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(&ipv4);
+    }
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // link local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+        // documentation, 2001:db8::/32
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+/// Whether `ip` can be reached from the internet, rather than being a loopback, private,
+/// link local or otherwise reserved address.
+pub fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+type AttemptCallback = Arc<dyn Fn(DeliveryAttempt) + Send + Sync>;
+
+/// Reports every attempt made by the retry middleware it runs inside of.
+struct AttemptRecorder {
+    on_attempt: AttemptCallback,
+}
+
+#[async_trait]
+impl Middleware for AttemptRecorder {
+    async fn handle(
+        &self,
+        request: Request,
+        extensions: &mut http::Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let url = request.url().to_string();
+        let result = next.run(request, extensions).await;
+        let (status, error) = match &result {
+            Ok(response) => (Some(response.status().as_u16()), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+        (self.on_attempt)(DeliveryAttempt {
+            url,
+            timestamp: now(),
+            status,
+            error,
+        });
+        result
+    }
+}
+
+pub struct WebhookClient {
+    secret: Vec<u8>,
+    retry_policy: ExponentialBackoff,
+    /// Hosts that callbacks can be delivered to even when they aren't public, such as
+    /// services on the same network.
+    allowed_hosts: Vec<String>,
+}
+
+impl WebhookClient {
+    pub fn try_new(
+        secret: String,
+        max_retries: u32,
+        min_retry_interval: Duration,
+        allowed_hosts: Vec<String>,
+    ) -> Result<Self, WebhookError> {
+        if secret.is_empty() {
+            return Err(WebhookError::InvalidSecretConfig);
+        }
+
+        let max_retry_interval = min_retry_interval
+            .saturating_mul(2u32.checked_pow(max_retries).unwrap_or(u32::MAX))
+            .min(MAX_RETRY_INTERVAL)
+            .max(min_retry_interval);
+        let retry_policy = ExponentialBackoff::builder()
+            .retry_bounds(min_retry_interval, max_retry_interval)
+            .build_with_max_retries(max_retries);
+        Ok(WebhookClient {
+            secret: secret.into_bytes(),
+            retry_policy,
+            allowed_hosts: allowed_hosts
+                .into_iter()
+                .map(|host| host.to_lowercase())
+                .collect(),
+        })
+    }
+
+    /// The URL of `callback` and the addresses it resolves to, when it's an HTTP(S) URL that
+    /// only resolves to public addresses or its host is allowed.
+    pub async fn check_callback(
+        &self,
+        callback: &str,
+    ) -> Result<(Url, Vec<SocketAddr>), WebhookError> {
+        let invalid = || WebhookError::InvalidCallback(callback.to_owned());
+        let url = Url::parse(callback).map_err(|_| invalid())?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid());
+        }
+        let host = url.host_str().ok_or_else(invalid)?.to_lowercase();
+        let port = url.port_or_known_default().ok_or_else(invalid)?;
+        // brackets around IPv6 addresses aren't part of the address
+        let addresses = lookup_host((host.trim_matches(['[', ']']), port))
+            .await?
+            .collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Err(invalid());
+        }
+        if !self.allowed_hosts.contains(&host)
+            && !addresses.iter().all(|address| is_public_ip(&address.ip()))
+        {
+            return Err(WebhookError::NonPublicCallback(callback.to_owned()));
+        }
+        Ok((url, addresses))
+    }
+
+    pub fn sign(&self, body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// POST the signed payload to `url`, retrying transient failures with exponential backoff
+    /// and calling `on_attempt` after every attempt. The URL is checked again when it's
+    /// delivered to, and only the addresses it was checked with are connected to, so it can't
+    /// be pointed at another address in between.
+    pub async fn deliver<Payload: Serialize>(
+        &self,
+        url: &str,
+        payload: &Payload,
+        on_attempt: impl Fn(DeliveryAttempt) + Send + Sync + 'static,
+    ) -> Result<(), WebhookError> {
+        let (url, addresses) = self.check_callback(url).await?;
+        let body = serde_json::to_vec(payload)?;
+        let mut client = reqwest::ClientBuilder::new().redirect(Policy::none());
+        if let Some(domain) = url.domain() {
+            client = client.resolve_to_addrs(domain, &addresses);
+        }
+        let client = reqwest_middleware::ClientBuilder::new(client.build()?)
+            .with(RetryTransientMiddleware::new_with_policy(self.retry_policy))
+            .with(AttemptRecorder {
+                on_attempt: Arc::new(on_attempt),
+            })
+            .build();
+        let response = client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, self.sign(&body))
+            .body(body)
+            .send()
+            .await?;
+        match response.status().is_success() {
+            true => Ok(()),
+            false => Err(WebhookError::UnsuccessfulResponse(
+                response.status().as_u16(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::webhook::{is_public_ip, WebhookClient, WebhookError, SIGNATURE_HEADER};
+    use serde_json::json;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use translation::job::DeliveryAttempt;
+
+    fn client(max_retries: u32) -> WebhookClient {
+        WebhookClient::try_new(
+            "secret".to_owned(),
+            max_retries,
+            Duration::from_millis(10),
+            vec!["127.0.0.1".to_owned()],
+        )
+        .unwrap()
+    }
+
+    /// Answer requests with `statuses` in turn, returning the headers and body of each request.
+    fn listen(statuses: Vec<u16>) -> (String, thread::JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/callback", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            statuses
+                .into_iter()
+                .map(|status| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut headers = String::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        headers.push_str(&line.to_lowercase());
+                    }
+                    let length = headers
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .map(|length| length.trim().parse::<usize>().unwrap())
+                        .unwrap_or(0);
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+                    write!(
+                        &stream,
+                        "HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+                    (headers, String::from_utf8(body).unwrap())
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    #[actix_rt::test]
+    async fn it_should_retry_signed_deliveries() {
+        let (url, listener) = listen(vec![503, 200]);
+        let client = client(3);
+        let attempts = Arc::new(Mutex::new(Vec::<DeliveryAttempt>::new()));
+        let recorded = attempts.clone();
+
+        client
+            .deliver(&url, &json!({ "id": "job" }), move |attempt| {
+                recorded.lock().unwrap().push(attempt)
+            })
+            .await
+            .unwrap();
+
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|attempt| attempt.status)
+                .collect::<Vec<_>>(),
+            vec![Some(503), Some(200)]
+        );
+        let requests = listener.join().unwrap();
+        let (headers, body) = &requests[1];
+        assert_eq!(body, r#"{"id":"job"}"#);
+        let signature = client.sign(body.as_bytes());
+        assert!(headers.contains(&format!("{}: {signature}", SIGNATURE_HEADER.to_lowercase())));
+        // HMAC-SHA256 of the body with the key "secret"
+        assert_eq!(
+            signature,
+            "sha256=f0b345ee29eca7f040a0772cafb0c9f7b23474ee5d8c72d03b236e8886580182"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn it_should_give_up_after_the_last_retry() {
+        let (url, listener) = listen(vec![500, 500]);
+        let client = client(1);
+        let result = client.deliver(&url, &json!({}), |_| {}).await;
+        assert!(result.is_err());
+        assert_eq!(listener.join().unwrap().len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn it_should_reject_callbacks_to_non_public_addresses() {
+        let client = WebhookClient::try_new(
+            "secret".to_owned(),
+            40,
+            Duration::from_secs(1),
+            vec!["localhost".to_owned()],
+        )
+        .unwrap();
+        for callback in [
+            "http://127.0.0.1/callback",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.1/callback",
+            "http://[::1]/callback",
+            "http://[fd00:ec2::254]/callback",
+        ] {
+            assert!(
+                matches!(
+                    client.check_callback(callback).await,
+                    Err(WebhookError::NonPublicCallback(_))
+                ),
+                "{callback}"
+            );
+        }
+        assert!(matches!(
+            client.check_callback("ftp://example.com/callback").await,
+            Err(WebhookError::InvalidCallback(_))
+        ));
+        assert!(client
+            .check_callback("http://localhost:8080/callback")
+            .await
+            .is_ok());
+
+        assert!(is_public_ip(&"93.184.216.34".parse().unwrap()));
+        assert!(!is_public_ip(&"100.100.100.200".parse().unwrap()));
+        assert!(!is_public_ip(&"::ffff:192.168.0.1".parse().unwrap()));
+    }
+}
@@ -5,13 +5,21 @@ use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use sse::EventStream;
+use std::sync::Arc;
 use tokio::sync::Semaphore;
 
 const API: &str = "https://api.anthropic.com/v1";
 
+/// The fewest tokens a response is allowed by default, so short prompts can still have
+/// responses longer than themselves.
+const MIN_MAX_TOKENS: usize = 1024;
+
+/// Clones share the limit on parallel requests.
+#[derive(Clone)]
 pub struct ClaudeClient {
     model: ClaudeModel,
-    parallel_requests_semaphore: Semaphore,
+    parallel_requests_semaphore: Arc<Semaphore>,
     client: reqwest_middleware::ClientWithMiddleware,
 }
 
@@ -45,59 +53,329 @@ impl ClaudeClient {
 
         Ok(ClaudeClient {
             model,
-            parallel_requests_semaphore: Semaphore::new(max_parallel_requests),
+            parallel_requests_semaphore: Arc::new(Semaphore::new(max_parallel_requests)),
             client: client_with_middleware,
         })
     }
+
+    /// A client of `model` that shares the limit on parallel requests of this one.
+    pub fn with_model(&self, model: ClaudeModel) -> Self {
+        ClaudeClient {
+            model,
+            ..self.clone()
+        }
+    }
+}
+
+/// A tool the model must call, so that its response is the tool's input following
+/// `input_schema`, see [ClaudeClient::respond_with_tool].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ClaudeTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: serde_json::Value,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
-struct ClaudeContent {
-    #[serde(rename = "text")]
-    text: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeContent {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        input: serde_json::Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl ClaudeContent {
+    /// The text of a text block, or the input of a tool call as JSON.
+    fn into_text(self) -> Option<String> {
+        match self {
+            ClaudeContent::Text { text } => Some(text),
+            ClaudeContent::ToolUse { input } => Some(input.to_string()),
+            ClaudeContent::Other => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaudeStopReason {
+    EndTurn,
+    /// The response was cut off at the maximum number of tokens.
+    MaxTokens,
+    StopSequence,
+    ToolUse,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClaudeUsage {
+    #[serde(default)]
+    pub input_tokens: usize,
+    #[serde(default)]
+    pub output_tokens: usize,
+}
+
+/// The text of a response along with why it stopped and the tokens it used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClaudeMessage {
+    pub text: String,
+    pub stop_reason: Option<ClaudeStopReason>,
+    pub usage: ClaudeUsage,
+}
+
+impl ClaudeMessage {
+    pub fn is_truncated(&self) -> bool {
+        self.stop_reason == Some(ClaudeStopReason::MaxTokens)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct ClaudeResponse {
     content: Vec<ClaudeContent>,
+    stop_reason: Option<ClaudeStopReason>,
+    #[serde(default)]
+    usage: ClaudeUsage,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ClaudeStreamDelta {
+    text: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ClaudeStreamMessage {
+    #[serde(default)]
+    usage: ClaudeUsage,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ClaudeStreamMessageDelta {
+    stop_reason: Option<ClaudeStopReason>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ClaudeStreamError {
+    message: String,
+}
+
+/// The events of a streamed message that matter for its text.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeStreamEvent {
+    MessageStart {
+        message: ClaudeStreamMessage,
+    },
+    MessageDelta {
+        delta: ClaudeStreamMessageDelta,
+        #[serde(default)]
+        usage: ClaudeUsage,
+    },
+    ContentBlockDelta {
+        delta: ClaudeStreamDelta,
+    },
+    Error {
+        error: ClaudeStreamError,
+    },
+    #[serde(other)]
+    Other,
 }
 
 impl ClaudeClient {
-    pub async fn respond_to(
+    /// Enough tokens for a response of about `expected_characters`, twice as many, which covers
+    /// translations into scripts that take several tokens a character, within what the model
+    /// can give.
+    pub fn max_tokens_for(&self, expected_characters: usize) -> usize {
+        (expected_characters * 2).clamp(MIN_MAX_TOKENS, self.model.max_output_tokens())
+    }
+
+    fn message_request(
         &self,
+        system_prompt: String,
         prompt: String,
+        prefill: Option<&str>,
+        tool: Option<ClaudeTool>,
         max_tokens: Option<usize>,
-    ) -> Result<String, ClaudeError> {
-        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
-
-        let url = format!("{API}/messages");
-        let request_json = json!({
+        stream: bool,
+    ) -> serde_json::Value {
+        let max_tokens = max_tokens.unwrap_or_else(|| self.max_tokens_for(prompt.chars().count()));
+        let mut messages = vec![json!({
+            "role": "user",
+            "content": prompt
+        })];
+        if let Some(prefill) = prefill {
+            messages.push(json!({
+                "role": "assistant",
+                "content": prefill
+            }));
+        }
+        let mut request = json!({
             "model": self.model,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": prompt
-                }
-            ],
-            "max_tokens": max_tokens.unwrap_or(4096),
+            "system": system_prompt,
+            "messages": messages,
+            "max_tokens": max_tokens,
+            "stream": stream,
         });
+        if let Some(tool) = tool {
+            request["tool_choice"] = json!({ "type": "tool", "name": tool.name });
+            request["tools"] = json!([tool]);
+        }
+        request
+    }
 
+    async fn send_message(
+        &self,
+        request_json: serde_json::Value,
+    ) -> Result<ClaudeMessage, ClaudeError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
+
+        let url = format!("{API}/messages");
         let response = self.client.post(&url).json(&request_json).send().await?;
         let status = response.status();
         match status {
-            StatusCode::OK => Ok(response
-                .json::<ClaudeResponse>()
-                .await?
-                .content
-                .first()
-                .ok_or(ClaudeError::UnexpectedApiResponse(
-                    "Expected response from claude but there wasn't one.".to_owned(),
-                ))?
-                .clone()
-                .text),
+            StatusCode::OK => {
+                let response = response.json::<ClaudeResponse>().await?;
+                Ok(ClaudeMessage {
+                    text: response
+                        .content
+                        .into_iter()
+                        .find_map(ClaudeContent::into_text)
+                        .ok_or(ClaudeError::UnexpectedApiResponse(
+                            "Expected response from claude but there wasn't one.".to_owned(),
+                        ))?,
+                    stop_reason: response.stop_reason,
+                    usage: response.usage,
+                })
+            }
             _ => Err(ClaudeError::UnexpectedApiResponse(
                 format!("Expected 200 from {url} but got {status}").to_owned(),
             )),
         }
     }
+
+    /// Respond to `prompt`, with at most `max_tokens` or, by default, enough for a translation
+    /// of the prompt.
+    pub async fn respond_to(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        max_tokens: Option<usize>,
+    ) -> Result<ClaudeMessage, ClaudeError> {
+        let request_json =
+            self.message_request(system_prompt, prompt, None, None, max_tokens, false);
+        self.send_message(request_json).await
+    }
+
+    /// Like [Self::respond_to], but the response is the input of a call to `tool`, as JSON.
+    pub async fn respond_with_tool(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        tool: ClaudeTool,
+        max_tokens: Option<usize>,
+    ) -> Result<ClaudeMessage, ClaudeError> {
+        let request_json =
+            self.message_request(system_prompt, prompt, None, Some(tool), max_tokens, false);
+        self.send_message(request_json).await
+    }
+
+    /// Continue a `partial` response to `prompt` that was truncated, responding with the rest
+    /// of it.
+    pub async fn continue_response(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        partial: &str,
+        max_tokens: Option<usize>,
+    ) -> Result<ClaudeMessage, ClaudeError> {
+        // a prefilled response can't end with whitespace
+        let request_json = self.message_request(
+            system_prompt,
+            prompt,
+            Some(partial.trim_end()),
+            None,
+            max_tokens,
+            false,
+        );
+        self.send_message(request_json).await
+    }
+
+    /// Like [Self::respond_to], but streams the response, calling `on_delta` with each part of
+    /// the text as it's generated.
+    pub async fn respond_to_streaming(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        max_tokens: Option<usize>,
+        mut on_delta: impl FnMut(&str),
+    ) -> Result<ClaudeMessage, ClaudeError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
+
+        let url = format!("{API}/messages");
+        let request_json =
+            self.message_request(system_prompt, prompt, None, None, max_tokens, true);
+        let mut response = self.client.post(&url).json(&request_json).send().await?;
+        let status = response.status();
+        if status != StatusCode::OK {
+            return Err(ClaudeError::UnexpectedApiResponse(format!(
+                "Expected 200 from {url} but got {status}"
+            )));
+        }
+
+        let mut message = ClaudeMessage::default();
+        let mut events = EventStream::default();
+        while let Some(chunk) = response.chunk().await? {
+            for data in events.push(&chunk) {
+                match serde_json::from_str::<ClaudeStreamEvent>(&data)? {
+                    ClaudeStreamEvent::MessageStart {
+                        message: ClaudeStreamMessage { usage },
+                    } => message.usage.input_tokens = usage.input_tokens,
+                    // the usage is cumulative
+                    ClaudeStreamEvent::MessageDelta { delta, usage } => {
+                        message.stop_reason = delta.stop_reason.or(message.stop_reason);
+                        message.usage.output_tokens = usage.output_tokens;
+                    }
+                    ClaudeStreamEvent::ContentBlockDelta {
+                        delta: ClaudeStreamDelta { text: Some(delta) },
+                    } => {
+                        on_delta(&delta);
+                        message.text.push_str(&delta);
+                    }
+                    ClaudeStreamEvent::Error { error } => {
+                        return Err(ClaudeError::UnexpectedApiResponse(error.message))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{ClaudeContent, ClaudeResponse};
+
+    #[test]
+    fn it_should_read_the_input_of_a_tool_call_as_text() {
+        let response = serde_json::from_str::<ClaudeResponse>(
+            r#"{
+                "content": [
+                    { "type": "tool_use", "id": "toolu_1", "name": "alternatives", "input": { "translations": [] } }
+                ],
+                "stop_reason": "tool_use"
+            }"#,
+        )
+        .unwrap();
+        let text = response
+            .content
+            .into_iter()
+            .find_map(ClaudeContent::into_text)
+            .unwrap();
+        assert_eq!(text, r#"{"translations":[]}"#);
+    }
 }
@@ -1,6 +1,6 @@
 use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
 
-#[derive(Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
 pub enum ClaudeModel {
     #[serde(rename = "claude-3-5-sonnet-20241022")]
     Claude3Point5Sonnet20241022,
@@ -20,6 +20,16 @@ pub enum ClaudeModel {
     ClaudeInstant1Point2,
 }
 
+impl ClaudeModel {
+    /// The most tokens the model can respond with.
+    pub fn max_output_tokens(&self) -> usize {
+        match self {
+            ClaudeModel::Claude3Point5Sonnet20241022 | ClaudeModel::Claude3Point5Sonnet => 8192,
+            _ => 4096,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::model::ClaudeModel;
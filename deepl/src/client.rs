@@ -5,11 +5,14 @@ use reqwest::StatusCode;
 use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tokio::sync::Semaphore;
 
+/// Clones share the limit on parallel requests.
+#[derive(Clone)]
 pub struct DeepLClient {
     api: String,
-    parallel_requests_semaphore: Semaphore,
+    parallel_requests_semaphore: Arc<Semaphore>,
     client: reqwest_middleware::ClientWithMiddleware,
 }
 
@@ -43,7 +46,7 @@ impl DeepLClient {
 
         Ok(DeepLClient {
             api,
-            parallel_requests_semaphore: Semaphore::new(max_parallel_requests),
+            parallel_requests_semaphore: Arc::new(Semaphore::new(max_parallel_requests)),
             client: client_with_middleware,
         })
     }
@@ -58,6 +61,8 @@ struct DeepLTranslationRequest {
     source_language: Option<String>,
     #[serde(rename = "target_lang")]
     target_language: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -105,6 +110,7 @@ impl DeepLClient {
         texts: Vec<String>,
         source_language: Option<String>,
         target_language: String,
+        context: Option<String>,
     ) -> Result<DeepLTranslationOutput, DeepLError> {
         let _request_permit = self.parallel_requests_semaphore.acquire().await?;
 
@@ -116,6 +122,7 @@ impl DeepLClient {
                 texts,
                 source_language,
                 target_language,
+                context,
             })
             .send()
             .await?;
@@ -134,20 +141,51 @@ impl DeepLClient {
 
 const MAX_TEXTS_PER_REQUEST: u8 = 50;
 
+/// DeepL rejects requests with a body over 128 KiB, so batches leave room for the rest of it.
+const MAX_BATCH_BYTES: usize = 120 * 1024;
+
+/// `texts` in batches of at most `batch_size` texts and [MAX_BATCH_BYTES], unless a text is
+/// larger on its own.
+fn batches(texts: Vec<String>, batch_size: usize) -> Vec<Vec<String>> {
+    let mut batches = Vec::<Vec<String>>::new();
+    let mut batch_bytes = 0;
+    for text in texts {
+        // the quotes and comma around the text in the request
+        let text_bytes = text.len() + 3;
+        match batches.last_mut() {
+            Some(batch)
+                if batch.len() < batch_size && batch_bytes + text_bytes <= MAX_BATCH_BYTES =>
+            {
+                batch_bytes += text_bytes;
+                batch.push(text);
+            }
+            _ => {
+                batch_bytes = text_bytes;
+                batches.push(vec![text]);
+            }
+        }
+    }
+    batches
+}
+
 impl DeepLClient {
+    /// Translate `texts`, all with the same `context` which DeepL uses to pick the right
+    /// translation without translating it.
     pub async fn translate(
         &self,
         texts: Vec<String>,
         source_language: Option<String>,
         target_language: String,
+        context: Option<String>,
     ) -> Result<DeepLTranslationOutput, DeepLError> {
-        let translation_futures = texts
-            .chunks(texts.len().div_ceil(MAX_TEXTS_PER_REQUEST as usize))
-            .map(|chunk| {
+        let translation_futures = batches(texts, MAX_TEXTS_PER_REQUEST as usize)
+            .into_iter()
+            .map(|batch| {
                 self.translate_batch(
-                    chunk.to_vec(),
+                    batch,
                     source_language.clone(),
                     target_language.clone(),
+                    context.clone(),
                 )
             })
             .collect_vec();
@@ -189,3 +227,30 @@ impl DeepLClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{batches, MAX_BATCH_BYTES, MAX_TEXTS_PER_REQUEST};
+
+    #[test]
+    fn it_should_batch_texts_within_the_request_limits() {
+        let texts = (0..2600).map(|index| index.to_string()).collect::<Vec<_>>();
+
+        let batched = batches(texts.clone(), MAX_TEXTS_PER_REQUEST as usize);
+
+        assert_eq!(batched.len(), 52);
+        assert!(batched
+            .iter()
+            .all(|batch| batch.len() <= MAX_TEXTS_PER_REQUEST as usize));
+        assert_eq!(batched.concat(), texts);
+
+        let long = vec!["a".repeat(MAX_BATCH_BYTES / 2); 3];
+        assert_eq!(
+            batches(long, MAX_TEXTS_PER_REQUEST as usize)
+                .iter()
+                .map(Vec::len)
+                .collect::<Vec<_>>(),
+            vec![1, 1, 1]
+        );
+    }
+}
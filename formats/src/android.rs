@@ -0,0 +1,311 @@
+use crate::error::FormatError;
+use crate::resource::{ResourceEntry, ResourceFormat, ResourceValue};
+use itertools::Itertools;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use translation::placeholder::{tokenize, Token};
+use translation::Language;
+
+const INDENT: &str = "    ";
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, FormatError> {
+    Ok(element
+        .try_get_attribute(name)?
+        .map(|attribute| attribute.unescape_value())
+        .transpose()?
+        .map(|value| value.into_owned()))
+}
+
+fn is_translatable(element: &BytesStart) -> Result<bool, FormatError> {
+    Ok(attribute(element, "translatable")?.as_deref() != Some("false"))
+}
+
+/// Remove Android's backslash escapes and the double quotes that preserve whitespace from the
+/// text of a resource, leaving its markup and XML entities alone.
+fn unescape(raw: &str) -> String {
+    tokenize(raw.trim())
+        .into_iter()
+        .map(|token| match token {
+            Token::Placeholder(placeholder) => placeholder.to_owned(),
+            Token::Text(text) => {
+                let mut unescaped = String::with_capacity(text.len());
+                let mut chars = text.chars();
+                while let Some(char) = chars.next() {
+                    match char {
+                        '\\' => match chars.next() {
+                            Some('n') => unescaped.push('\n'),
+                            Some('t') => unescaped.push('\t'),
+                            Some(other) => unescaped.push(other),
+                            None => {}
+                        },
+                        '"' => {}
+                        char => unescaped.push(char),
+                    }
+                }
+                unescaped
+            }
+        })
+        .collect()
+}
+
+/// Escape translated text for a resource, the reverse of [unescape].
+fn escape_text(text: &str) -> String {
+    let escaped: String = tokenize(text)
+        .into_iter()
+        .map(|token| match token {
+            Token::Placeholder(placeholder) => placeholder.to_owned(),
+            Token::Text(text) => text
+                .replace('\\', "\\\\")
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('\'', "\\'")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\t', "\\t"),
+        })
+        .collect();
+    match escaped.chars().next() {
+        Some('@' | '?') => format!("\\{escaped}"),
+        _ => escaped,
+    }
+}
+
+fn is_reference(text: &str) -> bool {
+    text.starts_with('@') || text.starts_with('?')
+}
+
+enum Container {
+    Plurals(Vec<(String, String)>),
+    Array(Vec<String>),
+}
+
+/// An Android `strings.xml` resource file. Resources marked `translatable="false"` and
+/// references to other resources aren't translated.
+pub struct AndroidStrings {
+    entries: Vec<ResourceEntry>,
+}
+
+impl AndroidStrings {
+    pub fn parse(document: &str) -> Result<Self, FormatError> {
+        let mut reader = Reader::from_str(document);
+        reader.config_mut().trim_text(false);
+
+        let mut entries = Vec::new();
+        let mut comment = None::<String>;
+        let mut container = None::<(String, bool, Container)>;
+        loop {
+            match reader.read_event()? {
+                Event::Comment(text) if container.is_none() => {
+                    comment = Some(String::from_utf8_lossy(&text).trim().to_owned());
+                }
+                Event::Start(element) => match element.name().as_ref() {
+                    b"string" => {
+                        let name = attribute(&element, "name")?;
+                        let translatable = is_translatable(&element)?;
+                        let text = unescape(&reader.read_text(element.name())?);
+                        let comment = comment.take();
+                        if let Some(name) = name.filter(|_| translatable) {
+                            if !text.trim().is_empty() && !is_reference(&text) {
+                                entries.push(ResourceEntry {
+                                    key: name,
+                                    comment,
+                                    value: ResourceValue::Text(text),
+                                });
+                            }
+                        }
+                    }
+                    b"plurals" => {
+                        let name = attribute(&element, "name")?.unwrap_or_default();
+                        let translatable = is_translatable(&element)?;
+                        container = Some((name, translatable, Container::Plurals(Vec::new())));
+                    }
+                    b"string-array" => {
+                        let name = attribute(&element, "name")?.unwrap_or_default();
+                        let translatable = is_translatable(&element)?;
+                        container = Some((name, translatable, Container::Array(Vec::new())));
+                    }
+                    b"item" => {
+                        let quantity = attribute(&element, "quantity")?;
+                        let text = unescape(&reader.read_text(element.name())?);
+                        match (container.as_mut(), quantity) {
+                            (Some((_, _, Container::Plurals(forms))), Some(quantity)) => {
+                                forms.push((quantity, text))
+                            }
+                            (Some((_, _, Container::Array(items))), _) => items.push(text),
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                },
+                Event::End(element)
+                    if matches!(element.name().as_ref(), b"plurals" | b"string-array") =>
+                {
+                    let comment = comment.take();
+                    let value = match container.take() {
+                        Some((name, true, Container::Plurals(forms))) if !forms.is_empty() => {
+                            Some((name, ResourceValue::Plural(forms)))
+                        }
+                        Some((name, true, Container::Array(items)))
+                            if !items.is_empty()
+                                && !items.iter().any(|item| is_reference(item)) =>
+                        {
+                            Some((name, ResourceValue::Array(items)))
+                        }
+                        _ => None,
+                    };
+                    if let Some((key, value)) = value {
+                        entries.push(ResourceEntry {
+                            key,
+                            comment,
+                            value,
+                        });
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(AndroidStrings { entries })
+    }
+}
+
+impl ResourceFormat for AndroidStrings {
+    fn entries(&self) -> &[ResourceEntry] {
+        &self.entries
+    }
+
+    fn write(&self, translations: &[Option<ResourceValue>], _: &Language) -> String {
+        let mut lines = vec![
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>".to_owned(),
+            "<resources>".to_owned(),
+        ];
+        for (entry, translation) in self.entries.iter().zip(translations) {
+            let Some(translation) = translation else {
+                continue;
+            };
+            if let Some(comment) = &entry.comment {
+                lines.push(format!("{INDENT}<!-- {comment} -->"));
+            }
+            let name = escape(&entry.key);
+            match translation {
+                ResourceValue::Text(text) => lines.push(format!(
+                    "{INDENT}<string name=\"{name}\">{}</string>",
+                    escape_text(text)
+                )),
+                ResourceValue::Plural(forms) => {
+                    lines.push(format!("{INDENT}<plurals name=\"{name}\">"));
+                    lines.extend(forms.iter().map(|(quantity, text)| {
+                        format!(
+                            "{INDENT}{INDENT}<item quantity=\"{quantity}\">{}</item>",
+                            escape_text(text)
+                        )
+                    }));
+                    lines.push(format!("{INDENT}</plurals>"));
+                }
+                ResourceValue::Array(items) => {
+                    lines.push(format!("{INDENT}<string-array name=\"{name}\">"));
+                    lines.extend(
+                        items.iter().map(|text| {
+                            format!("{INDENT}{INDENT}<item>{}</item>", escape_text(text))
+                        }),
+                    );
+                    lines.push(format!("{INDENT}</string-array>"));
+                }
+            }
+        }
+        lines.push("</resources>".to_owned());
+        lines.iter().join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::android::AndroidStrings;
+    use crate::resource::ResourceTranslation;
+    use std::str::FromStr;
+    use translation::{Language, TranslationOutput};
+
+    const STRINGS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<resources xmlns:xliff="urn:oasis:names:tc:xliff:document:1.2">
+    <string name="app_name" translatable="false">Acme</string>
+    <!-- Greeting on the home screen -->
+    <string name="greeting">Hello, %1$s! Don\'t <b>forget</b> &amp; go</string>
+    <string name="alias">@string/greeting</string>
+    <plurals name="files">
+        <item quantity="one">%d file</item>
+        <item quantity="other">%d files</item>
+    </plurals>
+    <string-array name="planets">
+        <item>Mercury</item>
+        <item>"Venus "</item>
+    </string-array>
+</resources>
+"#;
+
+    #[test]
+    fn it_should_translate_android_strings() {
+        let translation = ResourceTranslation::new(
+            AndroidStrings::parse(STRINGS).unwrap(),
+            Language::from_str("ru").unwrap(),
+        );
+        let inputs = translation.inputs(None);
+        assert_eq!(
+            inputs.iter().map(|input| input.text()).collect::<Vec<_>>(),
+            vec![
+                "Hello, %1$s! Don't <b>forget</b> &amp; go",
+                "%d file",
+                "%d files",
+                "Mercury",
+                "Venus ",
+            ]
+        );
+
+        let translated = translation.apply(&[
+            TranslationOutput::new(
+                "Привет, %1$s! Не <b>забудь</b> &amp; иди's".to_owned(),
+                None,
+            ),
+            TranslationOutput::new("%d файл".to_owned(), None),
+            TranslationOutput::new("%d файлов".to_owned(), None),
+            TranslationOutput::new("Меркурий".to_owned(), None),
+            TranslationOutput::new("Венера".to_owned(), None),
+        ]);
+        assert_eq!(translated.untranslated, 0);
+        assert_eq!(
+            translated.document,
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<resources>
+    <!-- Greeting on the home screen -->
+    <string name="greeting">Привет, %1$s! Не <b>забудь</b> &amp; иди\'s</string>
+    <plurals name="files">
+        <item quantity="one">%d файл</item>
+        <item quantity="few">%d файлов</item>
+        <item quantity="many">%d файлов</item>
+        <item quantity="other">%d файлов</item>
+    </plurals>
+    <string-array name="planets">
+        <item>Меркурий</item>
+        <item>Венера</item>
+    </string-array>
+</resources>
+"#
+        );
+    }
+
+    #[test]
+    fn it_should_leave_out_translations_that_change_format_specifiers() {
+        let translation = ResourceTranslation::new(
+            AndroidStrings::parse(
+                r#"<resources><string name="greeting">Hello, %1$s</string></resources>"#,
+            )
+            .unwrap(),
+            Language::from_str("fr").unwrap(),
+        );
+        let translated =
+            translation.apply(&[TranslationOutput::new("Bonjour, %s".to_owned(), None)]);
+        assert_eq!(translated.untranslated, 1);
+        assert!(!translated.document.contains("greeting"));
+    }
+}
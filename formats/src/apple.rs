@@ -0,0 +1,760 @@
+use crate::error::FormatError;
+use crate::plural::PLURAL_CATEGORIES;
+use crate::resource::{ResourceEntry, ResourceFormat, ResourceValue};
+use itertools::Itertools;
+use quick_xml::escape::{escape, unescape};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::{json, Map, Value};
+use std::iter::Peekable;
+use std::str::CharIndices;
+use translation::placeholder::{tokenize, Token};
+use translation::Language;
+
+const FORMAT_KEY: &str = "NSStringLocalizedFormatKey";
+const SPEC_TYPE_KEY: &str = "NSStringFormatSpecTypeKey";
+const PLURAL_RULE_TYPE: &str = "NSStringPluralRuleType";
+
+fn unescape_string(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('U' | 'u') => {
+                let code = chars.by_ref().take(4).collect::<String>();
+                u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .into_iter()
+                    .for_each(|char| unescaped.push(char));
+            }
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+fn escape_string(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+struct StringsParser<'a> {
+    document: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> StringsParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .next_if(|(_, char)| char.is_whitespace())
+            .is_some()
+        {}
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.document.len(), |(index, _)| *index)
+    }
+
+    fn error(&mut self, expected: &str) -> FormatError {
+        let position = self.position();
+        let line = self.document[..position].lines().count().max(1);
+        FormatError::InvalidDocument(format!("Expected {expected} on line {line}"))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), FormatError> {
+        self.skip_whitespace();
+        match self.chars.next_if(|(_, char)| *char == expected) {
+            Some(_) => Ok(()),
+            None => Err(self.error(&format!("'{expected}'"))),
+        }
+    }
+
+    /// A comment, if there's one next.
+    fn comment(&mut self) -> Option<String> {
+        let start = self.position();
+        let rest = &self.document[start..];
+        let (comment, length) = if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment.find("*/").map_or(comment.len(), |end| end + 2);
+            (comment[..end].trim_end_matches("*/"), end + 2)
+        } else {
+            let comment = rest.strip_prefix("//")?;
+            let end = comment.find('\n').unwrap_or(comment.len());
+            (&comment[..end], end + 2)
+        };
+        while self
+            .chars
+            .next_if(|(index, _)| *index < start + length)
+            .is_some()
+        {}
+        Some(comment.trim().to_owned())
+    }
+
+    /// A quoted string, or an unquoted word which old style property lists allow.
+    fn string(&mut self) -> Result<String, FormatError> {
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, char)| *char == '"').is_none() {
+            let start = self.position();
+            while self
+                .chars
+                .next_if(|(_, char)| char.is_alphanumeric() || matches!(char, '_' | '.' | '-'))
+                .is_some()
+            {}
+            let end = self.position();
+            return match end > start {
+                true => Ok(self.document[start..end].to_owned()),
+                false => Err(self.error("a string")),
+            };
+        }
+        let start = self.position();
+        let mut escaped = false;
+        for (index, char) in self.chars.by_ref() {
+            match char {
+                '"' if !escaped => return Ok(unescape_string(&self.document[start..index])),
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+        }
+        Err(self.error("a closing '\"'"))
+    }
+}
+
+/// An Apple `.strings` file, in UTF-8.
+pub struct AppleStrings {
+    entries: Vec<ResourceEntry>,
+}
+
+impl AppleStrings {
+    pub fn parse(document: &str) -> Result<Self, FormatError> {
+        let document = document.trim_start_matches('\u{feff}');
+        let mut parser = StringsParser {
+            document,
+            chars: document.char_indices().peekable(),
+        };
+        let mut entries = Vec::new();
+        let mut comment = None;
+        loop {
+            parser.skip_whitespace();
+            if parser.chars.peek().is_none() {
+                break;
+            }
+            if let Some(next_comment) = parser.comment() {
+                comment = Some(next_comment);
+                continue;
+            }
+            let key = parser.string()?;
+            parser.expect('=')?;
+            let value = parser.string()?;
+            parser.expect(';')?;
+            let comment = comment.take();
+            if !value.trim().is_empty() {
+                entries.push(ResourceEntry {
+                    key,
+                    comment,
+                    value: ResourceValue::Text(value),
+                });
+            }
+        }
+        Ok(AppleStrings { entries })
+    }
+}
+
+impl ResourceFormat for AppleStrings {
+    fn entries(&self) -> &[ResourceEntry] {
+        &self.entries
+    }
+
+    fn write(&self, translations: &[Option<ResourceValue>], _: &Language) -> String {
+        self.entries
+            .iter()
+            .zip(translations)
+            .filter_map(|(entry, translation)| match translation {
+                Some(ResourceValue::Text(text)) => {
+                    let comment = entry
+                        .comment
+                        .as_ref()
+                        .map(|comment| format!("/* {comment} */\n"))
+                        .unwrap_or_default();
+                    Some(format!(
+                        "{comment}\"{}\" = \"{}\";\n",
+                        escape_string(&entry.key),
+                        escape_string(text)
+                    ))
+                }
+                _ => None,
+            })
+            .join("\n")
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PlistValue {
+    Dict(Vec<(String, PlistValue)>),
+    String(String),
+    /// Any other value, as it appeared in the document.
+    Other(String),
+}
+
+impl PlistValue {
+    fn get(&self, key: &str) -> Option<&PlistValue> {
+        match self {
+            PlistValue::Dict(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            PlistValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn write(&self, indent: usize, lines: &mut Vec<String>) {
+        let tabs = "\t".repeat(indent);
+        match self {
+            PlistValue::Dict(entries) => {
+                lines.push(format!("{tabs}<dict>"));
+                for (key, value) in entries {
+                    lines.push(format!("{tabs}\t<key>{}</key>", escape(key)));
+                    value.write(indent + 1, lines);
+                }
+                lines.push(format!("{tabs}</dict>"));
+            }
+            PlistValue::String(value) => {
+                lines.push(format!("{tabs}<string>{}</string>", escape(value)))
+            }
+            PlistValue::Other(raw) => lines.push(format!("{tabs}{raw}")),
+        }
+    }
+}
+
+fn read_plist_value(
+    document: &str,
+    reader: &mut Reader<&[u8]>,
+    element: &BytesStart,
+    start: usize,
+) -> Result<PlistValue, FormatError> {
+    match element.name().as_ref() {
+        b"dict" => {
+            let mut entries = Vec::new();
+            let mut key = None::<String>;
+            loop {
+                let value_start = reader.buffer_position() as usize;
+                match reader.read_event()? {
+                    Event::Start(child) if child.name().as_ref() == b"key" => {
+                        key = Some(
+                            unescape(&reader.read_text(child.name())?)
+                                .map_err(quick_xml::Error::from)?
+                                .into_owned(),
+                        );
+                    }
+                    Event::Start(child) => {
+                        let value = read_plist_value(document, reader, &child, value_start)?;
+                        entries.push((key.take().unwrap_or_default(), value));
+                    }
+                    Event::Empty(_) => {
+                        let end = reader.buffer_position() as usize;
+                        let raw = document[value_start..end].to_owned();
+                        entries.push((key.take().unwrap_or_default(), PlistValue::Other(raw)));
+                    }
+                    Event::End(_) => return Ok(PlistValue::Dict(entries)),
+                    Event::Eof => {
+                        return Err(FormatError::InvalidDocument("Unclosed dict".to_owned()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        b"string" => Ok(PlistValue::String(
+            unescape(&reader.read_text(element.name())?)
+                .map_err(quick_xml::Error::from)?
+                .into_owned(),
+        )),
+        _ => {
+            reader.read_to_end(element.name())?;
+            let end = reader.buffer_position() as usize;
+            Ok(PlistValue::Other(document[start..end].to_owned()))
+        }
+    }
+}
+
+fn has_text(format: &str) -> bool {
+    tokenize(format).into_iter().any(|token| match token {
+        Token::Text(text) => text.chars().any(char::is_alphabetic),
+        Token::Placeholder(_) => false,
+    })
+}
+
+/// Where the value of an entry comes from in a stringsdict: the format of a key, or one of
+/// its plural variables.
+struct StringsDictLocation {
+    key: usize,
+    variable: Option<String>,
+}
+
+/// An Apple `.stringsdict` property list of plural rules.
+pub struct AppleStringsDict {
+    keys: Vec<(String, PlistValue)>,
+    entries: Vec<ResourceEntry>,
+    locations: Vec<StringsDictLocation>,
+}
+
+impl AppleStringsDict {
+    pub fn parse(document: &str) -> Result<Self, FormatError> {
+        let mut reader = Reader::from_str(document);
+        reader.config_mut().trim_text(false);
+        let root = loop {
+            let start = reader.buffer_position() as usize;
+            match reader.read_event()? {
+                Event::Start(element) if element.name().as_ref() == b"dict" => {
+                    break read_plist_value(document, &mut reader, &element, start)?;
+                }
+                Event::Eof => {
+                    return Err(FormatError::InvalidDocument("Missing dict".to_owned()));
+                }
+                _ => {}
+            }
+        };
+        let PlistValue::Dict(keys) = root else {
+            return Err(FormatError::InvalidDocument("Missing dict".to_owned()));
+        };
+
+        let mut entries = Vec::new();
+        let mut locations = Vec::new();
+        for (index, (key, value)) in keys.iter().enumerate() {
+            let PlistValue::Dict(rules) = value else {
+                continue;
+            };
+            if let Some(format) = value
+                .get(FORMAT_KEY)
+                .and_then(PlistValue::as_str)
+                .filter(|format| has_text(format))
+            {
+                entries.push(ResourceEntry {
+                    key: key.clone(),
+                    comment: None,
+                    value: ResourceValue::Text(format.to_owned()),
+                });
+                locations.push(StringsDictLocation {
+                    key: index,
+                    variable: None,
+                });
+            }
+            for (variable, rule) in rules {
+                if rule.get(SPEC_TYPE_KEY).and_then(PlistValue::as_str) != Some(PLURAL_RULE_TYPE) {
+                    continue;
+                }
+                let PlistValue::Dict(forms) = rule else {
+                    continue;
+                };
+                let forms = forms
+                    .iter()
+                    .filter(|(category, _)| PLURAL_CATEGORIES.contains(&category.as_str()))
+                    .filter_map(|(category, text)| {
+                        text.as_str()
+                            .map(|text| (category.clone(), text.to_owned()))
+                    })
+                    .collect_vec();
+                if !forms.is_empty() {
+                    entries.push(ResourceEntry {
+                        key: format!("{key}.{variable}"),
+                        comment: None,
+                        value: ResourceValue::Plural(forms),
+                    });
+                    locations.push(StringsDictLocation {
+                        key: index,
+                        variable: Some(variable.clone()),
+                    });
+                }
+            }
+        }
+        Ok(AppleStringsDict {
+            keys,
+            entries,
+            locations,
+        })
+    }
+}
+
+impl ResourceFormat for AppleStringsDict {
+    fn entries(&self) -> &[ResourceEntry] {
+        &self.entries
+    }
+
+    fn write(&self, translations: &[Option<ResourceValue>], _: &Language) -> String {
+        let mut keys = self.keys.clone();
+        let mut complete = vec![true; keys.len()];
+        for (location, translation) in self.locations.iter().zip(translations) {
+            let Some(translation) = translation else {
+                complete[location.key] = false;
+                continue;
+            };
+            let PlistValue::Dict(rules) = &mut keys[location.key].1 else {
+                continue;
+            };
+            let name = location.variable.as_deref().unwrap_or(FORMAT_KEY);
+            let Some((_, value)) = rules.iter_mut().find(|(key, _)| key == name) else {
+                continue;
+            };
+            match (value, translation) {
+                (value, ResourceValue::Text(text)) => *value = PlistValue::String(text.clone()),
+                (PlistValue::Dict(rule), ResourceValue::Plural(forms)) => {
+                    rule.retain(|(key, _)| !PLURAL_CATEGORIES.contains(&key.as_str()));
+                    rule.extend(forms.iter().map(|(category, text)| {
+                        (category.clone(), PlistValue::String(text.clone()))
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        let root = PlistValue::Dict(
+            keys.into_iter()
+                .zip(complete)
+                .filter(|(_, complete)| *complete)
+                .map(|(key, _)| key)
+                .collect_vec(),
+        );
+        let mut lines = vec![
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_owned(),
+            "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">".to_owned(),
+            "<plist version=\"1.0\">".to_owned(),
+        ];
+        root.write(0, &mut lines);
+        lines.push("</plist>".to_owned());
+        lines.iter().join("\n") + "\n"
+    }
+}
+
+const SOURCE_LANGUAGE_KEY: &str = "sourceLanguage";
+const NEW_STATE: &str = "new";
+/// Machine translations need reviewing before they're marked as translated.
+const NEEDS_REVIEW_STATE: &str = "needs_review";
+
+fn string_unit(value: &str) -> Value {
+    json!({ "stringUnit": { "state": NEEDS_REVIEW_STATE, "value": value } })
+}
+
+fn string_unit_value(localization: &Value) -> Option<&str> {
+    localization
+        .pointer("/stringUnit/value")
+        .and_then(Value::as_str)
+}
+
+/// Whether a localization has a value for every string unit that isn't new.
+fn is_localized(localization: &Value) -> bool {
+    match localization {
+        Value::Object(map) => match map.get("stringUnit") {
+            Some(unit) => {
+                unit.get("state").and_then(Value::as_str) != Some(NEW_STATE)
+                    && unit
+                        .get("value")
+                        .and_then(Value::as_str)
+                        .is_some_and(|value| !value.is_empty())
+            }
+            None => !map.is_empty() && map.values().all(is_localized),
+        },
+        _ => false,
+    }
+}
+
+/// An Xcode `.xcstrings` string catalog, which has every language in the one file.
+/// Translations are added for the target language and marked for review.
+pub struct AppleStringCatalog {
+    catalog: Value,
+    entries: Vec<ResourceEntry>,
+}
+
+impl AppleStringCatalog {
+    pub fn parse(document: &str, target_language: &Language) -> Result<Self, FormatError> {
+        let catalog = serde_json::from_str::<Value>(document)
+            .map_err(|error| FormatError::InvalidDocument(error.to_string()))?;
+        let source_language = catalog
+            .get(SOURCE_LANGUAGE_KEY)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        let target = target_language.to_string();
+        let strings = catalog
+            .get("strings")
+            .and_then(Value::as_object)
+            .ok_or(FormatError::InvalidDocument("Missing strings".to_owned()))?;
+
+        let entries = strings
+            .iter()
+            .filter(|(_, string)| string.get("shouldTranslate") != Some(&Value::Bool(false)))
+            .filter(|(_, string)| {
+                !string
+                    .get("localizations")
+                    .and_then(|localizations| localizations.get(&target))
+                    .is_some_and(is_localized)
+            })
+            .filter_map(|(key, string)| {
+                let source = string
+                    .get("localizations")
+                    .and_then(|localizations| localizations.get(&source_language));
+                let value = match source {
+                    None => ResourceValue::Text(key.clone()),
+                    Some(source) => match source.pointer("/variations/plural") {
+                        Some(Value::Object(forms)) => ResourceValue::Plural(
+                            forms
+                                .iter()
+                                .filter_map(|(category, form)| {
+                                    string_unit_value(form)
+                                        .map(|text| (category.clone(), text.to_owned()))
+                                })
+                                .collect_vec(),
+                        ),
+                        Some(_) => return None,
+                        None => ResourceValue::Text(string_unit_value(source)?.to_owned()),
+                    },
+                };
+                let is_empty = match &value {
+                    ResourceValue::Text(text) => text.trim().is_empty(),
+                    ResourceValue::Plural(forms) => forms.is_empty(),
+                    ResourceValue::Array(items) => items.is_empty(),
+                };
+                (!is_empty).then(|| ResourceEntry {
+                    key: key.clone(),
+                    comment: string
+                        .get("comment")
+                        .and_then(Value::as_str)
+                        .map(str::to_owned),
+                    value,
+                })
+            })
+            .collect_vec();
+        Ok(AppleStringCatalog { catalog, entries })
+    }
+}
+
+impl ResourceFormat for AppleStringCatalog {
+    fn entries(&self) -> &[ResourceEntry] {
+        &self.entries
+    }
+
+    fn write(&self, translations: &[Option<ResourceValue>], target_language: &Language) -> String {
+        let mut catalog = self.catalog.clone();
+        let target = target_language.to_string();
+        for (entry, translation) in self.entries.iter().zip(translations) {
+            let localization = match translation {
+                Some(ResourceValue::Text(text)) => string_unit(text),
+                Some(ResourceValue::Plural(forms)) => {
+                    let forms = forms
+                        .iter()
+                        .map(|(category, text)| (category.clone(), string_unit(text)))
+                        .collect::<Map<_, _>>();
+                    json!({ "variations": { "plural": forms } })
+                }
+                _ => continue,
+            };
+            let Some(string) = catalog
+                .get_mut("strings")
+                .and_then(|strings| strings.get_mut(&entry.key))
+                .and_then(Value::as_object_mut)
+            else {
+                continue;
+            };
+            if let Some(localizations) = string
+                .entry("localizations")
+                .or_insert(json!({}))
+                .as_object_mut()
+            {
+                localizations.insert(target.clone(), localization);
+            }
+        }
+        serde_json::to_string_pretty(&catalog).unwrap_or_default() + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apple::{AppleStringCatalog, AppleStrings, AppleStringsDict};
+    use crate::resource::ResourceTranslation;
+    use std::str::FromStr;
+    use translation::{Language, TranslationOutput};
+
+    #[test]
+    fn it_should_translate_strings_files() {
+        let strings = r#"/* Title of the save button */
+"save" = "Save";
+
+// Greeting
+"greeting" = "Hello, %@! \"Welcome\"\n";
+"empty" = "";
+"#;
+        let translation = ResourceTranslation::new(
+            AppleStrings::parse(strings).unwrap(),
+            Language::from_str("fr").unwrap(),
+        );
+        let inputs = translation.inputs(None);
+        assert_eq!(
+            inputs.iter().map(|input| input.text()).collect::<Vec<_>>(),
+            vec!["Save", "Hello, %@! \"Welcome\"\n"]
+        );
+
+        let translated = translation.apply(&[
+            TranslationOutput::new("Enregistrer".to_owned(), None),
+            TranslationOutput::new("Bonjour, %@ ! « Bienvenue »\n".to_owned(), None),
+        ]);
+        assert_eq!(
+            translated.document,
+            r#"/* Title of the save button */
+"save" = "Enregistrer";
+
+/* Greeting */
+"greeting" = "Bonjour, %@ ! « Bienvenue »\n";
+"#
+        );
+    }
+
+    const STRINGSDICT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>files_remaining</key>
+	<dict>
+		<key>NSStringLocalizedFormatKey</key>
+		<string>%#@files@ remaining</string>
+		<key>files</key>
+		<dict>
+			<key>NSStringFormatSpecTypeKey</key>
+			<string>NSStringPluralRuleType</string>
+			<key>NSStringFormatValueTypeKey</key>
+			<string>d</string>
+			<key>one</key>
+			<string>%d file</string>
+			<key>other</key>
+			<string>%d files</string>
+		</dict>
+	</dict>
+</dict>
+</plist>
+"#;
+
+    #[test]
+    fn it_should_translate_stringsdict_files() {
+        let translation = ResourceTranslation::new(
+            AppleStringsDict::parse(STRINGSDICT).unwrap(),
+            Language::from_str("pl").unwrap(),
+        );
+        let inputs = translation.inputs(None);
+        assert_eq!(
+            inputs.iter().map(|input| input.text()).collect::<Vec<_>>(),
+            vec!["%#@files@ remaining", "%d file", "%d files"]
+        );
+
+        let translated = translation.apply(&[
+            TranslationOutput::new("Pozostało: %#@files@".to_owned(), None),
+            TranslationOutput::new("%d plik".to_owned(), None),
+            TranslationOutput::new("%d plików".to_owned(), None),
+        ]);
+        assert_eq!(translated.untranslated, 0);
+        assert_eq!(
+            translated.document,
+            STRINGSDICT
+                .replace("%#@files@ remaining", "Pozostało: %#@files@")
+                .replace(
+                    "\t\t\t<key>one</key>\n\t\t\t<string>%d file</string>\n\t\t\t<key>other</key>\n\t\t\t<string>%d files</string>",
+                    "\t\t\t<key>one</key>\n\t\t\t<string>%d plik</string>\n\t\t\t<key>few</key>\n\t\t\t<string>%d plików</string>\n\t\t\t<key>many</key>\n\t\t\t<string>%d plików</string>\n\t\t\t<key>other</key>\n\t\t\t<string>%d plików</string>"
+                )
+        );
+    }
+
+    #[test]
+    fn it_should_leave_out_keys_with_broken_variables() {
+        let translation = ResourceTranslation::new(
+            AppleStringsDict::parse(STRINGSDICT).unwrap(),
+            Language::from_str("pl").unwrap(),
+        );
+        let translated = translation.apply(&[
+            TranslationOutput::new("Pozostało: %#@pliki@".to_owned(), None),
+            TranslationOutput::new("%d plik".to_owned(), None),
+            TranslationOutput::new("%d plików".to_owned(), None),
+        ]);
+        assert_eq!(translated.untranslated, 1);
+        assert!(!translated.document.contains("files_remaining"));
+    }
+
+    #[test]
+    fn it_should_translate_string_catalogs() {
+        let catalog = serde_json::json!({
+            "sourceLanguage": "en",
+            "strings": {
+                "Acme": { "shouldTranslate": false },
+                "Hello %@": { "comment": "Greeting" },
+                "Done": {
+                    "localizations": {
+                        "fr": { "stringUnit": { "state": "translated", "value": "Terminé" } }
+                    }
+                },
+                "%lld files": {
+                    "localizations": {
+                        "en": {
+                            "variations": {
+                                "plural": {
+                                    "one": { "stringUnit": { "state": "translated", "value": "%lld file" } },
+                                    "other": { "stringUnit": { "state": "translated", "value": "%lld files" } }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "version": "1.0"
+        })
+        .to_string();
+        let french = Language::from_str("fr").unwrap();
+        let translation = ResourceTranslation::new(
+            AppleStringCatalog::parse(&catalog, &french).unwrap(),
+            french,
+        );
+        let inputs = translation.inputs(None);
+        assert_eq!(
+            inputs.iter().map(|input| input.text()).collect::<Vec<_>>(),
+            vec!["Hello %@", "%lld file", "%lld files"]
+        );
+        assert_eq!(inputs[0].hints(), ["Greeting", "Key: Hello %@"]);
+
+        let translated = translation.apply(&[
+            TranslationOutput::new("Bonjour %@".to_owned(), None),
+            TranslationOutput::new("%lld fichier".to_owned(), None),
+            TranslationOutput::new("%lld fichiers".to_owned(), None),
+        ]);
+        let catalog = serde_json::from_str::<serde_json::Value>(&translated.document).unwrap();
+        assert_eq!(
+            catalog.pointer("/strings/Hello %@/localizations/fr/stringUnit"),
+            Some(&serde_json::json!({ "state": "needs_review", "value": "Bonjour %@" }))
+        );
+        assert_eq!(
+            catalog.pointer(
+                "/strings/%lld files/localizations/fr/variations/plural/many/stringUnit/value"
+            ),
+            Some(&serde_json::json!("%lld fichiers"))
+        );
+        assert_eq!(
+            catalog.pointer("/strings/Done/localizations/fr/stringUnit/value"),
+            Some(&serde_json::json!("Terminé"))
+        );
+        assert!(catalog.pointer("/strings/Acme/localizations").is_none());
+    }
+}
@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error("XmlError {0}")]
+    XmlError(#[from] quick_xml::Error),
+    #[error("CsvError {0}")]
+    CsvError(#[from] csv::Error),
+    #[error("Invalid document {0}")]
+    InvalidDocument(String),
+}
@@ -0,0 +1,335 @@
+use crate::plural::{plural_categories, ONE_CATEGORY, OTHER_CATEGORY, PLURAL_CATEGORIES};
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use translation::placeholder::placeholders;
+use translation::{Language, TranslationInput, TranslationOutput};
+
+fn plural_base(key: &str) -> Option<(&str, &str)> {
+    let (base, category) = key.rsplit_once('_')?;
+    PLURAL_CATEGORIES
+        .contains(&category)
+        .then_some((base, category))
+}
+
+/// Interpolations (`{{name}}`), nested translations (`$t(key)`) and markup of a string.
+fn interpolations(text: &str) -> Vec<&str> {
+    let mut interpolations = placeholders(text);
+    let mut rest = text;
+    while let Some(start) = rest.find("$t(") {
+        let Some(end) = rest[start..].find(')') else {
+            break;
+        };
+        interpolations.push(&rest[start..start + end + 1]);
+        rest = &rest[start + end + 1..];
+    }
+    interpolations.sort();
+    interpolations
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    match path {
+        "" => key.to_owned(),
+        path => format!("{path}.{key}"),
+    }
+}
+
+fn existing_string(existing: Option<&Value>, key: &str) -> Option<Value> {
+    existing
+        .and_then(|existing| existing.get(key))
+        .filter(|value| value.as_str().is_some_and(|value| !value.is_empty()))
+        .cloned()
+}
+
+/// Rebuild `value` in the target language, calling `translate` with the path and text of
+/// each string that needs translating, in document order. Strings that are already in
+/// `existing` are kept as they are.
+fn transform(
+    value: &Value,
+    existing: Option<&Value>,
+    path: &str,
+    categories: &[&str],
+    translate: &mut dyn FnMut(&str, &str) -> Value,
+) -> Value {
+    match value {
+        Value::String(text) => match existing.filter(|existing| {
+            existing
+                .as_str()
+                .is_some_and(|existing| !existing.is_empty())
+        }) {
+            Some(existing) => existing.clone(),
+            None if text.trim().is_empty() => value.clone(),
+            None => translate(path, text),
+        },
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    transform(
+                        value,
+                        existing.and_then(|existing| existing.get(index)),
+                        &join_path(path, &index.to_string()),
+                        categories,
+                        translate,
+                    )
+                })
+                .collect_vec(),
+        ),
+        Value::Object(map) => {
+            let mut translated = Map::new();
+            for (key, value) in map {
+                let plural = plural_base(key).filter(|(base, _)| {
+                    map.get(&format!("{base}_{OTHER_CATEGORY}"))
+                        .is_some_and(Value::is_string)
+                        && value.is_string()
+                });
+                match plural {
+                    Some((base, _)) => {
+                        if translated.contains_key(&format!("{base}_{OTHER_CATEGORY}")) {
+                            continue;
+                        }
+                        translate_plural(map, existing, path, base, categories, translate)
+                            .into_iter()
+                            .for_each(|(key, value)| {
+                                translated.insert(key, value);
+                            });
+                    }
+                    None => {
+                        let value = transform(
+                            value,
+                            existing.and_then(|existing| existing.get(key)),
+                            &join_path(path, key),
+                            categories,
+                            translate,
+                        );
+                        translated.insert(key.clone(), value);
+                    }
+                }
+            }
+            Value::Object(translated)
+        }
+        value => value.clone(),
+    }
+}
+
+/// Translate an i18next plural group to the plural categories of the target language. The
+/// `one` form is translated from the source `one` form and every other category from the
+/// source `other` form.
+fn translate_plural(
+    map: &Map<String, Value>,
+    existing: Option<&Value>,
+    path: &str,
+    base: &str,
+    categories: &[&str],
+    translate: &mut dyn FnMut(&str, &str) -> Value,
+) -> Vec<(String, Value)> {
+    let other_key = format!("{base}_{OTHER_CATEGORY}");
+    let one_key = format!("{base}_{ONE_CATEGORY}");
+    let source_other = map.get(&other_key).and_then(Value::as_str).unwrap_or("");
+    let source_one = map
+        .get(&one_key)
+        .and_then(Value::as_str)
+        .unwrap_or(source_other);
+
+    let mut translated_one = None;
+    let mut translated_other = None;
+    categories
+        .iter()
+        .map(|category| {
+            let key = format!("{base}_{category}");
+            let value = existing_string(existing, &key).unwrap_or_else(|| {
+                if *category == ONE_CATEGORY {
+                    translated_one
+                        .get_or_insert_with(|| translate(&join_path(path, &one_key), source_one))
+                        .clone()
+                } else {
+                    translated_other
+                        .get_or_insert_with(|| {
+                            translate(&join_path(path, &other_key), source_other)
+                        })
+                        .clone()
+                }
+            });
+            (key, value)
+        })
+        .collect_vec()
+}
+
+/// A nested JSON resource file, as used by i18next, Angular and Vue.
+pub struct JsonResource {
+    resource: Value,
+    existing: Option<Value>,
+    target_language: Language,
+}
+
+pub struct TranslatedJson {
+    pub resource: Value,
+    /// Strings left in the source language because the translation lost interpolations.
+    pub untranslated: usize,
+}
+
+impl JsonResource {
+    /// `existing` is the current resource in the target language, only strings missing from
+    /// it are translated.
+    pub fn new(resource: Value, existing: Option<Value>, target_language: Language) -> Self {
+        JsonResource {
+            resource,
+            existing,
+            target_language,
+        }
+    }
+
+    fn categories(&self) -> &'static [&'static str] {
+        plural_categories(&self.target_language)
+    }
+
+    /// One input per string that needs translating, in document order, with its key as a
+    /// hint.
+    pub fn inputs(&self, source_language: Option<Language>) -> Vec<TranslationInput> {
+        let mut inputs = Vec::new();
+        transform(
+            &self.resource,
+            self.existing.as_ref(),
+            "",
+            self.categories(),
+            &mut |path, text| {
+                inputs.push(
+                    TranslationInput::new(
+                        text.to_owned(),
+                        source_language.clone(),
+                        self.target_language.clone(),
+                    )
+                    .with_hints(vec![format!("Key: {path}")]),
+                );
+                Value::Null
+            },
+        );
+        inputs
+    }
+
+    /// Build the resource in the target language from the translations of [Self::inputs].
+    pub fn apply(&self, outputs: &[TranslationOutput]) -> TranslatedJson {
+        let mut outputs = outputs.iter();
+        let mut untranslated = 0;
+        let resource = transform(
+            &self.resource,
+            self.existing.as_ref(),
+            "",
+            self.categories(),
+            &mut |_, text| match outputs.next() {
+                Some(output) if interpolations(text) == interpolations(output.text()) => {
+                    Value::String(output.text().to_owned())
+                }
+                _ => {
+                    untranslated += 1;
+                    Value::String(text.to_owned())
+                }
+            },
+        );
+        TranslatedJson {
+            resource,
+            untranslated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::JsonResource;
+    use serde_json::json;
+    use std::str::FromStr;
+    use translation::{Language, TranslationOutput};
+
+    #[test]
+    fn it_should_translate_string_leaves() {
+        let resource = JsonResource::new(
+            json!({
+                "title": "Welcome {{name}}",
+                "menu": { "items": ["Home", "Settings"], "count": 2, "enabled": true },
+                "nested": "$t(title) again",
+            }),
+            None,
+            Language::from_str("fr").unwrap(),
+        );
+        let inputs = resource.inputs(None);
+        assert_eq!(
+            inputs.iter().map(|input| input.text()).collect::<Vec<_>>(),
+            vec!["Welcome {{name}}", "Home", "Settings", "$t(title) again"]
+        );
+
+        let translated = resource.apply(&[
+            TranslationOutput::new("Bienvenue {{name}}".to_owned(), None),
+            TranslationOutput::new("Accueil".to_owned(), None),
+            TranslationOutput::new("Paramètres".to_owned(), None),
+            TranslationOutput::new("encore".to_owned(), None),
+        ]);
+        assert_eq!(translated.untranslated, 1);
+        assert_eq!(
+            translated.resource,
+            json!({
+                "title": "Bienvenue {{name}}",
+                "menu": { "items": ["Accueil", "Paramètres"], "count": 2, "enabled": true },
+                "nested": "$t(title) again",
+            })
+        );
+        assert_eq!(
+            serde_json::to_string(&translated.resource).unwrap(),
+            r#"{"title":"Bienvenue {{name}}","menu":{"items":["Accueil","Paramètres"],"count":2,"enabled":true},"nested":"$t(title) again"}"#
+        );
+    }
+
+    #[test]
+    fn it_should_translate_plurals_to_the_target_categories() {
+        let resource = JsonResource::new(
+            json!({ "file_one": "{{count}} file", "file_other": "{{count}} files" }),
+            None,
+            Language::from_str("ru").unwrap(),
+        );
+        let inputs = resource.inputs(None);
+        assert_eq!(inputs.len(), 2);
+
+        let translated = resource.apply(&[
+            TranslationOutput::new("{{count}} файл".to_owned(), None),
+            TranslationOutput::new("{{count}} файлов".to_owned(), None),
+        ]);
+        assert_eq!(
+            translated.resource,
+            json!({
+                "file_one": "{{count}} файл",
+                "file_few": "{{count}} файлов",
+                "file_many": "{{count}} файлов",
+                "file_other": "{{count}} файлов",
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_only_translate_missing_keys() {
+        let resource = JsonResource::new(
+            json!({ "save": "Save", "cancel": "Cancel", "file_one": "A file", "file_other": "Files" }),
+            Some(json!({ "save": "Enregistrer", "file_other": "Fichiers" })),
+            Language::from_str("fr").unwrap(),
+        );
+        let inputs = resource.inputs(None);
+        assert_eq!(
+            inputs.iter().map(|input| input.text()).collect::<Vec<_>>(),
+            vec!["Cancel", "A file", "Files"]
+        );
+
+        let translated = resource.apply(&[
+            TranslationOutput::new("Annuler".to_owned(), None),
+            TranslationOutput::new("Un fichier".to_owned(), None),
+            TranslationOutput::new("Des fichiers".to_owned(), None),
+        ]);
+        assert_eq!(
+            translated.resource,
+            json!({
+                "save": "Enregistrer",
+                "cancel": "Annuler",
+                "file_one": "Un fichier",
+                "file_many": "Des fichiers",
+                "file_other": "Fichiers",
+            })
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use std::str::FromStr;
+use translation::Language;
+
+/// Parse a language code such as `fr` or `fr-CA`, falling back to the primary language
+/// for regional variants that [Language] doesn't know about.
+pub fn parse_language(code: &str) -> Option<Language> {
+    let code = code.trim().to_ascii_lowercase();
+    Language::from_str(&code).ok().or_else(|| {
+        let primary = code.split(['-', '_']).next()?;
+        Language::from_str(primary).ok()
+    })
+}
+
+pub(crate) fn same_language_code(first: &str, second: &str) -> bool {
+    first.eq_ignore_ascii_case(second)
+        || matches!(
+            (parse_language(first), parse_language(second)),
+            (Some(first), Some(second)) if first.to_string() == second.to_string()
+        )
+}
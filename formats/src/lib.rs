@@ -0,0 +1,12 @@
+pub mod android;
+pub mod apple;
+pub mod error;
+pub mod json;
+pub mod language;
+mod plural;
+pub mod po;
+pub mod resource;
+pub mod spreadsheet;
+pub mod subtitles;
+pub mod tmx;
+pub mod xliff;
@@ -0,0 +1,35 @@
+use translation::Language;
+
+pub(crate) const PLURAL_CATEGORIES: [&str; 6] = ["zero", "one", "two", "few", "many", "other"];
+pub(crate) const OTHER_CATEGORY: &str = "other";
+pub(crate) const ONE_CATEGORY: &str = "one";
+pub(crate) const ZERO_CATEGORY: &str = "zero";
+
+/// The CLDR plural categories of a language, as used by i18next, Android and Apple.
+pub(crate) fn plural_categories(language: &Language) -> &'static [&'static str] {
+    match language.to_string().as_str() {
+        "id" | "ja" | "km" | "ko" | "lo" | "ms" | "my" | "th" | "vi" | "zh" => &["other"],
+        "fr" | "it" | "es" | "pt" | "ca" => &["one", "many", "other"],
+        "cs" | "lt" | "pl" | "ru" | "sk" | "uk" => &["one", "few", "many", "other"],
+        "be" | "bs" | "hr" | "sr" => &["one", "few", "other"],
+        "he" => &["one", "two", "other"],
+        "lv" => &["zero", "one", "other"],
+        "ro" => &["one", "few", "other"],
+        "sl" => &["one", "two", "few", "other"],
+        "ga" => &["one", "two", "few", "many", "other"],
+        "ar" | "cy" => &["zero", "one", "two", "few", "many", "other"],
+        _ => &["one", "other"],
+    }
+}
+
+/// The source plural form to translate for a target plural category. Source languages
+/// rarely have more than `one` and `other`, so every other category is translated from
+/// `other`.
+pub(crate) fn source_category<'a>(target_category: &'a str, source_categories: &[&str]) -> &'a str {
+    let is_specific = target_category == ONE_CATEGORY || target_category == ZERO_CATEGORY;
+    if is_specific && source_categories.contains(&target_category) {
+        target_category
+    } else {
+        OTHER_CATEGORY
+    }
+}
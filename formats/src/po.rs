@@ -0,0 +1,612 @@
+use crate::error::FormatError;
+use crate::plural::{plural_categories, source_category, ONE_CATEGORY, OTHER_CATEGORY};
+use itertools::Itertools;
+use translation::{Language, TranslationInput, TranslationOutput};
+
+const FUZZY_FLAG: &str = "fuzzy";
+const DEFAULT_PLURAL_FORMS: &str = "nplurals=2; plural=(n != 1);";
+
+/// Plural forms of the target language, following the GNU gettext manual.
+fn plural_forms(language: &Language) -> &'static str {
+    match language.to_string().as_str() {
+        "id" | "ja" | "km" | "ko" | "lo" | "ms" | "my" | "th" | "vi" | "zh" => {
+            "nplurals=1; plural=0;"
+        }
+        "br" | "fr" | "oc" => "nplurals=2; plural=(n > 1);",
+        "be" | "bs" | "hr" | "ru" | "sr" | "uk" => {
+            "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);"
+        }
+        "cs" | "sk" => "nplurals=3; plural=(n==1) ? 0 : (n>=2 && n<=4) ? 1 : 2;",
+        "pl" => {
+            "nplurals=3; plural=(n==1 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);"
+        }
+        "lt" => {
+            "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n%10>=2 && (n%100<10 || n%100>=20) ? 1 : 2);"
+        }
+        "lv" => "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n != 0 ? 1 : 2);",
+        "ro" => "nplurals=3; plural=(n==1 ? 0 : (n==0 || (n%100 > 0 && n%100 < 20)) ? 1 : 2);",
+        "sl" => "nplurals=4; plural=(n%100==1 ? 0 : n%100==2 ? 1 : n%100==3 || n%100==4 ? 2 : 3);",
+        "ga" => "nplurals=5; plural=(n==1 ? 0 : n==2 ? 1 : n<7 ? 2 : n<11 ? 3 : 4);",
+        "ar" => {
+            "nplurals=6; plural=(n==0 ? 0 : n==1 ? 1 : n==2 ? 2 : n%100>=3 && n%100<=10 ? 3 : n%100>=11 ? 4 : 5);"
+        }
+        _ => DEFAULT_PLURAL_FORMS,
+    }
+}
+
+/// The CLDR plural category of each `msgstr[n]` of [plural_forms]. gettext only counts
+/// integers, so it leaves out the categories CLDR keeps for fractions.
+fn msgstr_categories(language: &Language, plural_count: usize) -> Vec<&'static str> {
+    let categories = match language.to_string().as_str() {
+        "ca" | "es" | "fr" | "it" | "pt" => &[ONE_CATEGORY, OTHER_CATEGORY][..],
+        "be" | "pl" | "ru" | "uk" => &["one", "few", "many"],
+        "cs" | "lt" | "sk" => &["one", "few", "other"],
+        "lv" => &["one", "other", "zero"],
+        _ => plural_categories(language),
+    };
+    match plural_count {
+        _ if categories.len() == plural_count => categories.to_vec(),
+        1 => vec![OTHER_CATEGORY],
+        _ => std::iter::once(ONE_CATEGORY)
+            .chain(std::iter::repeat_n(OTHER_CATEGORY, plural_count - 1))
+            .collect_vec(),
+    }
+}
+
+fn plural_count(plural_forms: &str) -> Option<usize> {
+    plural_forms
+        .split(';')
+        .filter_map(|part| part.trim().strip_prefix("nplurals="))
+        .next()
+        .and_then(|count| count.trim().parse::<usize>().ok())
+        .filter(|count| *count > 0)
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+fn parse_quoted(value: &str, line: usize) -> Result<String, FormatError> {
+    value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .map(unescape)
+        .ok_or(FormatError::InvalidDocument(format!(
+            "Expected a quoted string on line {line}"
+        )))
+}
+
+fn write_string(lines: &mut Vec<String>, keyword: &str, value: &str) {
+    let parts = value.split_inclusive('\n').collect_vec();
+    if parts.len() > 1 {
+        lines.push(format!("{keyword} \"\""));
+        lines.extend(parts.iter().map(|part| format!("\"{}\"", escape(part))));
+    } else {
+        lines.push(format!("{keyword} \"{}\"", escape(value)));
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Context,
+    Id,
+    PluralId,
+    String(usize),
+}
+
+#[derive(Default)]
+struct PoEntry {
+    /// Lines of the entry as they appeared in the document.
+    raw: Vec<String>,
+    comments: Vec<String>,
+    context: Option<String>,
+    id: Option<String>,
+    plural_id: Option<String>,
+    strings: Vec<String>,
+}
+
+impl PoEntry {
+    fn has_strings(&self) -> bool {
+        !self.strings.is_empty()
+    }
+
+    fn is_header(&self) -> bool {
+        self.context.is_none() && self.id.as_deref() == Some("")
+    }
+
+    fn needs_translation(&self) -> bool {
+        !self.is_header()
+            && self.id.is_some()
+            && self.strings.iter().all(|string| string.is_empty())
+    }
+
+    fn field_mut(&mut self, field: Field) -> &mut String {
+        match field {
+            Field::Context => self.context.get_or_insert_with(String::new),
+            Field::Id => self.id.get_or_insert_with(String::new),
+            Field::PluralId => self.plural_id.get_or_insert_with(String::new),
+            Field::String(index) => {
+                if self.strings.len() <= index {
+                    self.strings.resize(index + 1, String::new());
+                }
+                &mut self.strings[index]
+            }
+        }
+    }
+
+    /// Extracted comments (`#.`), as hints for translators.
+    fn hints(&self) -> Vec<String> {
+        self.comments
+            .iter()
+            .filter_map(|comment| comment.strip_prefix("#."))
+            .map(|comment| comment.trim().to_owned())
+            .collect_vec()
+    }
+
+    /// The texts to translate with their plural category: one per `msgstr[n]` of an entry
+    /// with a plural, translated from the singular for `one` and from the plural otherwise.
+    fn texts(&self, categories: &[&'static str]) -> Vec<(String, Option<&'static str>)> {
+        match (&self.id, &self.plural_id) {
+            (Some(id), None) => vec![(id.clone(), None)],
+            (Some(id), Some(plural_id)) => categories
+                .iter()
+                .map(|category| {
+                    let text = match source_category(category, &[ONE_CATEGORY, OTHER_CATEGORY]) {
+                        ONE_CATEGORY => id,
+                        _ => plural_id,
+                    };
+                    (text.clone(), Some(*category))
+                })
+                .collect_vec(),
+            (None, _) => Vec::new(),
+        }
+    }
+
+    fn add_flag(&mut self, flag: &str) {
+        match self
+            .comments
+            .iter()
+            .position(|comment| comment.starts_with("#,"))
+        {
+            Some(index) => {
+                let flags = self.comments[index][2..]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|existing| !existing.is_empty() && *existing != flag)
+                    .collect_vec();
+                self.comments[index] = format!("#, {}", [flag].iter().chain(&flags).join(", "));
+            }
+            None => {
+                let index = self
+                    .comments
+                    .iter()
+                    .position(|comment| comment.starts_with("#|"))
+                    .unwrap_or(self.comments.len());
+                self.comments.insert(index, format!("#, {flag}"));
+            }
+        }
+    }
+
+    fn set_header_field(&mut self, name: &str, value: &str) {
+        let header = self.strings.first().cloned().unwrap_or_default();
+        let mut found = false;
+        let mut lines = header
+            .split_inclusive('\n')
+            .map(|line| match line.split_once(':') {
+                Some((field, _)) if field.trim() == name => {
+                    found = true;
+                    format!("{name}: {value}\n")
+                }
+                _ => line.to_owned(),
+            })
+            .collect_vec();
+        if !found {
+            lines.push(format!("{name}: {value}\n"));
+        }
+        self.strings = vec![lines.concat()];
+    }
+
+    fn header_field(&self, name: &str) -> Option<&str> {
+        self.strings.first()?.lines().find_map(|line| {
+            let (field, value) = line.split_once(':')?;
+            (field.trim() == name).then_some(value.trim())
+        })
+    }
+
+    fn to_lines(&self) -> Vec<String> {
+        let mut lines = self.comments.clone();
+        if let Some(context) = &self.context {
+            write_string(&mut lines, "msgctxt", context);
+        }
+        write_string(&mut lines, "msgid", self.id.as_deref().unwrap_or_default());
+        match &self.plural_id {
+            Some(plural_id) => {
+                write_string(&mut lines, "msgid_plural", plural_id);
+                for (index, string) in self.strings.iter().enumerate() {
+                    write_string(&mut lines, &format!("msgstr[{index}]"), string);
+                }
+            }
+            None => write_string(
+                &mut lines,
+                "msgstr",
+                self.strings.first().map(String::as_str).unwrap_or_default(),
+            ),
+        }
+        lines
+    }
+}
+
+enum PoItem {
+    Line(String),
+    Entry(PoEntry, bool),
+}
+
+/// A gettext catalog, a POT template or a partially translated PO file.
+pub struct PoCatalog {
+    items: Vec<PoItem>,
+    target_language: Language,
+    plural_forms: String,
+    /// CLDR plural category of each `msgstr[n]`.
+    plural_categories: Vec<&'static str>,
+}
+
+fn parse_items(document: &str) -> Result<Vec<PoItem>, FormatError> {
+    let mut items = Vec::new();
+    let mut entry = None::<PoEntry>;
+    let mut field = None::<Field>;
+
+    for (index, line) in document.lines().enumerate() {
+        let line_number = index + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if let Some(entry) = entry.take() {
+                items.push(PoItem::Entry(entry, false));
+            }
+            items.push(PoItem::Line(line.to_owned()));
+            field = None;
+            continue;
+        }
+
+        if trimmed.starts_with('"') {
+            let (Some(current), Some(field)) = (entry.as_mut(), field) else {
+                return Err(FormatError::InvalidDocument(format!(
+                    "Unexpected string on line {line_number}"
+                )));
+            };
+            let value = parse_quoted(trimmed, line_number)?;
+            current.field_mut(field).push_str(&value);
+            current.raw.push(line.to_owned());
+            continue;
+        }
+
+        let starts_entry = trimmed.starts_with('#')
+            || trimmed.starts_with("msgctxt ")
+            || trimmed.starts_with("msgid ");
+        if starts_entry && entry.as_ref().is_some_and(PoEntry::has_strings) {
+            if let Some(entry) = entry.take() {
+                items.push(PoItem::Entry(entry, false));
+            }
+        }
+        let current = entry.get_or_insert_with(PoEntry::default);
+        current.raw.push(line.to_owned());
+
+        if trimmed.starts_with('#') {
+            current.comments.push(trimmed.to_owned());
+            field = None;
+            continue;
+        }
+
+        let (keyword, value) =
+            trimmed
+                .split_once(char::is_whitespace)
+                .ok_or(FormatError::InvalidDocument(format!(
+                    "Expected a keyword on line {line_number}"
+                )))?;
+        let next_field = match keyword {
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgid_plural" => Field::PluralId,
+            "msgstr" => Field::String(0),
+            keyword => keyword
+                .strip_prefix("msgstr[")
+                .and_then(|index| index.strip_suffix(']'))
+                .and_then(|index| index.parse::<usize>().ok())
+                .map(Field::String)
+                .ok_or(FormatError::InvalidDocument(format!(
+                    "Unknown keyword {keyword} on line {line_number}"
+                )))?,
+        };
+        let value = parse_quoted(value, line_number)?;
+        *current.field_mut(next_field) = value;
+        field = Some(next_field);
+    }
+    if let Some(entry) = entry.take() {
+        items.push(PoItem::Entry(entry, false));
+    }
+    Ok(items)
+}
+
+impl PoCatalog {
+    /// Parse a catalog to be translated to `target_language`. The plural forms come from the
+    /// `Plural-Forms` header when it's filled in, otherwise from the target language.
+    pub fn parse(document: &str, target_language: Language) -> Result<Self, FormatError> {
+        let mut items = parse_items(document)?;
+        if document.ends_with('\n') {
+            items.push(PoItem::Line(String::new()));
+        }
+
+        let header_plural_forms = items.iter().find_map(|item| match item {
+            PoItem::Entry(entry, _) if entry.is_header() => entry
+                .header_field("Plural-Forms")
+                .filter(|plural_forms| plural_count(plural_forms).is_some())
+                .map(str::to_owned),
+            _ => None,
+        });
+        let plural_forms = header_plural_forms.unwrap_or(plural_forms(&target_language).to_owned());
+        let plural_count = plural_count(&plural_forms).unwrap_or(2);
+        let plural_categories = msgstr_categories(&target_language, plural_count);
+
+        Ok(PoCatalog {
+            items,
+            target_language,
+            plural_forms,
+            plural_categories,
+        })
+    }
+
+    fn entries_to_translate(&self) -> impl Iterator<Item = &PoEntry> {
+        self.items.iter().filter_map(|item| match item {
+            PoItem::Entry(entry, _) if entry.needs_translation() => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// Inputs for the entries with empty translations, in document order. Entries with a
+    /// plural have an input per plural form of the target language, hinting its category.
+    pub fn inputs(&self, source_language: Option<Language>) -> Vec<TranslationInput> {
+        self.entries_to_translate()
+            .flat_map(|entry| {
+                let hints = entry.hints();
+                entry
+                    .texts(&self.plural_categories)
+                    .into_iter()
+                    .map(|(text, category)| {
+                        let hints = hints
+                            .iter()
+                            .cloned()
+                            .chain(category.map(|category| format!("Plural category: {category}")))
+                            .collect_vec();
+                        TranslationInput::new(
+                            text,
+                            source_language.clone(),
+                            self.target_language.clone(),
+                        )
+                        .with_context(entry.context.clone())
+                        .with_hints(hints)
+                    })
+                    .collect_vec()
+            })
+            .collect_vec()
+    }
+
+    /// Fill in the translations of [Self::inputs], flagging each translated entry as fuzzy
+    /// so it gets reviewed, and set the language headers.
+    pub fn apply(mut self, outputs: &[TranslationOutput]) -> String {
+        let mut outputs = outputs.iter();
+        for item in self.items.iter_mut() {
+            let PoItem::Entry(entry, modified) = item else {
+                continue;
+            };
+            if !entry.needs_translation() {
+                continue;
+            }
+            let count = entry.texts(&self.plural_categories).len();
+            let translations = outputs
+                .by_ref()
+                .take(count)
+                .map(|output| output.text().to_owned())
+                .collect_vec();
+            if translations.len() < count {
+                continue;
+            }
+            entry.strings = translations;
+            entry.add_flag(FUZZY_FLAG);
+            *modified = true;
+        }
+
+        let language = self.target_language.to_string();
+        let header = self.items.iter_mut().find_map(|item| match item {
+            PoItem::Entry(entry, modified) if entry.is_header() => Some((entry, modified)),
+            _ => None,
+        });
+        match header {
+            Some((entry, modified)) => {
+                entry.set_header_field("Language", &language);
+                entry.set_header_field("Plural-Forms", &self.plural_forms);
+                *modified = true;
+            }
+            None => {
+                let mut entry = PoEntry {
+                    id: Some(String::new()),
+                    ..PoEntry::default()
+                };
+                entry.set_header_field("Content-Type", "text/plain; charset=UTF-8");
+                entry.set_header_field("Language", &language);
+                entry.set_header_field("Plural-Forms", &self.plural_forms);
+                self.items.insert(0, PoItem::Line(String::new()));
+                self.items.insert(0, PoItem::Entry(entry, true));
+            }
+        }
+
+        self.items
+            .iter()
+            .flat_map(|item| match item {
+                PoItem::Line(line) => vec![line.clone()],
+                PoItem::Entry(entry, true) => entry.to_lines(),
+                PoItem::Entry(entry, false) => entry.raw.clone(),
+            })
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::po::PoCatalog;
+    use std::str::FromStr;
+    use translation::{Language, TranslationOutput};
+
+    const POT: &str = r#"# Example catalog
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+"Plural-Forms: nplurals=INTEGER; plural=EXPRESSION;\n"
+
+#. Shown on the toolbar
+#: src/editor.py:12
+msgctxt "verb"
+msgid "Save"
+msgstr ""
+
+#: src/files.py:40
+#, python-format
+msgid "%(count)d file"
+msgid_plural "%(count)d files"
+msgstr[0] ""
+msgstr[1] ""
+
+msgid "Cancel"
+msgstr "Annuler"
+
+msgid ""
+"Line one\n"
+"Line two"
+msgstr ""
+"#;
+
+    #[test]
+    fn it_should_find_entries_to_translate() {
+        let catalog = PoCatalog::parse(POT, Language::from_str("ru").unwrap()).unwrap();
+        let inputs = catalog.inputs(Some(Language::from_str("en").unwrap()));
+
+        assert_eq!(
+            inputs.iter().map(|input| input.text()).collect::<Vec<_>>(),
+            vec![
+                "Save",
+                "%(count)d file",
+                "%(count)d files",
+                "%(count)d files",
+                "Line one\nLine two"
+            ]
+        );
+        assert_eq!(inputs[0].source_language().unwrap().to_string(), "en");
+        assert_eq!(inputs[0].context(), Some("verb"));
+        assert_eq!(inputs[0].hints(), ["Shown on the toolbar"]);
+        assert_eq!(
+            inputs[1..4]
+                .iter()
+                .map(|input| input.hints().join(""))
+                .collect::<Vec<_>>(),
+            vec![
+                "Plural category: one",
+                "Plural category: few",
+                "Plural category: many"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_fill_in_translations() {
+        let catalog = PoCatalog::parse(POT, Language::from_str("ru").unwrap()).unwrap();
+        let translated = catalog.apply(&[
+            TranslationOutput::new("Сохранить".to_owned(), None),
+            TranslationOutput::new("%(count)d файл".to_owned(), None),
+            TranslationOutput::new("%(count)d файла".to_owned(), None),
+            TranslationOutput::new("%(count)d файлов".to_owned(), None),
+            TranslationOutput::new("Строка один\nСтрока два".to_owned(), None),
+        ]);
+
+        assert_eq!(
+            translated,
+            r#"# Example catalog
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+"Plural-Forms: nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);\n"
+"Language: ru\n"
+
+#. Shown on the toolbar
+#: src/editor.py:12
+#, fuzzy
+msgctxt "verb"
+msgid "Save"
+msgstr "Сохранить"
+
+#: src/files.py:40
+#, fuzzy, python-format
+msgid "%(count)d file"
+msgid_plural "%(count)d files"
+msgstr[0] "%(count)d файл"
+msgstr[1] "%(count)d файла"
+msgstr[2] "%(count)d файлов"
+
+msgid "Cancel"
+msgstr "Annuler"
+
+#, fuzzy
+msgid ""
+"Line one\n"
+"Line two"
+msgstr ""
+"Строка один\n"
+"Строка два"
+"#
+        );
+    }
+
+    #[test]
+    fn it_should_use_the_plural_forms_header() {
+        let po = "msgid \"\"\nmsgstr \"Plural-Forms: nplurals=1; plural=0;\\n\"\n\nmsgid \"One day\"\nmsgid_plural \"%d days\"\nmsgstr[0] \"\"\n";
+        let catalog = PoCatalog::parse(po, Language::from_str("fr").unwrap()).unwrap();
+        let inputs = catalog.inputs(None);
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].text(), "%d days");
+    }
+
+    #[test]
+    fn it_should_add_a_header() {
+        let catalog = PoCatalog::parse(
+            "msgid \"Hello\"\nmsgstr \"\"\n",
+            Language::from_str("fr").unwrap(),
+        )
+        .unwrap();
+        let translated = catalog.apply(&[TranslationOutput::new("Bonjour".to_owned(), None)]);
+        assert!(translated.starts_with("msgid \"\"\nmsgstr \"\"\n\"Content-Type"));
+        assert!(translated.contains("\"Plural-Forms: nplurals=2; plural=(n > 1);\\n\"\n"));
+        assert!(translated.ends_with("#, fuzzy\nmsgid \"Hello\"\nmsgstr \"Bonjour\"\n"));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::plural::{plural_categories, source_category};
+use itertools::Itertools;
+use std::collections::HashMap;
+use translation::placeholder::placeholders;
+use translation::{Language, TranslationInput, TranslationOutput};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResourceValue {
+    Text(String),
+    /// Plural category and text of each plural form.
+    Plural(Vec<(String, String)>),
+    Array(Vec<String>),
+}
+
+/// A translatable string of a mobile resource file.
+#[derive(Clone, Debug)]
+pub struct ResourceEntry {
+    pub key: String,
+    pub comment: Option<String>,
+    pub value: ResourceValue,
+}
+
+/// A resource file format that has its strings translated by [ResourceTranslation].
+pub trait ResourceFormat {
+    /// The strings that need translating.
+    fn entries(&self) -> &[ResourceEntry];
+
+    /// Write the file in the target language. `translations` has the translated value of
+    /// each of [Self::entries], or none when it couldn't be translated safely.
+    fn write(&self, translations: &[Option<ResourceValue>], target_language: &Language) -> String;
+}
+
+pub struct TranslatedResource {
+    pub document: String,
+    /// Entries left out because the translation changed their format specifiers or markup.
+    pub untranslated: usize,
+}
+
+/// Whether `translation` has the same placeholders and markup as `source`, in any order.
+pub(crate) fn keeps_placeholders(source: &str, translation: &str) -> bool {
+    placeholders(source).into_iter().sorted().collect_vec()
+        == placeholders(translation).into_iter().sorted().collect_vec()
+}
+
+/// The source plural forms to translate for the plural categories of the target language.
+fn plural_sources<'a>(
+    forms: &'a [(String, String)],
+    categories: &[&str],
+) -> Vec<(&'a str, &'a str)> {
+    let source_categories = forms
+        .iter()
+        .map(|(category, _)| category.as_str())
+        .collect_vec();
+    categories
+        .iter()
+        .map(|category| source_category(category, &source_categories))
+        .unique()
+        .filter_map(|category| {
+            forms
+                .iter()
+                .find(|(source, _)| source == category)
+                .map(|(category, text)| (category.as_str(), text.as_str()))
+        })
+        .collect_vec()
+}
+
+impl ResourceValue {
+    fn source_texts(&self, categories: &[&str]) -> Vec<&str> {
+        match self {
+            ResourceValue::Text(text) => vec![text.as_str()],
+            ResourceValue::Array(items) => items.iter().map(String::as_str).collect_vec(),
+            ResourceValue::Plural(forms) => plural_sources(forms, categories)
+                .into_iter()
+                .map(|(_, text)| text)
+                .collect_vec(),
+        }
+    }
+
+    fn translated(&self, categories: &[&str], translations: &[&str]) -> Option<ResourceValue> {
+        let sources = self.source_texts(categories);
+        let is_valid = sources.len() == translations.len()
+            && sources
+                .iter()
+                .zip(translations)
+                .all(|(source, translation)| keeps_placeholders(source, translation));
+        if !is_valid {
+            return None;
+        }
+
+        match self {
+            ResourceValue::Text(_) => Some(ResourceValue::Text(translations[0].to_owned())),
+            ResourceValue::Array(_) => Some(ResourceValue::Array(
+                translations
+                    .iter()
+                    .map(|text| text.to_string())
+                    .collect_vec(),
+            )),
+            ResourceValue::Plural(forms) => {
+                let source_categories = forms
+                    .iter()
+                    .map(|(category, _)| category.as_str())
+                    .collect_vec();
+                let translated_forms = plural_sources(forms, categories)
+                    .into_iter()
+                    .map(|(category, _)| category)
+                    .zip(translations.iter().copied())
+                    .collect::<HashMap<_, _>>();
+                categories
+                    .iter()
+                    .map(|category| {
+                        translated_forms
+                            .get(source_category(category, &source_categories))
+                            .map(|text| (category.to_string(), text.to_string()))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .map(ResourceValue::Plural)
+            }
+        }
+    }
+}
+
+/// Translation of a resource file to a target language, through the existing translation
+/// inputs and outputs so any provider can be used.
+pub struct ResourceTranslation<Format: ResourceFormat> {
+    format: Format,
+    target_language: Language,
+}
+
+impl<Format: ResourceFormat> ResourceTranslation<Format> {
+    pub fn new(format: Format, target_language: Language) -> Self {
+        ResourceTranslation {
+            format,
+            target_language,
+        }
+    }
+
+    fn categories(&self) -> &'static [&'static str] {
+        plural_categories(&self.target_language)
+    }
+
+    /// One input per string to translate, in order, with the comment and key as hints.
+    pub fn inputs(&self, source_language: Option<Language>) -> Vec<TranslationInput> {
+        self.format
+            .entries()
+            .iter()
+            .flat_map(|entry| {
+                let hints = entry
+                    .comment
+                    .iter()
+                    .cloned()
+                    .chain([format!("Key: {}", entry.key)])
+                    .collect_vec();
+                entry
+                    .value
+                    .source_texts(self.categories())
+                    .into_iter()
+                    .map(|text| {
+                        TranslationInput::new(
+                            text.to_owned(),
+                            source_language.clone(),
+                            self.target_language.clone(),
+                        )
+                        .with_hints(hints.clone())
+                    })
+                    .collect_vec()
+            })
+            .collect_vec()
+    }
+
+    /// Write the file in the target language from the translations of [Self::inputs].
+    pub fn apply(&self, outputs: &[TranslationOutput]) -> TranslatedResource {
+        let mut outputs = outputs.iter();
+        let translations = self
+            .format
+            .entries()
+            .iter()
+            .map(|entry| {
+                let count = entry.value.source_texts(self.categories()).len();
+                let texts = outputs
+                    .by_ref()
+                    .take(count)
+                    .map(TranslationOutput::text)
+                    .collect_vec();
+                entry.value.translated(self.categories(), &texts)
+            })
+            .collect_vec();
+        TranslatedResource {
+            document: self.format.write(&translations, &self.target_language),
+            untranslated: translations
+                .iter()
+                .filter(|translation| translation.is_none())
+                .count(),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::error::FormatError;
+use csv::{ReaderBuilder, WriterBuilder};
+use itertools::Itertools;
+use translation::{Language, Translation, TranslationInput, TranslationOutput};
+
+/// Rows translated with each call to the translation provider.
+pub const CSV_CHUNK_ROWS: usize = 100;
+const ERROR_COLUMN_SUFFIX: &str = "_error";
+
+fn write_records(records: &[Vec<String>]) -> Result<String, FormatError> {
+    let mut writer = WriterBuilder::new().flexible(true).from_writer(Vec::new());
+    for record in records {
+        writer.write_record(record)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|error| FormatError::InvalidDocument(error.to_string()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// A CSV file with a column of text to translate. One column with the translation, and
+/// another with any error, is appended for each target language.
+pub struct CsvTranslation {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    source_column: usize,
+    source_language: Option<Language>,
+    target_languages: Vec<Language>,
+}
+
+impl CsvTranslation {
+    pub fn parse(
+        document: &str,
+        source_column: &str,
+        source_language: Option<Language>,
+        target_languages: Vec<Language>,
+    ) -> Result<Self, FormatError> {
+        let mut reader = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(document.as_bytes());
+        let headers = reader.headers()?.iter().map(str::to_owned).collect_vec();
+        let source_column = headers
+            .iter()
+            .position(|header| header == source_column)
+            .ok_or(FormatError::InvalidDocument(format!(
+                "No column named {source_column}"
+            )))?;
+        let rows = reader
+            .records()
+            .map_ok(|record| record.iter().map(str::to_owned).collect_vec())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CsvTranslation {
+            headers,
+            rows,
+            source_column,
+            source_language,
+            target_languages,
+        })
+    }
+
+    /// The header row of the translated file.
+    pub fn header(&self) -> Result<String, FormatError> {
+        let headers = self
+            .headers
+            .iter()
+            .cloned()
+            .chain(self.target_languages.iter().flat_map(|language| {
+                [
+                    language.to_string(),
+                    format!("{language}{ERROR_COLUMN_SUFFIX}"),
+                ]
+            }))
+            .collect_vec();
+        write_records(&[headers])
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.rows.len().div_ceil(CSV_CHUNK_ROWS)
+    }
+
+    fn source_text(&self, row: &[String]) -> Option<String> {
+        row.get(self.source_column)
+            .filter(|text| !text.trim().is_empty())
+            .cloned()
+    }
+
+    fn chunk_rows(&self, chunk: usize) -> &[Vec<String>] {
+        let start = (chunk * CSV_CHUNK_ROWS).min(self.rows.len());
+        let end = (start + CSV_CHUNK_ROWS).min(self.rows.len());
+        &self.rows[start..end]
+    }
+
+    /// One input per target language for each row with text in the source column.
+    fn rows_inputs(&self, rows: &[Vec<String>]) -> Vec<TranslationInput> {
+        rows.iter()
+            .filter_map(|row| self.source_text(row))
+            .flat_map(|text| {
+                self.target_languages.iter().map(move |target| {
+                    TranslationInput::new(
+                        text.clone(),
+                        self.source_language.clone(),
+                        target.clone(),
+                    )
+                })
+            })
+            .collect_vec()
+    }
+
+    /// Write `rows` with a translation or error for each of their [Self::rows_inputs].
+    fn write_rows(
+        &self,
+        rows: &[Vec<String>],
+        results: Vec<Result<TranslationOutput, String>>,
+    ) -> Result<String, FormatError> {
+        let mut results = results.into_iter();
+        let records = rows
+            .iter()
+            .map(|row| {
+                let has_text = self.source_text(row).is_some();
+                let translations = self.target_languages.iter().flat_map(|_| {
+                    match has_text.then(|| results.next()).flatten() {
+                        Some(Ok(output)) => [output.text().to_owned(), String::new()],
+                        Some(Err(error)) => [String::new(), error],
+                        None => [String::new(), String::new()],
+                    }
+                });
+                // Short rows are padded so the translations line up with their headers.
+                row.iter()
+                    .cloned()
+                    .pad_using(self.headers.len(), |_| String::new())
+                    .chain(translations)
+                    .collect_vec()
+            })
+            .collect_vec();
+        write_records(&records)
+    }
+
+    /// Inputs for every row, for translating the whole file at once.
+    pub fn inputs(&self) -> Vec<TranslationInput> {
+        self.rows_inputs(&self.rows)
+    }
+
+    /// Write the whole file, header included, with the translations of [Self::inputs].
+    pub fn apply(&self, outputs: &[TranslationOutput]) -> Result<String, FormatError> {
+        let results = outputs.iter().cloned().map(Ok).collect_vec();
+        Ok(self.header()? + &self.write_rows(&self.rows, results)?)
+    }
+
+    /// Translate a chunk of [CSV_CHUNK_ROWS] rows with one call to the provider. If that
+    /// fails, each row is retried by itself so that an error only affects its own row.
+    pub async fn translate_chunk<TP: Translation>(
+        &self,
+        translator: &TP,
+        chunk: usize,
+    ) -> Result<String, FormatError> {
+        let rows = self.chunk_rows(chunk);
+        let inputs = self.rows_inputs(rows);
+
+        let results = match translator.translate(inputs.clone()).await {
+            Ok(outputs) if outputs.len() == inputs.len() => {
+                outputs.into_iter().map(Ok).collect_vec()
+            }
+            _ => {
+                let mut results = Vec::with_capacity(inputs.len());
+                for input in inputs {
+                    let result = translator
+                        .translate(vec![input])
+                        .await
+                        .map_err(|error| error.to_string())
+                        .and_then(|outputs| {
+                            outputs
+                                .into_iter()
+                                .next()
+                                .ok_or("No translation".to_owned())
+                        });
+                    results.push(result);
+                }
+                results
+            }
+        };
+        self.write_rows(rows, results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spreadsheet::CsvTranslation;
+    use futures::executor::block_on;
+    use std::str::FromStr;
+    use translation::mock::MockFault;
+    use translation::{
+        Language, Translation, TranslationError, TranslationInput, TranslationOutput,
+    };
+
+    /// Upper cases text, failing any batch that has the text "fail" in it.
+    struct UpperCaseTranslator;
+
+    impl Translation for UpperCaseTranslator {
+        async fn translate(
+            &self,
+            inputs: Vec<TranslationInput>,
+        ) -> Result<Vec<TranslationOutput>, TranslationError> {
+            if inputs.iter().any(|input| input.text() == "fail") {
+                return Err(MockFault::DeepL.into());
+            }
+            Ok(inputs
+                .iter()
+                .map(|input| {
+                    serde_json::from_value(serde_json::json!({
+                        "output": format!("{} {}", input.text().to_uppercase(), input.target_language())
+                    }))
+                    .unwrap()
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn it_should_append_translated_columns() {
+        let csv = "id,text,notes\n1,hello,\"a, b\"\n2,,empty\n3,fail,\n4,bye\n";
+        let translation = CsvTranslation::parse(
+            csv,
+            "text",
+            None,
+            vec![
+                Language::from_str("fr").unwrap(),
+                Language::from_str("de").unwrap(),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(translation.chunk_count(), 1);
+        assert_eq!(
+            translation.header().unwrap(),
+            "id,text,notes,fr,fr_error,de,de_error\n"
+        );
+        assert_eq!(
+            block_on(translation.translate_chunk(&UpperCaseTranslator, 0)).unwrap(),
+            "1,hello,\"a, b\",HELLO fr,,HELLO de,\n\
+             2,,empty,,,,\n\
+             3,fail,,,DeepLError Unexpected API response Injected fault,,DeepLError Unexpected API response Injected fault\n\
+             4,bye,,BYE fr,,BYE de,\n"
+        );
+    }
+
+    #[test]
+    fn it_should_apply_translations_to_the_whole_file() {
+        let translation = CsvTranslation::parse(
+            "id,text\n1,hello\n2,\n",
+            "text",
+            None,
+            vec![Language::from_str("fr").unwrap()],
+        )
+        .unwrap();
+        let outputs = block_on(UpperCaseTranslator.translate(translation.inputs())).unwrap();
+        assert_eq!(
+            translation.apply(&outputs).unwrap(),
+            "id,text,fr,fr_error\n1,hello,HELLO fr,\n2,,,\n"
+        );
+    }
+
+    #[test]
+    fn it_should_require_the_source_column() {
+        assert!(CsvTranslation::parse("id,text\n", "body", None, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::error::FormatError;
+use itertools::Itertools;
+use translation::{Language, TranslationInput, TranslationOutput};
+
+const WEB_VTT_HEADER: &str = "WEBVTT";
+const TIMING_SEPARATOR: &str = "-->";
+/// WebVTT blocks that aren't cues.
+const WEB_VTT_BLOCKS: [&str; 4] = [WEB_VTT_HEADER, "NOTE", "STYLE", "REGION"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+}
+
+#[derive(Debug, Default)]
+pub struct SubtitleOptions {
+    /// Re-wrap translated cues to lines of at most this many characters.
+    pub max_line_length: Option<usize>,
+}
+
+struct Block {
+    /// Identifier and timing of a cue, or the whole of any other block.
+    header: Vec<String>,
+    text: Vec<String>,
+}
+
+impl Block {
+    fn is_cue(&self) -> bool {
+        !self.text.is_empty()
+    }
+}
+
+/// Words of `text`, not splitting the styling tags that have spaces, like `<v Speaker>`.
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut in_tag = false;
+    for (index, char) in text.char_indices() {
+        match char {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            char if char.is_whitespace() && !in_tag => {
+                if let Some(start) = start.take() {
+                    words.push(&text[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(start) = start {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+/// Length of `text` as displayed, without styling tags.
+fn visible_length(text: &str) -> usize {
+    let mut in_tag = false;
+    text.chars()
+        .filter(|char| match char {
+            '<' => {
+                in_tag = true;
+                false
+            }
+            '>' => {
+                in_tag = false;
+                false
+            }
+            _ => !in_tag,
+        })
+        .count()
+}
+
+fn wrap(text: &str, max_line_length: usize) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for word in words(text) {
+        match lines.last_mut() {
+            Some(line) if visible_length(line) + 1 + visible_length(word) <= max_line_length => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_owned()),
+        }
+    }
+    lines
+}
+
+/// Lay out a translation over `line_count` lines of similar length, like the source cue.
+fn balance(text: &str, line_count: usize) -> Vec<String> {
+    let length = visible_length(text);
+    (length.div_ceil(line_count.max(1))..length)
+        .map(|max_line_length| wrap(text, max_line_length))
+        .find(|lines| lines.len() <= line_count)
+        .unwrap_or(vec![text.to_owned()])
+}
+
+/// An SRT or WebVTT subtitle file. Only the text of cues is translated, identifiers,
+/// timings, settings and other blocks are kept as they are.
+pub struct Subtitles {
+    format: SubtitleFormat,
+    blocks: Vec<Block>,
+    target_language: Language,
+}
+
+impl Subtitles {
+    pub fn parse(document: &str, target_language: Language) -> Result<Self, FormatError> {
+        let document = document
+            .trim_start_matches('\u{feff}')
+            .replace("\r\n", "\n");
+        let format = match document.starts_with(WEB_VTT_HEADER) {
+            true => SubtitleFormat::WebVtt,
+            false => SubtitleFormat::Srt,
+        };
+
+        let blocks = document
+            .split("\n\n")
+            .map(|block| block.trim_matches('\n'))
+            .filter(|block| !block.trim().is_empty())
+            .map(|block| {
+                let lines = block.lines().map(str::to_owned).collect_vec();
+                let is_other_block = format == SubtitleFormat::WebVtt
+                    && WEB_VTT_BLOCKS.iter().any(|name| lines[0].starts_with(name));
+                match lines
+                    .iter()
+                    .position(|line| line.contains(TIMING_SEPARATOR))
+                {
+                    Some(timing) if !is_other_block => {
+                        let mut header = lines;
+                        let text = header.split_off(timing + 1);
+                        Block { header, text }
+                    }
+                    _ => Block {
+                        header: lines,
+                        text: Vec::new(),
+                    },
+                }
+            })
+            .collect_vec();
+        if !blocks.iter().any(Block::is_cue) {
+            return Err(FormatError::InvalidDocument("No cues".to_owned()));
+        }
+        Ok(Subtitles {
+            format,
+            blocks,
+            target_language,
+        })
+    }
+
+    pub fn format(&self) -> SubtitleFormat {
+        self.format
+    }
+
+    fn cues(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter().filter(|block| block.is_cue())
+    }
+
+    /// One input per cue, with the text of the neighbouring cues as hints so sentences split
+    /// across cues translate coherently.
+    pub fn inputs(&self, source_language: Option<Language>) -> Vec<TranslationInput> {
+        let texts = self.cues().map(|cue| cue.text.join("\n")).collect_vec();
+        texts
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let previous = index
+                    .checked_sub(1)
+                    .and_then(|previous| texts.get(previous))
+                    .map(|previous| format!("Previous subtitle: {previous}"));
+                let next = texts
+                    .get(index + 1)
+                    .map(|next| format!("Next subtitle: {next}"));
+                TranslationInput::new(
+                    text.clone(),
+                    source_language.clone(),
+                    self.target_language.clone(),
+                )
+                .with_hints(previous.into_iter().chain(next).collect_vec())
+            })
+            .collect_vec()
+    }
+
+    /// Write the subtitles with the translations of [Self::inputs]. Translations keep the
+    /// number of lines of their cue unless lines are re-wrapped to a maximum length.
+    pub fn apply(&self, outputs: &[TranslationOutput], options: &SubtitleOptions) -> String {
+        let mut outputs = outputs.iter();
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let translation = block.is_cue().then(|| outputs.next()).flatten();
+                let text = match translation {
+                    Some(output) => {
+                        let translation = output.text().trim();
+                        match options.max_line_length {
+                            Some(max_line_length) => {
+                                wrap(&translation.replace('\n', " "), max_line_length)
+                            }
+                            None if translation.lines().count() == block.text.len() => {
+                                translation.lines().map(str::to_owned).collect_vec()
+                            }
+                            None => balance(&translation.replace('\n', " "), block.text.len()),
+                        }
+                    }
+                    None => block.text.clone(),
+                };
+                block.header.iter().chain(&text).join("\n")
+            })
+            .collect_vec();
+        blocks.join("\n\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subtitles::{SubtitleFormat, SubtitleOptions, Subtitles};
+    use std::str::FromStr;
+    use translation::{Language, TranslationOutput};
+
+    const SRT: &str = "1\r\n00:00:01,000 --> 00:00:03,500\r\nWelcome to the course,\r\nmy name is Sam\r\n\r\n2\r\n00:00:04,000 --> 00:00:06,000\r\nand today we'll learn Rust.\r\n";
+
+    #[test]
+    fn it_should_translate_srt_cues() {
+        let subtitles = Subtitles::parse(SRT, Language::from_str("fr").unwrap()).unwrap();
+        assert_eq!(subtitles.format(), SubtitleFormat::Srt);
+        let inputs = subtitles.inputs(None);
+        assert_eq!(inputs[0].text(), "Welcome to the course,\nmy name is Sam");
+        assert_eq!(
+            inputs[0].hints(),
+            ["Next subtitle: and today we'll learn Rust."]
+        );
+        assert_eq!(
+            inputs[1].hints(),
+            ["Previous subtitle: Welcome to the course,\nmy name is Sam"]
+        );
+
+        let translated = subtitles.apply(
+            &[
+                TranslationOutput::new(
+                    "Bienvenue dans le cours, je m'appelle Sam".to_owned(),
+                    None,
+                ),
+                TranslationOutput::new("et aujourd'hui nous apprendrons Rust.".to_owned(), None),
+            ],
+            &SubtitleOptions::default(),
+        );
+        assert_eq!(
+            translated,
+            "1\n00:00:01,000 --> 00:00:03,500\nBienvenue dans le\ncours, je m'appelle Sam\n\n2\n00:00:04,000 --> 00:00:06,000\net aujourd'hui nous apprendrons Rust.\n"
+        );
+    }
+
+    const WEB_VTT: &str = "WEBVTT\n\nNOTE Recorded in 2024 --> edited later\n\nintro\n00:00.000 --> 00:02.000 line:90%\n<v Sam Smith>Hello <i>everyone</i></v>\n";
+
+    #[test]
+    fn it_should_translate_web_vtt_cues() {
+        let subtitles = Subtitles::parse(WEB_VTT, Language::from_str("de").unwrap()).unwrap();
+        assert_eq!(subtitles.format(), SubtitleFormat::WebVtt);
+        let inputs = subtitles.inputs(None);
+        assert_eq!(inputs.len(), 1);
+
+        let translated = subtitles.apply(
+            &[TranslationOutput::new(
+                "<v Sam Smith>Hallo <i>zusammen</i></v>".to_owned(),
+                None,
+            )],
+            &SubtitleOptions::default(),
+        );
+        assert_eq!(
+            translated,
+            WEB_VTT.replace("Hello <i>everyone</i>", "Hallo <i>zusammen</i>")
+        );
+    }
+
+    #[test]
+    fn it_should_rewrap_lines() {
+        let subtitles = Subtitles::parse(SRT, Language::from_str("fr").unwrap()).unwrap();
+        let translated = subtitles.apply(
+            &[
+                TranslationOutput::new(
+                    "Bienvenue dans le cours,\nje m'appelle Sam".to_owned(),
+                    None,
+                ),
+                TranslationOutput::new(
+                    "et <b>aujourd'hui</b> nous apprendrons Rust.".to_owned(),
+                    None,
+                ),
+            ],
+            &SubtitleOptions {
+                max_line_length: Some(20),
+            },
+        );
+        assert!(translated.contains("\nBienvenue dans le\ncours, je m'appelle\nSam\n"));
+        assert!(translated.ends_with("\net <b>aujourd'hui</b> nous\napprendrons Rust.\n"));
+    }
+}
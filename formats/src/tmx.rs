@@ -0,0 +1,349 @@
+use crate::error::FormatError;
+use crate::language::{parse_language, same_language_code};
+use chrono::{DateTime, NaiveDateTime};
+use itertools::Itertools;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use translation::memory::Segment;
+use translation::Language;
+
+const TMX_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const PROJECT_PROPERTY: &str = "x-project";
+const CONTEXT_PROPERTY: &str = "x-context";
+const IMPORT_PROVIDER: &str = "tmx";
+
+/// Inline elements whose content is native markup rather than text.
+const INLINE_CODES: [&[u8]; 5] = [b"bpt", b"ept", b"ph", b"it", b"ut"];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SkippedUnit {
+    /// Position of the `<tu>` in the document, starting from 0.
+    pub unit: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct TmxImport {
+    pub segments: Vec<Segment>,
+    pub skipped: Vec<SkippedUnit>,
+}
+
+#[derive(Default)]
+struct TranslationUnit {
+    source_language: Option<String>,
+    timestamp: Option<u64>,
+    provider: Option<String>,
+    project: Option<String>,
+    context: Option<String>,
+    variants: Vec<(String, String)>,
+}
+
+fn parse_date(date: &str) -> Option<u64> {
+    NaiveDateTime::parse_from_str(date, TMX_DATE_FORMAT)
+        .ok()
+        .and_then(|date| u64::try_from(date.and_utc().timestamp()).ok())
+}
+
+fn format_date(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .format(TMX_DATE_FORMAT)
+        .to_string()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, FormatError> {
+    Ok(element
+        .try_get_attribute(name)?
+        .map(|attribute| attribute.unescape_value())
+        .transpose()?
+        .map(|value| value.into_owned()))
+}
+
+impl TranslationUnit {
+    fn into_segments(
+        self,
+        default_source_language: Option<&str>,
+        now: u64,
+    ) -> Result<Vec<Segment>, String> {
+        let source_code = self
+            .source_language
+            .as_deref()
+            .or(default_source_language)
+            .filter(|code| *code != "*all*");
+        let source_index = match source_code {
+            Some(code) => self
+                .variants
+                .iter()
+                .position(|(language, _)| same_language_code(language, code))
+                .ok_or(format!("No variant in the source language {code}"))?,
+            None => 0,
+        };
+        let (source_code, source_text) = self
+            .variants
+            .get(source_index)
+            .ok_or("No variants".to_owned())?;
+        let source_language = parse_language(source_code)
+            .ok_or(format!("Unrecognised source language {source_code}"))?;
+
+        let segments = self
+            .variants
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != source_index)
+            .map(|(_, (target_code, target_text))| {
+                let target_language = parse_language(target_code)
+                    .ok_or(format!("Unrecognised target language {target_code}"))?;
+                Ok(Segment {
+                    source_text: source_text.clone(),
+                    target_text: target_text.clone(),
+                    source_language: Some(source_language.clone()),
+                    source_detected: false,
+                    target_language,
+                    provider: self.provider.clone().unwrap_or(IMPORT_PROVIDER.to_owned()),
+                    timestamp: self.timestamp.unwrap_or(now),
+                    project: self.project.clone(),
+                    context: self.context.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if segments.is_empty() {
+            return Err("No translations".to_owned());
+        }
+        Ok(segments)
+    }
+}
+
+/// Import the translation units of a TMX 1.4 document. Units that can't be understood, for
+/// example because of an unrecognised language, are reported rather than failing the import.
+pub fn import(document: &str, now: u64) -> Result<TmxImport, FormatError> {
+    let mut reader = Reader::from_str(document);
+    reader.config_mut().trim_text(false);
+
+    let mut import = TmxImport::default();
+    let mut default_source_language = None::<String>;
+    let mut unit = None::<TranslationUnit>;
+    let mut unit_index = 0;
+    let mut variant_language = None::<String>;
+    let mut text = None::<String>;
+    // the type and text of a property being read
+    let mut property = None::<(String, String)>;
+    let mut inline_code_depth = 0;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.name().as_ref() == b"header" =>
+            {
+                default_source_language = attribute(&element, "srclang")?;
+            }
+            Event::Start(element) => match element.name().as_ref() {
+                b"tu" => {
+                    let timestamp = attribute(&element, "changedate")?
+                        .or(attribute(&element, "creationdate")?)
+                        .and_then(|date| parse_date(&date));
+                    unit = Some(TranslationUnit {
+                        source_language: attribute(&element, "srclang")?,
+                        timestamp,
+                        provider: attribute(&element, "creationid")?,
+                        ..TranslationUnit::default()
+                    });
+                }
+                b"prop" if unit.is_some() => {
+                    property = attribute(&element, "type")?
+                        .filter(|kind| {
+                            [PROJECT_PROPERTY, CONTEXT_PROPERTY].contains(&kind.as_str())
+                        })
+                        .map(|kind| (kind, String::new()));
+                }
+                b"tuv" => {
+                    variant_language =
+                        attribute(&element, "xml:lang")?.or(attribute(&element, "lang")?);
+                }
+                b"seg" => text = Some(String::new()),
+                name if text.is_some() && INLINE_CODES.contains(&name) => {
+                    inline_code_depth += 1;
+                }
+                _ => {}
+            },
+            Event::Text(content) => {
+                let content = content.unescape()?;
+                if let Some((_, property)) = property.as_mut() {
+                    property.push_str(&content);
+                } else if let Some(text) = text.as_mut().filter(|_| inline_code_depth == 0) {
+                    text.push_str(&content);
+                }
+            }
+            Event::CData(content) => {
+                if let Some(text) = text.as_mut().filter(|_| inline_code_depth == 0) {
+                    text.push_str(&String::from_utf8_lossy(&content));
+                }
+            }
+            Event::End(element) => match element.name().as_ref() {
+                b"prop" => {
+                    if let (Some(unit), Some((kind, value))) = (unit.as_mut(), property.take()) {
+                        let value = Some(value.trim().to_owned());
+                        match kind.as_str() {
+                            PROJECT_PROPERTY => unit.project = value,
+                            _ => unit.context = value,
+                        }
+                    }
+                }
+                b"seg" => {
+                    if let (Some(unit), Some(language), Some(text)) =
+                        (unit.as_mut(), variant_language.clone(), text.take())
+                    {
+                        unit.variants.push((language, text));
+                    }
+                }
+                b"tu" => {
+                    if let Some(unit) = unit.take() {
+                        match unit.into_segments(default_source_language.as_deref(), now) {
+                            Ok(segments) => import.segments.extend(segments),
+                            Err(reason) => import.skipped.push(SkippedUnit {
+                                unit: unit_index,
+                                reason,
+                            }),
+                        }
+                    }
+                    unit_index += 1;
+                }
+                name if text.is_some() && INLINE_CODES.contains(&name) => {
+                    inline_code_depth -= 1;
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(import)
+}
+
+/// Export segments as a TMX 1.4 document, one translation unit per segment. Segments without
+/// a known source language can't be represented and are left out.
+pub fn export(segments: &[Segment]) -> String {
+    let segments = segments
+        .iter()
+        .filter(|segment| segment.source_language.is_some())
+        .collect_vec();
+    let source_languages = segments
+        .iter()
+        .filter_map(|segment| segment.source_language.as_ref().map(Language::to_string))
+        .unique()
+        .collect_vec();
+    let header_source_language = match source_languages.as_slice() {
+        [language] => language.clone(),
+        _ => "*all*".to_owned(),
+    };
+
+    let mut document = String::new();
+    document.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    document.push_str("<tmx version=\"1.4\">\n");
+    document.push_str(&format!(
+        "  <header creationtool=\"translation-api\" creationtoolversion=\"0.0.0\" \
+         segtype=\"sentence\" o-tmf=\"jsonl\" adminlang=\"en\" srclang=\"{}\" \
+         datatype=\"plaintext\"/>\n",
+        escape(&header_source_language)
+    ));
+    document.push_str("  <body>\n");
+    for segment in segments {
+        let Some(source_language) = segment.source_language.as_ref() else {
+            continue;
+        };
+        document.push_str(&format!(
+            "    <tu srclang=\"{}\" creationdate=\"{}\" creationid=\"{}\">\n",
+            source_language,
+            format_date(segment.timestamp),
+            escape(&segment.provider)
+        ));
+        for (kind, value) in [
+            (PROJECT_PROPERTY, &segment.project),
+            (CONTEXT_PROPERTY, &segment.context),
+        ] {
+            if let Some(value) = value {
+                document.push_str(&format!(
+                    "      <prop type=\"{kind}\">{}</prop>\n",
+                    escape(value)
+                ));
+            }
+        }
+        for (language, text) in [
+            (source_language, &segment.source_text),
+            (&segment.target_language, &segment.target_text),
+        ] {
+            document.push_str(&format!(
+                "      <tuv xml:lang=\"{language}\"><seg>{}</seg></tuv>\n",
+                escape(text)
+            ));
+        }
+        document.push_str("    </tu>\n");
+    }
+    document.push_str("  </body>\n");
+    document.push_str("</tmx>\n");
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tmx::{export, import};
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tmx version="1.4">
+  <header srclang="en-US" segtype="sentence" o-tmf="test" adminlang="en" datatype="plaintext" creationtool="test" creationtoolversion="1"/>
+  <body>
+    <tu creationdate="20240102T030405Z" creationid="vendor">
+      <prop type="x-project">website</prop>
+      <prop type="x-context">button</prop>
+      <tuv xml:lang="en-US"><seg>Press <bpt i="1">&lt;b&gt;</bpt>Save<ept i="1">&lt;/b&gt;</ept> &amp; exit</seg></tuv>
+      <tuv xml:lang="fr-FR"><seg>Appuyez sur <bpt i="1">&lt;b&gt;</bpt>Enregistrer<ept i="1">&lt;/b&gt;</ept> et quittez</seg></tuv>
+      <tuv xml:lang="de"><seg>Drücken Sie Speichern und beenden Sie</seg></tuv>
+    </tu>
+    <tu>
+      <tuv xml:lang="en"><seg>Hello</seg></tuv>
+      <tuv xml:lang="xx-YY"><seg>Bonjour</seg></tuv>
+    </tu>
+  </body>
+</tmx>"#;
+
+    #[test]
+    fn it_should_import_translation_units() {
+        let import = import(TMX, 0).unwrap();
+
+        assert_eq!(import.segments.len(), 2);
+        let french = &import.segments[0];
+        assert_eq!(french.source_text, "Press Save & exit");
+        assert_eq!(french.target_text, "Appuyez sur Enregistrer et quittez");
+        assert_eq!(french.source_language.as_ref().unwrap().to_string(), "en");
+        assert_eq!(french.target_language.to_string(), "fr");
+        assert_eq!(french.provider, "vendor");
+        assert_eq!(french.timestamp, 1704164645);
+        assert_eq!(french.project.as_deref(), Some("website"));
+        assert_eq!(french.context.as_deref(), Some("button"));
+        assert_eq!(import.segments[1].target_language.to_string(), "de");
+
+        assert_eq!(import.skipped.len(), 1);
+        assert_eq!(import.skipped[0].unit, 1);
+        assert_eq!(
+            import.skipped[0].reason,
+            "Unrecognised target language xx-YY"
+        );
+    }
+
+    #[test]
+    fn it_should_export_segments_that_can_be_imported() {
+        let segments = import(TMX, 0).unwrap().segments;
+        let exported = export(&segments);
+        assert!(exported.contains("<seg>Press Save &amp; exit</seg>"));
+
+        let reimported = import(&exported, 0).unwrap();
+        assert!(reimported.skipped.is_empty());
+        assert_eq!(reimported.segments.len(), 2);
+        assert_eq!(reimported.segments[0].source_text, "Press Save & exit");
+        assert_eq!(reimported.segments[0].timestamp, 1704164645);
+        assert_eq!(reimported.segments[0].context.as_deref(), Some("button"));
+        assert_eq!(reimported.segments[0].project.as_deref(), Some("website"));
+    }
+}
@@ -0,0 +1,574 @@
+use crate::error::FormatError;
+use crate::language::parse_language;
+use itertools::Itertools;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use translation::placeholder::placeholders;
+use translation::{Language, TranslationInput, TranslationOutput};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum XliffVersion {
+    V1,
+    V2,
+}
+
+/// States of an XLIFF 1.2 `<target>` that still need translating.
+const UNTRANSLATED_STATES: [&str; 2] = ["new", "needs-translation"];
+const TRANSLATED_STATE: &str = "translated";
+const FINAL_STATE: &str = "final";
+
+enum Edit {
+    Fixed(String),
+    Target(usize),
+    SegmentStart(usize),
+}
+
+struct Replacement {
+    start: usize,
+    end: usize,
+    edit: Edit,
+}
+
+struct XliffUnit {
+    /// Inner XML of the `<source>`, inline elements included, without surrounding whitespace.
+    source: String,
+    leading_whitespace: String,
+    trailing_whitespace: String,
+    /// XLIFF 2.0 `<segment>` start tag with the translated state.
+    translated_segment_start: Option<String>,
+}
+
+#[derive(Default)]
+struct PendingUnit {
+    source: Option<String>,
+    target_span: Option<(usize, usize)>,
+    translated: bool,
+    segment_start: Option<(usize, usize, String)>,
+}
+
+pub struct XliffDocument {
+    document: String,
+    version: XliffVersion,
+    source_language: Option<Language>,
+    target_language: Language,
+    units: Vec<XliffUnit>,
+    replacements: Vec<Replacement>,
+}
+
+pub struct TranslatedXliff {
+    pub document: String,
+    /// Units left untranslated because the translation broke their inline elements.
+    pub untranslated: usize,
+}
+
+fn name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.name().as_ref()).into_owned()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, FormatError> {
+    Ok(element
+        .try_get_attribute(name)?
+        .map(|attribute| attribute.unescape_value())
+        .transpose()?
+        .map(|value| value.into_owned()))
+}
+
+/// Render `element` as a start tag, adding or replacing the attribute `name`.
+fn with_attribute(element: &BytesStart, name: &str, value: &str) -> Result<String, FormatError> {
+    let mut tag = format!("<{}", self::name(element));
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        if attribute.key.as_ref() == name.as_bytes() {
+            continue;
+        }
+        let value = String::from_utf8_lossy(&attribute.value);
+        let quote = if value.contains('"') { '\'' } else { '"' };
+        tag.push_str(&format!(
+            " {}={quote}{value}{quote}",
+            String::from_utf8_lossy(attribute.key.as_ref())
+        ));
+    }
+    tag.push_str(&format!(" {name}=\"{}\">", escape(value)));
+    Ok(tag)
+}
+
+fn is_translatable(element: &BytesStart) -> Result<bool, FormatError> {
+    Ok(attribute(element, "translate")?.as_deref() != Some("no"))
+}
+
+fn inline_tags(text: &str) -> Vec<&str> {
+    placeholders(text)
+        .into_iter()
+        .filter(|placeholder| placeholder.starts_with('<'))
+        .sorted()
+        .collect_vec()
+}
+
+/// Length of the entity reference (`&amp;`, `&#38;`, `&#x26;`) at the start of `text`, if any.
+fn entity_length(text: &str) -> Option<usize> {
+    let end = text.find(';')?;
+    let name = &text[1..end];
+    let valid = match name.strip_prefix('#') {
+        Some(code) => match code.strip_prefix('x') {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !code.is_empty() && code.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => {
+            name.chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "_-.".contains(c))
+        }
+    };
+    valid.then_some(end + 1)
+}
+
+/// Escape the `&`, `<` and `>` of `translation` that don't start or end markup, so that a
+/// translation like "Q&A" or "a < b" is written back as well formed XML.
+fn escape_text(translation: &str) -> String {
+    let mut escaped = String::with_capacity(translation.len());
+    let mut in_tag = false;
+    let mut rest = translation;
+    while let Some(c) = rest.chars().next() {
+        let next = rest[c.len_utf8()..].chars().next();
+        match c {
+            '&' => match entity_length(rest) {
+                Some(length) => {
+                    escaped.push_str(&rest[..length]);
+                    rest = &rest[length..];
+                    continue;
+                }
+                None => escaped.push_str("&amp;"),
+            },
+            '<' if !in_tag
+                && next.is_some_and(|next| next.is_alphabetic() || "_/!?".contains(next)) =>
+            {
+                in_tag = true;
+                escaped.push(c);
+            }
+            '<' => escaped.push_str("&lt;"),
+            '>' if in_tag => {
+                in_tag = false;
+                escaped.push(c);
+            }
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    escaped
+}
+
+/// Whether `translation` is well formed, only uses known entities and keeps all of the
+/// inline elements of `source`.
+fn is_valid_translation(source: &str, translation: &str) -> bool {
+    let fragment = format!("<target>{translation}</target>");
+    let mut reader = Reader::from_str(&fragment);
+    loop {
+        match reader.read_event() {
+            Err(_) => return false,
+            Ok(Event::Text(text)) if text.unescape().is_err() => return false,
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+        }
+    }
+    inline_tags(source) == inline_tags(translation)
+}
+
+impl XliffDocument {
+    /// Parse an XLIFF 1.2 or 2.0 document, finding the units that need translating to
+    /// `target_language`. Units marked `translate="no"`, and units that already have a
+    /// translation, are left alone.
+    pub fn parse(document: String, target_language: Language) -> Result<Self, FormatError> {
+        let mut version = XliffVersion::V1;
+        let mut source_language = None;
+        let mut units = Vec::new();
+        let mut replacements = Vec::new();
+        let mut translatable_stack = Vec::<bool>::new();
+        let mut pending = None::<PendingUnit>;
+        let target_code = target_language.to_string();
+
+        let mut reader = Reader::from_str(&document);
+        reader.config_mut().trim_text(false);
+        loop {
+            let start = reader.buffer_position() as usize;
+            match reader.read_event()? {
+                Event::Start(element) => {
+                    let end = reader.buffer_position() as usize;
+                    match (version, name(&element).as_str()) {
+                        (_, "xliff")
+                            if attribute(&element, "version")?
+                                .is_some_and(|v| v.starts_with('2')) =>
+                        {
+                            version = XliffVersion::V2;
+                            source_language = attribute(&element, "srcLang")?
+                                .and_then(|code| parse_language(&code));
+                            replacements.push(Replacement {
+                                start,
+                                end,
+                                edit: Edit::Fixed(with_attribute(
+                                    &element,
+                                    "trgLang",
+                                    &target_code,
+                                )?),
+                            });
+                        }
+                        (XliffVersion::V1, "file") => {
+                            source_language = attribute(&element, "source-language")?
+                                .and_then(|code| parse_language(&code));
+                            translatable_stack.push(true);
+                            replacements.push(Replacement {
+                                start,
+                                end,
+                                edit: Edit::Fixed(with_attribute(
+                                    &element,
+                                    "target-language",
+                                    &target_code,
+                                )?),
+                            });
+                        }
+                        (XliffVersion::V2, "file" | "unit") | (_, "group") => {
+                            translatable_stack.push(is_translatable(&element)?);
+                        }
+                        (XliffVersion::V1, "trans-unit") => {
+                            translatable_stack.push(is_translatable(&element)?);
+                            pending = Some(PendingUnit::default());
+                        }
+                        (XliffVersion::V2, "segment") => {
+                            let state = attribute(&element, "state")?;
+                            pending = Some(PendingUnit {
+                                translated: state.as_deref() == Some(FINAL_STATE),
+                                segment_start: Some((
+                                    start,
+                                    end,
+                                    with_attribute(&element, "state", TRANSLATED_STATE)?,
+                                )),
+                                ..PendingUnit::default()
+                            });
+                        }
+                        (_, "source") if pending.is_some() => {
+                            let source = reader.read_text(element.name())?;
+                            let end = reader.buffer_position() as usize;
+                            if let Some(pending) = pending.as_mut() {
+                                pending.source = Some(source.into_owned());
+                                pending.target_span.get_or_insert((end, end));
+                            }
+                        }
+                        (_, "target") if pending.is_some() => {
+                            let state = attribute(&element, "state")?;
+                            let target = reader.read_text(element.name())?;
+                            let end = reader.buffer_position() as usize;
+                            let untranslated = target.trim().is_empty()
+                                || (version == XliffVersion::V1
+                                    && UNTRANSLATED_STATES
+                                        .contains(&state.as_deref().unwrap_or("")));
+                            if let Some(pending) = pending.as_mut() {
+                                if untranslated {
+                                    pending.target_span = Some((start, end));
+                                } else {
+                                    pending.translated = true;
+                                }
+                            }
+                        }
+                        // alternatives and ignorables have sources and targets of their own
+                        (XliffVersion::V1, "alt-trans") | (XliffVersion::V2, "ignorable") => {
+                            reader.read_to_end(element.name())?;
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Empty(element) if name(&element) == "target" => {
+                    let end = reader.buffer_position() as usize;
+                    if let Some(pending) = pending.as_mut() {
+                        pending.target_span = Some((start, end));
+                    }
+                }
+                Event::End(element) => {
+                    let element_name =
+                        String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                    let is_unit_end = match version {
+                        XliffVersion::V1 => element_name == "trans-unit",
+                        XliffVersion::V2 => element_name == "segment",
+                    };
+                    if is_unit_end {
+                        let translatable =
+                            translatable_stack.iter().all(|translatable| *translatable);
+                        if let Some(pending) = pending.take() {
+                            match (pending.source, pending.target_span) {
+                                (Some(source), Some((target_start, target_end)))
+                                    if translatable
+                                        && !pending.translated
+                                        && !source.trim().is_empty() =>
+                                {
+                                    let index = units.len();
+                                    let trimmed_start = source.trim_start();
+                                    let leading_whitespace =
+                                        source[..source.len() - trimmed_start.len()].to_owned();
+                                    let trimmed = trimmed_start.trim_end();
+                                    let trailing_whitespace =
+                                        trimmed_start[trimmed.len()..].to_owned();
+                                    let mut translated_segment_start = None;
+                                    if let Some((start, end, tag)) = pending.segment_start {
+                                        replacements.push(Replacement {
+                                            start,
+                                            end,
+                                            edit: Edit::SegmentStart(index),
+                                        });
+                                        translated_segment_start = Some(tag);
+                                    }
+                                    replacements.push(Replacement {
+                                        start: target_start,
+                                        end: target_end,
+                                        edit: Edit::Target(index),
+                                    });
+                                    units.push(XliffUnit {
+                                        source: trimmed.to_owned(),
+                                        leading_whitespace,
+                                        trailing_whitespace,
+                                        translated_segment_start,
+                                    });
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    let is_scope_end = match version {
+                        XliffVersion::V1 => {
+                            matches!(element_name.as_str(), "file" | "group" | "trans-unit")
+                        }
+                        XliffVersion::V2 => {
+                            matches!(element_name.as_str(), "file" | "group" | "unit")
+                        }
+                    };
+                    if is_scope_end {
+                        translatable_stack.pop();
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        replacements.sort_by_key(|replacement| replacement.start);
+        Ok(XliffDocument {
+            document,
+            version,
+            source_language,
+            target_language,
+            units,
+            replacements,
+        })
+    }
+
+    /// One input per unit that needs translating, in document order.
+    pub fn inputs(&self, source_language: Option<Language>) -> Vec<TranslationInput> {
+        self.units
+            .iter()
+            .map(|unit| {
+                TranslationInput::new(
+                    unit.source.clone(),
+                    source_language.clone().or(self.source_language.clone()),
+                    self.target_language.clone(),
+                )
+            })
+            .collect_vec()
+    }
+
+    /// Write the translations of [Self::inputs] into the document.
+    pub fn apply(self, outputs: &[TranslationOutput]) -> TranslatedXliff {
+        let valid = self
+            .units
+            .iter()
+            .zip(outputs.iter().map(Some).chain(std::iter::repeat(None)))
+            .map(|(unit, output)| {
+                output
+                    .map(|output| escape_text(output.text()))
+                    .filter(|translation| is_valid_translation(&unit.source, translation))
+            })
+            .collect_vec();
+
+        let mut document = String::with_capacity(self.document.len());
+        let mut position = 0;
+        for replacement in &self.replacements {
+            document.push_str(&self.document[position..replacement.start]);
+            let original = &self.document[replacement.start..replacement.end];
+            match &replacement.edit {
+                Edit::Fixed(text) => document.push_str(text),
+                Edit::Target(index) => match &valid[*index] {
+                    Some(translation) => {
+                        let unit = &self.units[*index];
+                        let start_tag = match self.version {
+                            XliffVersion::V1 => format!("<target state=\"{TRANSLATED_STATE}\">"),
+                            XliffVersion::V2 => "<target>".to_owned(),
+                        };
+                        document.push_str(&format!(
+                            "{start_tag}{}{}{}</target>",
+                            unit.leading_whitespace, translation, unit.trailing_whitespace
+                        ));
+                    }
+                    None => document.push_str(original),
+                },
+                Edit::SegmentStart(index) => {
+                    match (&valid[*index], &self.units[*index].translated_segment_start) {
+                        (Some(_), Some(tag)) => document.push_str(tag),
+                        _ => document.push_str(original),
+                    }
+                }
+            }
+            position = replacement.end;
+        }
+        document.push_str(&self.document[position..]);
+
+        TranslatedXliff {
+            document,
+            untranslated: valid.iter().filter(|output| output.is_none()).count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xliff::XliffDocument;
+    use std::str::FromStr;
+    use translation::{Language, TranslationOutput};
+
+    const XLIFF_1_2: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+  <file source-language="en" datatype="plaintext" original="app">
+    <body>
+      <trans-unit id="save">
+        <source>Press <g id="1">Save</g> now<x id="2"/></source>
+      </trans-unit>
+      <trans-unit id="brand" translate="no">
+        <source>Acme</source>
+      </trans-unit>
+      <trans-unit id="done">
+        <source>Done</source>
+        <target state="final">Terminé</target>
+      </trans-unit>
+      <trans-unit id="empty">
+        <source> Open &amp; close </source>
+        <target state="new"/>
+        <alt-trans><source>Open</source><target>Ouvrir</target></alt-trans>
+      </trans-unit>
+    </body>
+  </file>
+</xliff>"#;
+
+    #[test]
+    fn it_should_translate_xliff_1_2() {
+        let document =
+            XliffDocument::parse(XLIFF_1_2.to_owned(), Language::from_str("fr").unwrap()).unwrap();
+        let inputs = document.inputs(None);
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(
+            inputs[0].text(),
+            r#"Press <g id="1">Save</g> now<x id="2"/>"#
+        );
+        assert_eq!(inputs[0].source_language().unwrap().to_string(), "en");
+        assert_eq!(inputs[1].text(), "Open &amp; close");
+
+        let translated = document.apply(&[
+            TranslationOutput::new(
+                r#"Appuyez sur <g id="1">Enregistrer</g> maintenant<x id="2"/>"#.to_owned(),
+                None,
+            ),
+            TranslationOutput::new("Ouvrir &amp; fermer".to_owned(), None),
+        ]);
+        assert_eq!(translated.untranslated, 0);
+        let expected = XLIFF_1_2
+            .replace(
+                r#"original="app">"#,
+                r#"original="app" target-language="fr">"#,
+            )
+            .replace(
+                r#"now<x id="2"/></source>"#,
+                r#"now<x id="2"/></source><target state="translated">Appuyez sur <g id="1">Enregistrer</g> maintenant<x id="2"/></target>"#,
+            )
+            .replace(
+                r#"<target state="new"/>"#,
+                r#"<target state="translated"> Ouvrir &amp; fermer </target>"#,
+            );
+        assert_eq!(translated.document, expected);
+    }
+
+    const XLIFF_2_0: &str = r#"<xliff version="2.0" xmlns="urn:oasis:names:tc:xliff:document:2.0" srcLang="en">
+  <file id="f1">
+    <unit id="1">
+      <segment><source>Hello <pc id="1">world</pc><ph id="2"/></source></segment>
+      <segment state="final"><source>Bye</source><target>Au revoir</target></segment>
+    </unit>
+    <unit id="2" translate="no"><segment><source>Acme</source></segment></unit>
+  </file>
+</xliff>"#;
+
+    #[test]
+    fn it_should_translate_xliff_2_0() {
+        let document =
+            XliffDocument::parse(XLIFF_2_0.to_owned(), Language::from_str("de").unwrap()).unwrap();
+        let inputs = document.inputs(None);
+        assert_eq!(inputs.len(), 1);
+
+        let translated = document.apply(&[TranslationOutput::new(
+            r#"Hallo <pc id="1">Welt</pc><ph id="2"/>"#.to_owned(),
+            None,
+        )]);
+        assert!(translated
+            .document
+            .contains(r#"srcLang="en" trgLang="de">"#));
+        assert!(translated.document.contains(
+            r#"<segment state="translated"><source>Hello <pc id="1">world</pc><ph id="2"/></source><target>Hallo <pc id="1">Welt</pc><ph id="2"/></target></segment>"#
+        ));
+        assert!(translated
+            .document
+            .contains(r#"<segment state="final"><source>Bye</source><target>Au revoir</target>"#));
+    }
+
+    #[test]
+    fn it_should_not_write_translations_that_break_inline_elements() {
+        let document =
+            XliffDocument::parse(XLIFF_2_0.to_owned(), Language::from_str("de").unwrap()).unwrap();
+        let translated = document.apply(&[TranslationOutput::new(
+            r#"Hallo <pc id="1">Welt"#.to_owned(),
+            None,
+        )]);
+        assert_eq!(translated.untranslated, 1);
+        assert!(translated.document.contains(
+            r#"<segment><source>Hello <pc id="1">world</pc><ph id="2"/></source></segment>"#
+        ));
+    }
+
+    #[test]
+    fn it_should_escape_bare_markup_characters() {
+        let document =
+            XliffDocument::parse(XLIFF_1_2.to_owned(), Language::from_str("fr").unwrap()).unwrap();
+        let translated = document.apply(&[
+            TranslationOutput::new(
+                r#"Q&A : 1 < 2 > 0 <g id="1">Enregistrer</g> &amp; &#233;<x id="2"/>"#.to_owned(),
+                None,
+            ),
+            TranslationOutput::new("Ouvrir & fermer".to_owned(), None),
+        ]);
+        assert_eq!(translated.untranslated, 0);
+        assert!(translated.document.contains(
+            r#"<target state="translated">Q&amp;A : 1 &lt; 2 &gt; 0 <g id="1">Enregistrer</g> &amp; &#233;<x id="2"/></target>"#
+        ));
+        assert!(translated
+            .document
+            .contains(r#"<target state="translated"> Ouvrir &amp; fermer </target>"#));
+    }
+
+    #[test]
+    fn it_should_not_write_translations_with_unknown_entities() {
+        let document =
+            XliffDocument::parse(XLIFF_2_0.to_owned(), Language::from_str("de").unwrap()).unwrap();
+        let translated = document.apply(&[TranslationOutput::new(
+            r#"Hallo&nbsp;<pc id="1">Welt</pc><ph id="2"/>"#.to_owned(),
+            None,
+        )]);
+        assert_eq!(translated.untranslated, 1);
+    }
+}
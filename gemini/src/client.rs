@@ -0,0 +1,273 @@
+use crate::error::GeminiError;
+use crate::model::GeminiModel;
+use reqwest::StatusCode;
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::RetryTransientMiddleware;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// The fewest tokens a response is allowed by default, so short prompts can still have
+/// responses longer than themselves.
+const MIN_MAX_TOKENS: usize = 1024;
+
+/// What the model is told after the partial response it's to continue.
+const CONTINUATION_PROMPT: &str =
+    "Continue exactly where your response stopped, without repeating any of it.";
+
+/// Clones share the limit on parallel requests.
+#[derive(Clone)]
+pub struct GeminiClient {
+    api: String,
+    model: GeminiModel,
+    parallel_requests_semaphore: Arc<Semaphore>,
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+impl GeminiClient {
+    pub fn try_new(
+        api: String,
+        model: GeminiModel,
+        api_key: String,
+        max_parallel_requests: usize,
+    ) -> Result<Self, GeminiError> {
+        if max_parallel_requests == 0 {
+            return Err(GeminiError::InvalidMaxParallelRequestConfig);
+        }
+
+        let mut api_key_value = reqwest::header::HeaderValue::try_from(api_key)?;
+        api_key_value.set_sensitive(true);
+        let mut default_headers = reqwest::header::HeaderMap::new();
+        default_headers.insert("x-goog-api-key", api_key_value);
+
+        let client = reqwest::ClientBuilder::new()
+            .default_headers(default_headers)
+            .build()?;
+
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
+        let client_with_middleware = reqwest_middleware::ClientBuilder::new(client)
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build();
+
+        Ok(GeminiClient {
+            api,
+            model,
+            parallel_requests_semaphore: Arc::new(Semaphore::new(max_parallel_requests)),
+            client: client_with_middleware,
+        })
+    }
+
+    /// A client of `model` that shares the limit on parallel requests of this one.
+    pub fn with_model(&self, model: GeminiModel) -> Self {
+        GeminiClient {
+            model,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GeminiPart {
+    text: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GeminiContent {
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GeminiFinishReason {
+    Stop,
+    /// The response was cut off at the maximum number of tokens.
+    MaxTokens,
+    Safety,
+    Recitation,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    content: GeminiContent,
+    finish_reason: Option<GeminiFinishReason>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GeminiGenerateContentResponse {
+    candidates: Vec<GeminiCandidate>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GeminiCompletion {
+    pub text: String,
+    pub finish_reason: Option<GeminiFinishReason>,
+}
+
+impl GeminiCompletion {
+    pub fn is_truncated(&self) -> bool {
+        self.finish_reason == Some(GeminiFinishReason::MaxTokens)
+    }
+}
+
+impl TryFrom<GeminiGenerateContentResponse> for GeminiCompletion {
+    type Error = GeminiError;
+
+    fn try_from(response: GeminiGenerateContentResponse) -> Result<Self, Self::Error> {
+        let candidate =
+            response
+                .candidates
+                .into_iter()
+                .next()
+                .ok_or(GeminiError::UnexpectedApiResponse(
+                    "Expected response from gemini but there wasn't one.".to_owned(),
+                ))?;
+        Ok(GeminiCompletion {
+            text: candidate
+                .content
+                .parts
+                .iter()
+                .map(|part| part.text.as_str())
+                .collect(),
+            finish_reason: candidate.finish_reason,
+        })
+    }
+}
+
+impl GeminiClient {
+    /// Enough tokens for a response of about `expected_characters`, twice as many, which covers
+    /// translations into scripts that take several tokens a character, within what the model
+    /// can give.
+    pub fn max_tokens_for(&self, expected_characters: usize) -> usize {
+        (expected_characters * 2).clamp(MIN_MAX_TOKENS, self.model.max_output_tokens())
+    }
+
+    fn generate_content_request(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        partial: Option<&str>,
+        max_tokens: Option<usize>,
+    ) -> serde_json::Value {
+        let max_tokens = max_tokens.unwrap_or_else(|| self.max_tokens_for(prompt.chars().count()));
+        let mut contents = vec![json!({
+            "role": "user",
+            "parts": [{ "text": prompt }]
+        })];
+        if let Some(partial) = partial {
+            contents.push(json!({
+                "role": "model",
+                "parts": [{ "text": partial }]
+            }));
+            contents.push(json!({
+                "role": "user",
+                "parts": [{ "text": CONTINUATION_PROMPT }]
+            }));
+        }
+        json!({
+            "systemInstruction": {
+                "parts": [{ "text": system_prompt }]
+            },
+            "contents": contents,
+            "generationConfig": {
+                "maxOutputTokens": max_tokens,
+            },
+        })
+    }
+
+    async fn generate_content(
+        &self,
+        request_json: serde_json::Value,
+    ) -> Result<GeminiCompletion, GeminiError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
+
+        let url = format!("{}/models/{}:generateContent", self.api, self.model);
+        let response = self.client.post(&url).json(&request_json).send().await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => response
+                .json::<GeminiGenerateContentResponse>()
+                .await?
+                .try_into(),
+            _ => Err(GeminiError::UnexpectedApiResponse(
+                format!("Expected 200 from {url} but got {status}").to_owned(),
+            )),
+        }
+    }
+
+    /// Respond to `prompt`, with at most `max_tokens` or, by default, enough for a translation
+    /// of the prompt.
+    pub async fn respond_to(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        max_tokens: Option<usize>,
+    ) -> Result<GeminiCompletion, GeminiError> {
+        let request_json = self.generate_content_request(system_prompt, prompt, None, max_tokens);
+        self.generate_content(request_json).await
+    }
+
+    /// Continue a `partial` response to `prompt` that was truncated, responding with the rest
+    /// of it.
+    pub async fn continue_response(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        partial: &str,
+        max_tokens: Option<usize>,
+    ) -> Result<GeminiCompletion, GeminiError> {
+        let request_json =
+            self.generate_content_request(system_prompt, prompt, Some(partial), max_tokens);
+        self.generate_content(request_json).await
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GeminiModelResponse {
+    name: String,
+}
+
+impl GeminiClient {
+    pub async fn get_model(&self) -> Result<GeminiModelResponse, GeminiError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
+
+        let url = format!("{}/models/{}", self.api, self.model);
+        let response = self.client.get(&url).send().await?;
+
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json::<GeminiModelResponse>().await?),
+            _ => Err(GeminiError::UnexpectedApiResponse(
+                format!("Expected 200 from {url} but got {status}").to_owned(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{GeminiCompletion, GeminiFinishReason, GeminiGenerateContentResponse};
+
+    #[test]
+    fn it_should_report_responses_cut_off_at_the_maximum_tokens() {
+        let response = serde_json::from_value::<GeminiGenerateContentResponse>(serde_json::json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": "Il fait " }, { "text": "très" }] },
+                "finishReason": "MAX_TOKENS",
+            }],
+        }))
+        .unwrap();
+
+        let completion = GeminiCompletion::try_from(response).unwrap();
+        assert_eq!(completion.text, "Il fait très");
+        assert_eq!(
+            completion.finish_reason,
+            Some(GeminiFinishReason::MaxTokens)
+        );
+        assert!(completion.is_truncated());
+    }
+}
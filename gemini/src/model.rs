@@ -0,0 +1,54 @@
+use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
+
+#[derive(Clone, Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
+pub enum GeminiModel {
+    #[serde(rename = "gemini-2.0-flash-exp")]
+    Gemini2Point0FlashExp,
+    #[serde(rename = "gemini-1.5-pro")]
+    Gemini1Point5Pro,
+    #[serde(rename = "gemini-1.5-flash")]
+    Gemini1Point5Flash,
+    #[serde(rename = "gemini-1.5-flash-8b")]
+    Gemini1Point5Flash8B,
+    #[serde(rename = "gemini-1.0-pro")]
+    Gemini1Point0Pro,
+}
+
+impl GeminiModel {
+    /// The most tokens the model can respond with.
+    pub fn max_output_tokens(&self) -> usize {
+        match self {
+            GeminiModel::Gemini1Point0Pro => 2048,
+            _ => 8192,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::GeminiModel;
+
+    #[test]
+    fn it_should_get_models_from_string() {
+        assert_eq!(
+            GeminiModel::try_from("gemini-2.0-flash-exp".to_owned()).unwrap(),
+            GeminiModel::Gemini2Point0FlashExp
+        );
+        assert_eq!(
+            GeminiModel::try_from("gemini-1.5-pro".to_owned()).unwrap(),
+            GeminiModel::Gemini1Point5Pro
+        );
+        assert_eq!(
+            GeminiModel::try_from("gemini-1.5-flash".to_owned()).unwrap(),
+            GeminiModel::Gemini1Point5Flash
+        );
+        assert_eq!(
+            GeminiModel::try_from("gemini-1.5-flash-8b".to_owned()).unwrap(),
+            GeminiModel::Gemini1Point5Flash8B
+        );
+        assert_eq!(
+            GeminiModel::try_from("gemini-1.0-pro".to_owned()).unwrap(),
+            GeminiModel::Gemini1Point0Pro
+        );
+    }
+}
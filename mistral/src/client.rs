@@ -0,0 +1,265 @@
+use crate::error::MistralError;
+use crate::model::MistralModel;
+use reqwest::StatusCode;
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::RetryTransientMiddleware;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// The fewest tokens a response is allowed by default, so short prompts can still have
+/// responses longer than themselves.
+const MIN_MAX_TOKENS: usize = 1024;
+
+/// What the model is told after the partial response it's to continue.
+const CONTINUATION_PROMPT: &str =
+    "Continue exactly where your response stopped, without repeating any of it.";
+
+/// Clones share the limit on parallel requests.
+#[derive(Clone)]
+pub struct MistralClient {
+    api: String,
+    model: MistralModel,
+    parallel_requests_semaphore: Arc<Semaphore>,
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+impl MistralClient {
+    pub fn try_new(
+        api: String,
+        model: MistralModel,
+        api_key: String,
+        max_parallel_requests: usize,
+    ) -> Result<Self, MistralError> {
+        if max_parallel_requests == 0 {
+            return Err(MistralError::InvalidMaxParallelRequestConfig);
+        }
+
+        let mut api_key_value =
+            reqwest::header::HeaderValue::try_from(format!("Bearer {}", api_key))?;
+        api_key_value.set_sensitive(true);
+        let mut default_headers = reqwest::header::HeaderMap::new();
+        default_headers.insert("authorization", api_key_value);
+
+        let client = reqwest::ClientBuilder::new()
+            .default_headers(default_headers)
+            .build()?;
+
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
+        let client_with_middleware = reqwest_middleware::ClientBuilder::new(client)
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build();
+
+        Ok(MistralClient {
+            api,
+            model,
+            parallel_requests_semaphore: Arc::new(Semaphore::new(max_parallel_requests)),
+            client: client_with_middleware,
+        })
+    }
+
+    /// A client of `model` that shares the limit on parallel requests of this one.
+    pub fn with_model(&self, model: MistralModel) -> Self {
+        MistralClient {
+            model,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct MistralMessageResponse {
+    content: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MistralFinishReason {
+    Stop,
+    /// The response was cut off at the maximum number of tokens.
+    Length,
+    ModelLength,
+    Error,
+    ToolCalls,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct MistralChoiceResponse {
+    message: MistralMessageResponse,
+    finish_reason: Option<MistralFinishReason>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct MistralChatResponse {
+    choices: Vec<MistralChoiceResponse>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MistralCompletion {
+    pub text: String,
+    pub finish_reason: Option<MistralFinishReason>,
+}
+
+impl MistralCompletion {
+    pub fn is_truncated(&self) -> bool {
+        self.finish_reason == Some(MistralFinishReason::Length)
+    }
+}
+
+impl TryFrom<MistralChatResponse> for MistralCompletion {
+    type Error = MistralError;
+
+    fn try_from(response: MistralChatResponse) -> Result<Self, Self::Error> {
+        let choice =
+            response
+                .choices
+                .into_iter()
+                .next()
+                .ok_or(MistralError::UnexpectedApiResponse(
+                    "Expected response from mistral but there wasn't one.".to_owned(),
+                ))?;
+        Ok(MistralCompletion {
+            text: choice.message.content,
+            finish_reason: choice.finish_reason,
+        })
+    }
+}
+
+impl MistralClient {
+    /// Enough tokens for a response of about `expected_characters`, twice as many, which covers
+    /// translations into scripts that take several tokens a character, within what the model
+    /// can give.
+    pub fn max_tokens_for(&self, expected_characters: usize) -> usize {
+        (expected_characters * 2).clamp(MIN_MAX_TOKENS, self.model.max_output_tokens())
+    }
+
+    fn chat_request(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        partial: Option<&str>,
+        max_tokens: Option<usize>,
+    ) -> serde_json::Value {
+        let max_tokens = max_tokens.unwrap_or_else(|| self.max_tokens_for(prompt.chars().count()));
+        let mut messages = vec![
+            json!({
+                "role": "system",
+                "content": system_prompt
+            }),
+            json!({
+                "role": "user",
+                "content": prompt
+            }),
+        ];
+        if let Some(partial) = partial {
+            messages.push(json!({
+                "role": "assistant",
+                "content": partial
+            }));
+            messages.push(json!({
+                "role": "user",
+                "content": CONTINUATION_PROMPT
+            }));
+        }
+        json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": max_tokens,
+        })
+    }
+
+    async fn send_chat(
+        &self,
+        request_json: serde_json::Value,
+    ) -> Result<MistralCompletion, MistralError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
+
+        let url = format!("{}/chat/completions", self.api);
+        let response = self.client.post(&url).json(&request_json).send().await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => response.json::<MistralChatResponse>().await?.try_into(),
+            _ => Err(MistralError::UnexpectedApiResponse(
+                format!("Expected 200 from {url} but got {status}").to_owned(),
+            )),
+        }
+    }
+
+    /// Respond to `prompt`, with at most `max_tokens` or, by default, enough for a translation
+    /// of the prompt.
+    pub async fn respond_to(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        max_tokens: Option<usize>,
+    ) -> Result<MistralCompletion, MistralError> {
+        let request_json = self.chat_request(system_prompt, prompt, None, max_tokens);
+        self.send_chat(request_json).await
+    }
+
+    /// Continue a `partial` response to `prompt` that was truncated, responding with the rest
+    /// of it.
+    pub async fn continue_response(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        partial: &str,
+        max_tokens: Option<usize>,
+    ) -> Result<MistralCompletion, MistralError> {
+        let request_json = self.chat_request(system_prompt, prompt, Some(partial), max_tokens);
+        self.send_chat(request_json).await
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MistralModelsResponse {
+    #[serde(rename = "data")]
+    models: Vec<MistralModelResponse>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MistralModelResponse {
+    id: String,
+}
+
+impl MistralClient {
+    pub async fn get_models(&self) -> Result<MistralModelsResponse, MistralError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
+
+        let url = format!("{}/models", self.api);
+        let response = self.client.get(&url).send().await?;
+
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json::<MistralModelsResponse>().await?),
+            _ => Err(MistralError::UnexpectedApiResponse(
+                format!("Expected 200 from {url} but got {status}").to_owned(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{MistralChatResponse, MistralCompletion, MistralFinishReason};
+
+    #[test]
+    fn it_should_report_responses_cut_off_at_the_maximum_tokens() {
+        let response = serde_json::from_value::<MistralChatResponse>(serde_json::json!({
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Il fait très" },
+                "finish_reason": "length",
+            }],
+        }))
+        .unwrap();
+
+        let completion = MistralCompletion::try_from(response).unwrap();
+        assert_eq!(completion.text, "Il fait très");
+        assert_eq!(completion.finish_reason, Some(MistralFinishReason::Length));
+        assert!(completion.is_truncated());
+    }
+}
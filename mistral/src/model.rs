@@ -0,0 +1,57 @@
+use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
+
+#[derive(Clone, Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
+pub enum MistralModel {
+    #[serde(rename = "mistral-large-latest")]
+    MistralLargeLatest,
+    #[serde(rename = "mistral-small-latest")]
+    MistralSmallLatest,
+    #[serde(rename = "ministral-8b-latest")]
+    Ministral8BLatest,
+    #[serde(rename = "ministral-3b-latest")]
+    Ministral3BLatest,
+    #[serde(rename = "open-mistral-nemo")]
+    OpenMistralNemo,
+    #[serde(rename = "open-mixtral-8x22b")]
+    OpenMixtral8x22B,
+}
+
+impl MistralModel {
+    /// The most tokens the model is asked to respond with, well within its context window.
+    pub fn max_output_tokens(&self) -> usize {
+        8192
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::MistralModel;
+
+    #[test]
+    fn it_should_get_models_from_string() {
+        assert_eq!(
+            MistralModel::try_from("mistral-large-latest".to_owned()).unwrap(),
+            MistralModel::MistralLargeLatest
+        );
+        assert_eq!(
+            MistralModel::try_from("mistral-small-latest".to_owned()).unwrap(),
+            MistralModel::MistralSmallLatest
+        );
+        assert_eq!(
+            MistralModel::try_from("ministral-8b-latest".to_owned()).unwrap(),
+            MistralModel::Ministral8BLatest
+        );
+        assert_eq!(
+            MistralModel::try_from("ministral-3b-latest".to_owned()).unwrap(),
+            MistralModel::Ministral3BLatest
+        );
+        assert_eq!(
+            MistralModel::try_from("open-mistral-nemo".to_owned()).unwrap(),
+            MistralModel::OpenMistralNemo
+        );
+        assert_eq!(
+            MistralModel::try_from("open-mixtral-8x22b".to_owned()).unwrap(),
+            MistralModel::OpenMixtral8x22B
+        );
+    }
+}
@@ -5,11 +5,23 @@ use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use sse::EventStream;
+use std::sync::Arc;
 use tokio::sync::Semaphore;
 
+/// The fewest tokens a response is allowed by default, so short prompts can still have
+/// responses longer than themselves.
+const MIN_MAX_TOKENS: usize = 1024;
+
+/// What the model is told after the partial response it's to continue.
+const CONTINUATION_PROMPT: &str =
+    "Continue exactly where your response stopped, without repeating any of it.";
+
+/// Clones share the limit on parallel requests.
+#[derive(Clone)]
 pub struct OpenAIClient {
     model: OpenAIModel,
-    parallel_requests_semaphore: Semaphore,
+    parallel_requests_semaphore: Arc<Semaphore>,
     client: reqwest_middleware::ClientWithMiddleware,
 }
 
@@ -40,10 +52,25 @@ impl OpenAIClient {
 
         Ok(OpenAIClient {
             model,
-            parallel_requests_semaphore: Semaphore::new(max_parallel_requests),
+            parallel_requests_semaphore: Arc::new(Semaphore::new(max_parallel_requests)),
             client: client_with_middleware,
         })
     }
+
+    /// A client of `model` that shares the limit on parallel requests of this one.
+    pub fn with_model(&self, model: OpenAIModel) -> Self {
+        OpenAIClient {
+            model,
+            ..self.clone()
+        }
+    }
+}
+
+/// A JSON schema the response must follow, see [OpenAIClient::respond_with_schema].
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenAIResponseSchema {
+    pub name: String,
+    pub schema: serde_json::Value,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -51,41 +78,141 @@ struct OpenAIMessageResponse {
     content: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAIFinishReason {
+    Stop,
+    /// The response was cut off at the maximum number of tokens.
+    Length,
+    ContentFilter,
+    ToolCalls,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct OpenAIUsage {
+    #[serde(default)]
+    pub prompt_tokens: usize,
+    #[serde(default)]
+    pub completion_tokens: usize,
+}
+
+/// The text of a completion along with why it finished and the tokens it used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenAICompletion {
+    pub text: String,
+    pub finish_reason: Option<OpenAIFinishReason>,
+    pub usage: OpenAIUsage,
+}
+
+impl OpenAICompletion {
+    pub fn is_truncated(&self) -> bool {
+        self.finish_reason == Some(OpenAIFinishReason::Length)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct OpenAIChoiceResponse {
     message: OpenAIMessageResponse,
+    finish_reason: Option<OpenAIFinishReason>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct OpenAIChatResponse {
     choices: Vec<OpenAIChoiceResponse>,
+    #[serde(default)]
+    usage: OpenAIUsage,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct OpenAIDeltaResponse {
+    content: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct OpenAIChunkChoiceResponse {
+    delta: OpenAIDeltaResponse,
+    finish_reason: Option<OpenAIFinishReason>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct OpenAIChatChunkResponse {
+    choices: Vec<OpenAIChunkChoiceResponse>,
+    /// Only in the last chunk.
+    usage: Option<OpenAIUsage>,
+}
+
+/// The data of the last event of a streamed chat completion.
+const STREAM_DONE: &str = "[DONE]";
+
 const CHAT_COMPLETION_API_ENDPOINT: &str = "https://api.openai.com/v1/chat/completions";
 
 impl OpenAIClient {
-    pub async fn respond_to(
+    /// Enough tokens for a response of about `expected_characters`, twice as many, which covers
+    /// translations into scripts that take several tokens a character, within what the model
+    /// can give.
+    pub fn max_tokens_for(&self, expected_characters: usize) -> usize {
+        (expected_characters * 2).clamp(MIN_MAX_TOKENS, self.model.max_output_tokens())
+    }
+
+    fn chat_request(
         &self,
         system_prompt: String,
         prompt: String,
+        partial: Option<&str>,
+        schema: Option<OpenAIResponseSchema>,
         max_tokens: Option<usize>,
-    ) -> Result<String, OpenAIError> {
-        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
-
-        let request_json = json!({
+        stream: bool,
+    ) -> serde_json::Value {
+        let max_tokens = max_tokens.unwrap_or_else(|| self.max_tokens_for(prompt.chars().count()));
+        let mut messages = vec![
+            json!({
+                "role": "system",
+                "content": system_prompt
+            }),
+            json!({
+                "role": "user",
+                "content": prompt
+            }),
+        ];
+        if let Some(partial) = partial {
+            messages.push(json!({
+                "role": "assistant",
+                "content": partial
+            }));
+            messages.push(json!({
+                "role": "user",
+                "content": CONTINUATION_PROMPT
+            }));
+        }
+        let mut request = json!({
             "model": self.model,
-            "messages": [
-                {
-                    "role": "system",
-                    "content": system_prompt
-                },
-                {
-                    "role": "user",
-                    "content": prompt
-                }
-            ],
-            "max_tokens": max_tokens.unwrap_or(4096),
+            "messages": messages,
+            "max_tokens": max_tokens,
+            "stream": stream,
         });
+        if stream {
+            request["stream_options"] = json!({ "include_usage": true });
+        }
+        if let Some(schema) = schema {
+            request["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": schema.name,
+                    "strict": true,
+                    "schema": schema.schema,
+                },
+            });
+        }
+        request
+    }
+
+    async fn send_chat(
+        &self,
+        request_json: serde_json::Value,
+    ) -> Result<OpenAICompletion, OpenAIError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
 
         let response = self
             .client
@@ -95,23 +222,119 @@ impl OpenAIClient {
             .await?;
         let status = response.status();
         match status {
-            StatusCode::OK => Ok(response
-                .json::<OpenAIChatResponse>()
-                .await?
-                .choices
-                .first()
-                .ok_or(OpenAIError::UnexpectedApiResponse(
-                    "Expected response from openai but there wasn't one.".to_owned(),
-                ))?
-                .clone()
-                .message
-                .content),
+            StatusCode::OK => {
+                let response = response.json::<OpenAIChatResponse>().await?;
+                let choice = response
+                    .choices
+                    .first()
+                    .ok_or(OpenAIError::UnexpectedApiResponse(
+                        "Expected response from openai but there wasn't one.".to_owned(),
+                    ))?;
+                Ok(OpenAICompletion {
+                    text: choice.message.content.clone(),
+                    finish_reason: choice.finish_reason.clone(),
+                    usage: response.usage,
+                })
+            }
             _ => Err(OpenAIError::UnexpectedApiResponse(
                 format!("Expected 200 from {CHAT_COMPLETION_API_ENDPOINT} but got {status}")
                     .to_owned(),
             )),
         }
     }
+
+    /// Respond to `prompt`, with at most `max_tokens` or, by default, enough for a translation
+    /// of the prompt.
+    pub async fn respond_to(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        max_tokens: Option<usize>,
+    ) -> Result<OpenAICompletion, OpenAIError> {
+        let request_json = self.chat_request(system_prompt, prompt, None, None, max_tokens, false);
+        self.send_chat(request_json).await
+    }
+
+    /// Like [Self::respond_to], but the response is JSON that follows `schema`.
+    pub async fn respond_with_schema(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        schema: OpenAIResponseSchema,
+        max_tokens: Option<usize>,
+    ) -> Result<OpenAICompletion, OpenAIError> {
+        let request_json =
+            self.chat_request(system_prompt, prompt, None, Some(schema), max_tokens, false);
+        self.send_chat(request_json).await
+    }
+
+    /// Continue a `partial` response to `prompt` that was truncated, responding with the rest
+    /// of it.
+    pub async fn continue_response(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        partial: &str,
+        max_tokens: Option<usize>,
+    ) -> Result<OpenAICompletion, OpenAIError> {
+        let request_json = self.chat_request(
+            system_prompt,
+            prompt,
+            Some(partial),
+            None,
+            max_tokens,
+            false,
+        );
+        self.send_chat(request_json).await
+    }
+
+    /// Like [Self::respond_to], but streams the response, calling `on_delta` with each part of
+    /// the text as it's generated.
+    pub async fn respond_to_streaming(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        max_tokens: Option<usize>,
+        mut on_delta: impl FnMut(&str),
+    ) -> Result<OpenAICompletion, OpenAIError> {
+        let _request_permit = self.parallel_requests_semaphore.acquire().await?;
+
+        let request_json = self.chat_request(system_prompt, prompt, None, None, max_tokens, true);
+        let mut response = self
+            .client
+            .post(CHAT_COMPLETION_API_ENDPOINT)
+            .json(&request_json)
+            .send()
+            .await?;
+        let status = response.status();
+        if status != StatusCode::OK {
+            return Err(OpenAIError::UnexpectedApiResponse(format!(
+                "Expected 200 from {CHAT_COMPLETION_API_ENDPOINT} but got {status}"
+            )));
+        }
+
+        let mut completion = OpenAICompletion::default();
+        let mut events = EventStream::default();
+        while let Some(chunk) = response.chunk().await? {
+            for data in events.push(&chunk) {
+                if data == STREAM_DONE {
+                    return Ok(completion);
+                }
+                let chunk = serde_json::from_str::<OpenAIChatChunkResponse>(&data)?;
+                if let Some(usage) = chunk.usage {
+                    completion.usage = usage;
+                }
+                for choice in chunk.choices {
+                    if let Some(delta) = choice.delta.content {
+                        on_delta(&delta);
+                        completion.text.push_str(&delta);
+                    }
+                    completion.finish_reason = choice.finish_reason.or(completion.finish_reason);
+                }
+            }
+        }
+        Ok(completion)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
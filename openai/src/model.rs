@@ -1,6 +1,6 @@
 use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
 
-#[derive(Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
 pub enum OpenAIModel {
     #[serde(rename = "gpt-4o-mini")]
     GPT4OMini,
@@ -26,6 +26,19 @@ pub enum OpenAIModel {
     GPT3Point5Turbo1106,
 }
 
+impl OpenAIModel {
+    /// The most tokens the model can respond with.
+    pub fn max_output_tokens(&self) -> usize {
+        match self {
+            OpenAIModel::GPT4OMini
+            | OpenAIModel::GPT4OMini20240718
+            | OpenAIModel::GPT4O
+            | OpenAIModel::GPT4O20240806 => 16384,
+            _ => 4096,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::model::OpenAIModel;
@@ -0,0 +1,46 @@
+/// Splits a server-sent event stream, received in chunks, into the data of its events.
+#[derive(Default)]
+pub struct EventStream {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl EventStream {
+    /// Add a chunk of the response, returning the data of the events it completes.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_owned());
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EventStream;
+
+    #[test]
+    fn it_should_split_events_across_chunks() {
+        let mut stream = EventStream::default();
+        assert!(stream
+            .push(b"event: content_block_delta\r\ndata: {\"a\":")
+            .is_empty());
+        assert_eq!(
+            stream.push(b" 1}\r\n\r\nevent: ping\ndata: {}\n\n"),
+            vec!["{\"a\": 1}", "{}"]
+        );
+    }
+}
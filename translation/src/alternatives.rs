@@ -0,0 +1,36 @@
+use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+use serde::{Deserialize, Serialize};
+
+/// The most candidate translations that can be asked for an input.
+pub const MAX_ALTERNATIVES: usize = 5;
+
+/// One of several candidate translations of an input.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Alternative {
+    #[serde(rename = "output")]
+    pub text: String,
+    /// Why the candidate might be chosen, such as "more formal" or "literal".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AlternativeTranslation: Translation {
+    /// Whether [Self::translate_alternatives] can give more than one candidate.
+    fn supports_alternatives(&self) -> bool {
+        false
+    }
+
+    /// Translate `inputs`, each output with up to `count` distinct candidate translations in
+    /// its alternatives and the best of them as its text.
+    ///
+    /// By default the inputs are translated as usual, without alternatives, so check
+    /// [Self::supports_alternatives] first.
+    async fn translate_alternatives(
+        &self,
+        inputs: Vec<TranslationInput>,
+        _count: usize,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        self.translate(inputs).await
+    }
+}
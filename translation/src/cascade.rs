@@ -0,0 +1,394 @@
+use crate::alternatives::AlternativeTranslation;
+use crate::ensemble::EnsembleJudge;
+use crate::multi::MultiTargetTranslation;
+use crate::placeholder::placeholders;
+use crate::quality::{QualityEstimator, QualityJudge};
+use crate::stream::StreamingTranslation;
+use crate::validation::{
+    complete_outputs, expect_outputs, is_in_language, partial_outputs, InvalidTranslation,
+};
+use crate::{HealthCheck, Translation, TranslationError, TranslationInput, TranslationOutput};
+use async_trait::async_trait;
+use futures::future::join_all;
+use itertools::{Either, Itertools};
+use log::warn;
+use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
+
+/// How many characters a text needs before its length is checked, as short texts vary too much.
+const MIN_CHECKED_CHARACTERS: usize = 20;
+
+/// An automated check that a translation from a cheaper tier is good enough to keep.
+#[derive(Clone, Copy, Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
+pub enum CascadeCheck {
+    /// The translation has the same placeholders as the text, such as `{name}` or `%s`.
+    #[serde(rename = "placeholders")]
+    Placeholders,
+    /// The translation has the same HTML tags as the text.
+    #[serde(rename = "tags")]
+    Tags,
+    /// The translation is detected to be in the target language.
+    #[serde(rename = "language")]
+    Language,
+    /// The translation is no more than so many times longer or shorter than the text.
+    #[serde(rename = "length")]
+    Length,
+    /// An LLM judge doesn't score the translation below its threshold.
+    #[serde(rename = "quality")]
+    Quality,
+}
+
+fn sorted_tokens(text: &str, is_tag: bool) -> Vec<&str> {
+    placeholders(text)
+        .into_iter()
+        // entities such as &amp; may be translated
+        .filter(|token| !token.starts_with('&') && token.starts_with('<') == is_tag)
+        .sorted()
+        .collect_vec()
+}
+
+fn has_expected_length(
+    input: &TranslationInput,
+    output: &TranslationOutput,
+    max_length_ratio: f32,
+) -> bool {
+    let text_length = input.text.chars().count();
+    if text_length < MIN_CHECKED_CHARACTERS {
+        return true;
+    }
+    let ratio = output.text.chars().count() as f32 / text_length as f32;
+    ratio <= max_length_ratio && ratio >= 1.0 / max_length_ratio
+}
+
+pub struct CascadeTier<T> {
+    name: String,
+    translation: T,
+}
+
+/// Translates with the cheapest of several tiers first, then re-translates only the outputs
+/// that fail its checks with the next tier, and so on. The outputs of the last tier are kept
+/// whatever their checks, and each output records the tier that produced it.
+pub struct Cascade<T> {
+    tiers: Vec<CascadeTier<T>>,
+    checks: Vec<CascadeCheck>,
+    max_length_ratio: f32,
+    judge: Option<Box<QualityEstimator<T>>>,
+}
+
+impl<T> Cascade<T> {
+    /// A cascade through `tiers`, from the cheapest, each with its name.
+    pub fn new(tiers: Vec<(String, T)>, checks: Vec<CascadeCheck>, max_length_ratio: f32) -> Self {
+        Cascade {
+            tiers: tiers
+                .into_iter()
+                .map(|(name, translation)| CascadeTier { name, translation })
+                .collect_vec(),
+            checks,
+            max_length_ratio,
+            judge: None,
+        }
+    }
+
+    /// Use `judge` for the [CascadeCheck::Quality] check.
+    pub fn with_judge(mut self, judge: QualityEstimator<T>) -> Self {
+        self.judge = Some(Box::new(judge));
+        self
+    }
+
+    pub fn tier_names(&self) -> Vec<&str> {
+        self.tiers
+            .iter()
+            .map(|tier| tier.name.as_str())
+            .collect_vec()
+    }
+
+    fn passes(
+        &self,
+        check: CascadeCheck,
+        input: &TranslationInput,
+        output: &TranslationOutput,
+    ) -> bool {
+        match check {
+            CascadeCheck::Placeholders => {
+                sorted_tokens(&input.text, false) == sorted_tokens(&output.text, false)
+            }
+            CascadeCheck::Tags => {
+                sorted_tokens(&input.text, true) == sorted_tokens(&output.text, true)
+            }
+            CascadeCheck::Language => is_in_language(&output.text, &input.target_language),
+            CascadeCheck::Length => has_expected_length(input, output, self.max_length_ratio),
+            // scored for all the outputs at once
+            CascadeCheck::Quality => true,
+        }
+    }
+}
+
+impl<T: QualityJudge> Cascade<T> {
+    /// Whether each of `outputs` passes all the checks as a translation of its input.
+    async fn check(&self, inputs: &[TranslationInput], outputs: &[TranslationOutput]) -> Vec<bool> {
+        let mut passed = inputs
+            .iter()
+            .zip(outputs)
+            .map(|(input, output)| {
+                self.checks
+                    .iter()
+                    .all(|check| self.passes(*check, input, output))
+            })
+            .collect_vec();
+
+        let Some(judge) = self
+            .judge
+            .as_ref()
+            .filter(|_| self.checks.contains(&CascadeCheck::Quality))
+        else {
+            return passed;
+        };
+        match judge.estimate(inputs, outputs.to_vec()).await {
+            Ok(estimates) => {
+                for (passed, estimate) in passed.iter_mut().zip(estimates) {
+                    *passed &= estimate
+                        .quality
+                        .is_none_or(|quality| !quality.low_confidence);
+                }
+            }
+            // without a score nothing is known to be good enough
+            Err(error) => {
+                warn!("Escalating translations the cascade judge failed to score: {error}");
+                passed.fill(false)
+            }
+        }
+        passed
+    }
+}
+
+impl<T: QualityJudge> Translation for Cascade<T> {
+    async fn translate(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let mut outputs = vec![None; inputs.len()];
+        let mut errors = Vec::new();
+        let mut pending = (0..inputs.len()).collect_vec();
+        for (tier_index, tier) in self.tiers.iter().enumerate() {
+            let is_last = tier_index + 1 == self.tiers.len();
+            let tier_inputs = pending
+                .iter()
+                .map(|index| inputs[*index].clone())
+                .collect_vec();
+            let tier_outputs = partial_outputs(tier.translation.translate(tier_inputs).await)
+                .and_then(|(outputs, errors)| {
+                    Ok((expect_outputs(outputs, pending.len())?, errors))
+                });
+            let (tier_outputs, tier_errors) = match tier_outputs {
+                Ok(tier_outputs) => tier_outputs,
+                // the next tier gets all of them
+                Err(error) if !is_last => {
+                    warn!(
+                        "Escalating all translations of cascade tier {}: {error}",
+                        tier.name
+                    );
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            if is_last {
+                errors = tier_errors
+                    .into_iter()
+                    .map(|error| InvalidTranslation {
+                        index: pending[error.index],
+                        ..error
+                    })
+                    .collect_vec();
+            }
+
+            // invalid translations are escalated like those that fail the checks
+            let (translated, mut escalated): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .zip(tier_outputs)
+                .partition_map(|(index, output)| match output {
+                    Some(output) => Either::Left((index, output)),
+                    None => Either::Right(index),
+                });
+            let passed = if is_last {
+                vec![true; translated.len()]
+            } else {
+                let (checked_inputs, checked_outputs): (Vec<_>, Vec<_>) = translated
+                    .iter()
+                    .map(|(index, output)| (inputs[*index].clone(), output.clone()))
+                    .unzip();
+                self.check(&checked_inputs, &checked_outputs).await
+            };
+            for ((index, output), passed) in translated.into_iter().zip(passed) {
+                if passed {
+                    outputs[index] = Some(TranslationOutput {
+                        tier: Some(tier.name.clone()),
+                        ..output
+                    });
+                } else {
+                    escalated.push(index);
+                }
+            }
+            escalated.sort_unstable();
+            pending = escalated;
+            if pending.is_empty() {
+                break;
+            }
+        }
+        complete_outputs(outputs, errors)
+    }
+}
+
+impl<T: QualityJudge> StreamingTranslation for Cascade<T> {}
+
+impl<T: QualityJudge> MultiTargetTranslation for Cascade<T> {}
+
+impl<T: QualityJudge> AlternativeTranslation for Cascade<T> {}
+
+// there's no judge of its own, only those of its tiers
+impl<T: QualityJudge> QualityJudge for Cascade<T> {}
+
+impl<T: QualityJudge> EnsembleJudge for Cascade<T> {}
+
+#[async_trait]
+impl<T: HealthCheck + Send + Sync> HealthCheck for Cascade<T> {
+    async fn is_healthy(&self) -> Option<bool> {
+        let tiers = join_all(self.tiers.iter().map(|tier| tier.translation.is_healthy())).await;
+        tiers
+            .into_iter()
+            .flatten()
+            .reduce(|healthy, tier_healthy| healthy && tier_healthy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cascade::{Cascade, CascadeCheck};
+    use crate::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::quality::QualityJudge;
+    use crate::validation::detect_language;
+    use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+    use futures::executor::block_on;
+    use itertools::Itertools;
+    use std::collections::HashMap;
+
+    fn provider(translations: &[(&str, &str)]) -> MockTranslationProvider {
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            translations
+                .iter()
+                .map(|(text, translation)| (text.to_string(), translation.to_string()))
+                .collect(),
+        )]);
+        MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        )
+    }
+
+    #[test]
+    fn it_should_escalate_translations_that_fail_checks() {
+        let sentence = "The weather is lovely today, so we are going to the beach";
+        let cheap = provider(&[
+            ("Hello {name}", "Bonjour"),
+            ("Bye <b>now</b>", "Au revoir <b>maintenant</b>"),
+        ]);
+        let expensive = provider(&[
+            ("Hello {name}", "Bonjour {name}"),
+            (
+                sentence,
+                "Il fait très beau aujourd'hui, alors nous allons à la plage",
+            ),
+        ]);
+        let cascade = Cascade::new(
+            vec![
+                ("cheap".to_owned(), cheap),
+                ("expensive".to_owned(), expensive),
+            ],
+            vec![
+                CascadeCheck::Placeholders,
+                CascadeCheck::Tags,
+                CascadeCheck::Language,
+                CascadeCheck::Length,
+            ],
+            3.0,
+        );
+        let inputs = ["Hello {name}", "Bye <b>now</b>", sentence]
+            .map(|text| TranslationInput::test(text, "fr"))
+            .to_vec();
+
+        let outputs = block_on(cascade.translate(inputs)).unwrap();
+
+        assert_eq!(
+            outputs
+                .iter()
+                .map(|output| (output.text(), output.tier().unwrap()))
+                .collect_vec(),
+            vec![
+                ("Bonjour {name}", "expensive"),
+                ("Au revoir <b>maintenant</b>", "cheap"),
+                (
+                    "Il fait très beau aujourd'hui, alors nous allons à la plage",
+                    "expensive"
+                ),
+            ]
+        );
+    }
+
+    /// A tier that loses its last output.
+    struct LossyTier(MockTranslationProvider);
+
+    impl Translation for LossyTier {
+        async fn translate(
+            &self,
+            inputs: Vec<TranslationInput>,
+        ) -> Result<Vec<TranslationOutput>, TranslationError> {
+            let mut outputs = self.0.translate(inputs).await?;
+            outputs.pop();
+            Ok(outputs)
+        }
+    }
+
+    impl QualityJudge for LossyTier {}
+
+    #[test]
+    fn it_should_not_keep_the_outputs_of_a_tier_that_loses_some() {
+        let inputs = ["Hello", "Bye"]
+            .map(|text| TranslationInput::test(text, "fr"))
+            .to_vec();
+        let translations = [("Hello", "Bonjour"), ("Bye", "Au revoir")];
+        let cascade = Cascade::new(
+            vec![("lossy".to_owned(), LossyTier(provider(&translations)))],
+            vec![CascadeCheck::Placeholders],
+            3.0,
+        );
+
+        let error = block_on(cascade.translate(inputs)).unwrap_err();
+
+        assert!(matches!(
+            error,
+            TranslationError::OutputCountMismatch {
+                expected: 2,
+                received: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn it_should_check_chinese_translations_are_in_chinese() {
+        let text = "The weather is lovely today, so we are going to the beach";
+        let translation =
+            "今天天气非常好，阳光明媚，所以我们决定和朋友们一起去海边游泳、散步和吃晚饭。";
+        let input = TranslationInput::test(text, "zh");
+        let cascade = Cascade::new(
+            vec![("cheap".to_owned(), provider(&[]))],
+            vec![CascadeCheck::Language],
+            3.0,
+        );
+
+        let output = TranslationOutput::new(translation.to_owned(), None);
+
+        // detected as Mandarin, which has another code than Chinese
+        assert_eq!(detect_language(translation), Some("cmn"));
+        assert!(cascade.passes(CascadeCheck::Language, &input, &output));
+    }
+}
@@ -1,13 +1,23 @@
+use crate::alternatives::AlternativeTranslation;
+use crate::ensemble::{EnsembleCandidate, EnsembleJudge, EnsembleJudgement};
+use crate::llm::{
+    judge_candidates_with_llm, judge_quality_with_llm, translate_alternatives_with_llm,
+    translate_streaming_with_llm, translate_targets_with_llm, translate_with_llm, LLMClient,
+    LLMPrompt, LLMResponse,
+};
+use crate::multi::MultiTargetTranslation;
+use crate::quality::{QualityEstimate, QualityJudge};
+use crate::stream::{StreamingTranslation, TranslationEvents};
 use crate::{
-    HealthCheck, Language, Translation, TranslationError, TranslationInput, TranslationOutput,
+    HealthCheck, Translation, TranslationError, TranslationInput, TranslationOutput,
     TranslationProvider,
 };
 use async_trait::async_trait;
-use claude::client::ClaudeClient;
+use claude::client::{ClaudeClient, ClaudeMessage, ClaudeTool};
 use claude::error::ClaudeError;
-use futures::future::join_all;
-use itertools::Itertools;
+use claude::model::ClaudeModel;
 
+#[derive(Clone)]
 pub struct ClaudeTranslationProvider {
     claude_client: ClaudeClient,
 }
@@ -16,6 +26,84 @@ impl ClaudeTranslationProvider {
     pub fn new(claude_client: ClaudeClient) -> Self {
         ClaudeTranslationProvider { claude_client }
     }
+
+    /// A provider of `model` that shares the limit on parallel requests of this one.
+    pub fn with_model(&self, model: ClaudeModel) -> Self {
+        ClaudeTranslationProvider::new(self.claude_client.with_model(model))
+    }
+}
+
+impl From<ClaudeMessage> for LLMResponse {
+    fn from(response: ClaudeMessage) -> Self {
+        LLMResponse {
+            truncated: response.is_truncated(),
+            text: response.text,
+        }
+    }
+}
+
+impl LLMClient for ClaudeClient {
+    type Error = ClaudeError;
+
+    async fn respond_to(&self, prompt: LLMPrompt) -> Result<LLMResponse, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        match prompt.response_schema {
+            Some(schema) => {
+                let tool = ClaudeTool {
+                    name: schema.name.to_owned(),
+                    description: schema.description.to_owned(),
+                    input_schema: schema.schema,
+                };
+                ClaudeClient::respond_with_tool(
+                    self,
+                    prompt.system_prompt,
+                    prompt.prompt,
+                    tool,
+                    max_tokens,
+                )
+                .await
+            }
+            None => {
+                ClaudeClient::respond_to(self, prompt.system_prompt, prompt.prompt, max_tokens)
+                    .await
+            }
+        }
+        .map(LLMResponse::from)
+    }
+
+    async fn respond_to_streaming(
+        &self,
+        prompt: LLMPrompt,
+        on_delta: impl FnMut(&str),
+    ) -> Result<LLMResponse, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        ClaudeClient::respond_to_streaming(
+            self,
+            prompt.system_prompt,
+            prompt.prompt,
+            max_tokens,
+            on_delta,
+        )
+        .await
+        .map(LLMResponse::from)
+    }
+
+    async fn continue_response(
+        &self,
+        prompt: LLMPrompt,
+        partial: &str,
+    ) -> Result<Option<LLMResponse>, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        ClaudeClient::continue_response(
+            self,
+            prompt.system_prompt,
+            prompt.prompt,
+            partial,
+            max_tokens,
+        )
+        .await
+        .map(|response| Some(LLMResponse::from(response)))
+    }
 }
 
 #[async_trait]
@@ -31,31 +119,68 @@ impl Translation for ClaudeTranslationProvider {
         &self,
         inputs: Vec<TranslationInput>,
     ) -> Result<Vec<TranslationOutput>, TranslationError> {
-        Ok(join_all(
-            inputs
-                .into_iter()
-                .map(|input| {
-                    let from_source = format!(
-                        " from {}",
-                        input.source_language.map(|source| Language::to_string(&source)).unwrap_or("".to_owned())
-                    );
-                    let prompt = format!(
-                        "Please translate the following text{from_source} to {}, only respond with the translation:\n{}",
-                        input.target_language, input.text
-                    );
-                    self.claude_client.respond_to(prompt, None)
-                })
-                .collect_vec(),
-        )
-        .await
-        .into_iter()
-        .collect::<Result<Vec<String>, ClaudeError>>()?
-        .into_iter()
-        .map(|response| TranslationOutput {
-            text: response,
-            source_language: None,
-        })
-        .collect())
+        translate_with_llm(&self.claude_client, inputs).await
+    }
+}
+
+impl StreamingTranslation for ClaudeTranslationProvider {
+    async fn translate_streaming(
+        &self,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Vec<Option<TranslationOutput>> {
+        translate_streaming_with_llm(&self.claude_client, inputs, events).await
+    }
+}
+
+impl MultiTargetTranslation for ClaudeTranslationProvider {
+    async fn translate_targets(
+        &self,
+        groups: Vec<Vec<TranslationInput>>,
+    ) -> Result<Vec<Vec<TranslationOutput>>, TranslationError> {
+        translate_targets_with_llm(&self.claude_client, groups).await
+    }
+}
+
+impl AlternativeTranslation for ClaudeTranslationProvider {
+    fn supports_alternatives(&self) -> bool {
+        true
+    }
+
+    async fn translate_alternatives(
+        &self,
+        inputs: Vec<TranslationInput>,
+        count: usize,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        translate_alternatives_with_llm(&self.claude_client, inputs, count).await
+    }
+}
+
+impl QualityJudge for ClaudeTranslationProvider {
+    fn supports_quality_judging(&self) -> bool {
+        true
+    }
+
+    async fn judge_quality(
+        &self,
+        inputs: &[TranslationInput],
+        outputs: &[TranslationOutput],
+    ) -> Result<Vec<Option<QualityEstimate>>, TranslationError> {
+        judge_quality_with_llm(&self.claude_client, inputs, outputs).await
+    }
+}
+
+impl EnsembleJudge for ClaudeTranslationProvider {
+    fn supports_ensemble_judging(&self) -> bool {
+        true
+    }
+
+    async fn judge_candidates(
+        &self,
+        inputs: &[TranslationInput],
+        candidates: &[Vec<EnsembleCandidate>],
+    ) -> Result<Vec<Option<EnsembleJudgement>>, TranslationError> {
+        judge_candidates_with_llm(&self.claude_client, inputs, candidates).await
     }
 }
 
@@ -1,3 +1,8 @@
+use crate::alternatives::AlternativeTranslation;
+use crate::ensemble::EnsembleJudge;
+use crate::multi::MultiTargetTranslation;
+use crate::quality::QualityJudge;
+use crate::stream::StreamingTranslation;
 use crate::{
     HealthCheck, Language, Translation, TranslationError, TranslationInput, TranslationOutput,
     TranslationProvider,
@@ -11,6 +16,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
 
+#[derive(Clone)]
 pub struct DeepLTranslationProvider {
     deepl_client: DeepLClient,
 }
@@ -21,19 +27,22 @@ impl DeepLTranslationProvider {
     }
 }
 
+/// Inputs that can be translated in the same request.
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
-struct LanguageTranslationPair {
+struct TranslationGroup {
     source_lang: Option<String>,
     target_lang: String,
+    context: Option<String>,
 }
 
-impl From<TranslationInput> for LanguageTranslationPair {
+impl From<TranslationInput> for TranslationGroup {
     fn from(translation_input: TranslationInput) -> Self {
-        LanguageTranslationPair {
+        TranslationGroup {
             source_lang: translation_input
                 .source_language
                 .map(|source| Language::to_string(&source)),
             target_lang: translation_input.target_language.to_string(),
+            context: translation_input.context,
         }
     }
 }
@@ -57,30 +66,30 @@ impl Translation for DeepLTranslationProvider {
         &self,
         inputs: Vec<TranslationInput>,
     ) -> Result<Vec<TranslationOutput>, TranslationError> {
-        let language_pair_to_inputs = inputs
+        let group_to_inputs = inputs
             .into_iter()
             .zip(0_u32..)
             .map(|(translation_input, index)| {
                 (
-                    LanguageTranslationPair::from(translation_input.clone()),
+                    TranslationGroup::from(translation_input.clone()),
                     (translation_input.text, index),
                 )
             })
             .fold(
-                HashMap::<LanguageTranslationPair, Vec<(String, u32)>>::new(),
-                |mut pair_to_inputs, (language_pair, input)| {
-                    pair_to_inputs.entry(language_pair).or_default().push(input);
-                    pair_to_inputs
+                HashMap::<TranslationGroup, Vec<(String, u32)>>::new(),
+                |mut group_to_inputs, (group, input)| {
+                    group_to_inputs.entry(group).or_default().push(input);
+                    group_to_inputs
                 },
             );
 
-        let translations = language_pair_to_inputs
+        let translations = group_to_inputs
             .into_iter()
-            .map(|(language_pair, inputs)| {
+            .map(|(group, inputs)| {
                 let texts = inputs.iter().cloned().map(|(text, _)| text).collect_vec();
-                let indexes = inputs.iter().cloned().map(|(_, index)| index).collect_vec();
+                let indexes = inputs.iter().map(|(_, index)| *index).collect_vec();
                 self.deepl_client
-                    .translate(texts, language_pair.source_lang, language_pair.target_lang)
+                    .translate(texts, group.source_lang, group.target_lang, group.context)
                     .and_then(|translations| async move {
                         let source_language = translations.source_language.clone();
                         Ok(translations
@@ -107,9 +116,26 @@ impl Translation for DeepLTranslationProvider {
                     None => None,
                     Some(source) => Language::from_str(source.to_ascii_lowercase().as_str()).ok(),
                 },
+                match_score: None,
+                alternatives: Vec::new(),
+                quality: None,
+                tier: None,
+                ensemble: None,
             })
             .collect_vec())
     }
 }
 
+// translated in one request, so there's nothing to send until it's done
+impl StreamingTranslation for DeepLTranslationProvider {}
+
+// inputs are batched by target language, and DeepL has no multi-target requests
+impl MultiTargetTranslation for DeepLTranslationProvider {}
+
+impl AlternativeTranslation for DeepLTranslationProvider {}
+
+impl QualityJudge for DeepLTranslationProvider {}
+
+impl EnsembleJudge for DeepLTranslationProvider {}
+
 impl TranslationProvider for DeepLTranslationProvider {}
@@ -0,0 +1,131 @@
+use crate::memory::TranslationReference;
+use crate::validation::expect_outputs;
+use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+use itertools::Itertools;
+
+/// How many segments before and after each segment of a document are given along with it.
+pub const DOCUMENT_WINDOW_SEGMENTS: usize = 3;
+
+/// Translate `inputs` as the consecutive segments of one document, such as the paragraphs of
+/// an article. Each segment is translated along with the translations of the segments before
+/// it and the text of the segments after it, so terminology and pronouns stay consistent.
+///
+/// Segments are translated one at a time as each one needs the translations before it, which
+/// only helps providers that read those neighbours, LLMs rather than DeepL.
+pub async fn translate_document<T: Translation>(
+    translation: &T,
+    inputs: Vec<TranslationInput>,
+) -> Result<Vec<TranslationOutput>, TranslationError> {
+    let texts = inputs.iter().map(|input| input.text.clone()).collect_vec();
+    let mut outputs = Vec::<TranslationOutput>::with_capacity(inputs.len());
+    for (index, input) in inputs.into_iter().enumerate() {
+        let preceding = texts[index.saturating_sub(DOCUMENT_WINDOW_SEGMENTS)..index]
+            .iter()
+            .zip(&outputs[index.saturating_sub(DOCUMENT_WINDOW_SEGMENTS)..])
+            .map(|(source_text, output)| TranslationReference {
+                source_text: source_text.clone(),
+                target_text: output.text.clone(),
+            })
+            .collect_vec();
+        let following = texts
+            .iter()
+            .skip(index + 1)
+            .take(DOCUMENT_WINDOW_SEGMENTS)
+            .cloned()
+            .collect_vec();
+        let input = input.with_neighbours(preceding, following);
+        outputs.extend(expect_outputs(
+            translation.translate(vec![input]).await?,
+            1,
+        )?);
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::document::translate_document;
+    use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+    use futures::executor::block_on;
+    use itertools::Itertools;
+    use std::sync::Mutex;
+
+    /// Translates to upper case, remembering the neighbours each input was given.
+    #[derive(Default)]
+    struct NeighbourRecorder {
+        neighbours: Mutex<Vec<(Vec<String>, Vec<String>)>>,
+    }
+
+    impl Translation for NeighbourRecorder {
+        async fn translate(
+            &self,
+            inputs: Vec<TranslationInput>,
+        ) -> Result<Vec<TranslationOutput>, TranslationError> {
+            let mut neighbours = self.neighbours.lock().unwrap();
+            Ok(inputs
+                .into_iter()
+                .map(|input| {
+                    neighbours.push((
+                        input
+                            .preceding
+                            .iter()
+                            .map(|preceding| preceding.target_text.clone())
+                            .collect_vec(),
+                        input.following.clone(),
+                    ));
+                    TranslationOutput::new(input.text.to_uppercase(), None)
+                })
+                .collect_vec())
+        }
+    }
+
+    #[test]
+    fn it_should_translate_segments_with_their_neighbours() {
+        let inputs = ["a", "b", "c", "d", "e"]
+            .into_iter()
+            .map(|text| TranslationInput::test(text, "fr"))
+            .collect_vec();
+        let recorder = NeighbourRecorder::default();
+
+        let outputs = block_on(translate_document(&recorder, inputs)).unwrap();
+
+        assert_eq!(
+            outputs.iter().map(|output| output.text()).collect_vec(),
+            vec!["A", "B", "C", "D", "E"]
+        );
+        let neighbours = recorder.neighbours.into_inner().unwrap();
+        assert_eq!(
+            neighbours[0],
+            (vec![], vec!["b".into(), "c".into(), "d".into()])
+        );
+        assert_eq!(
+            neighbours[4],
+            (vec!["B".into(), "C".into(), "D".into()], vec![])
+        );
+    }
+
+    /// Translates nothing.
+    struct Silent;
+
+    impl Translation for Silent {
+        async fn translate(
+            &self,
+            _inputs: Vec<TranslationInput>,
+        ) -> Result<Vec<TranslationOutput>, TranslationError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn it_should_fail_when_a_segment_has_no_translation() {
+        let input = TranslationInput::test("a", "fr");
+
+        assert!(matches!(
+            block_on(translate_document(&Silent, vec![input])),
+            Err(TranslationError::OutputCountMismatch {
+                expected: 1,
+                received: 0
+            })
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::validation::expect_outputs;
+use crate::{HealthCheck, Translation, TranslationError, TranslationInput, TranslationOutput};
+use async_trait::async_trait;
+use futures::future::join_all;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// The translation of an input by one of the providers of an ensemble.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EnsembleCandidate {
+    pub provider: String,
+    #[serde(rename = "output")]
+    pub text: String,
+    /// From 0 to 100, when the candidate was judged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<u8>,
+}
+
+/// How the translation of an input was chosen from the candidates of an ensemble, kept to
+/// audit the choice.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EnsembleDecision {
+    pub candidates: Vec<EnsembleCandidate>,
+    /// The provider of the chosen candidate, none when they were merged or couldn't be judged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winner: Option<String>,
+    /// Whether the translation merges several candidates.
+    #[serde(default)]
+    pub merged: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub justification: Option<String>,
+}
+
+/// A judge's verdict on the candidate translations of an input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnsembleJudgement {
+    /// The score of each candidate, in their order.
+    pub scores: Vec<u8>,
+    /// The index of the chosen candidate, none when they were merged.
+    pub winner: Option<usize>,
+    pub text: String,
+    pub justification: Option<String>,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait EnsembleJudge: Translation {
+    /// Whether [Self::judge_candidates] gives judgements.
+    fn supports_ensemble_judging(&self) -> bool {
+        false
+    }
+
+    /// Score the candidate translations of each input and pick the best of them or merge them,
+    /// or `None` when they can't be judged.
+    async fn judge_candidates(
+        &self,
+        inputs: &[TranslationInput],
+        _candidates: &[Vec<EnsembleCandidate>],
+    ) -> Result<Vec<Option<EnsembleJudgement>>, TranslationError> {
+        Ok(vec![None; inputs.len()])
+    }
+}
+
+/// Translates each input with several providers in parallel, then has a judge choose the best
+/// of their translations or merge them. Inputs without a judgement get the first translation.
+pub struct Ensemble<T> {
+    members: Vec<(String, T)>,
+    judge: T,
+}
+
+impl<T: EnsembleJudge> Ensemble<T> {
+    /// An ensemble of `members`, each with its name, or none when `judge` can't judge.
+    pub fn new(members: Vec<(String, T)>, judge: T) -> Option<Self> {
+        (!members.is_empty() && judge.supports_ensemble_judging())
+            .then_some(Ensemble { members, judge })
+    }
+
+    pub fn member_names(&self) -> Vec<&str> {
+        self.members
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect_vec()
+    }
+}
+
+impl<T: EnsembleJudge> Translation for Ensemble<T> {
+    async fn translate(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let translations = join_all(
+            self.members
+                .iter()
+                .map(|(_, member)| member.translate(inputs.clone())),
+        )
+        .await;
+        // members that fail are left out, as long as one of them doesn't
+        let mut error = None;
+        let mut member_outputs = Vec::new();
+        for ((name, _), translation) in self.members.iter().zip(translations) {
+            // outputs that can't be matched to their inputs are no use
+            match translation.and_then(|outputs| expect_outputs(outputs, inputs.len())) {
+                Ok(outputs) => member_outputs.push((name, outputs)),
+                Err(member_error) => {
+                    error.get_or_insert(member_error);
+                }
+            }
+        }
+        if let (Some(error), true) = (error, member_outputs.is_empty()) {
+            return Err(error);
+        }
+
+        let candidates = (0..inputs.len())
+            .map(|index| {
+                member_outputs
+                    .iter()
+                    .map(|(name, outputs)| EnsembleCandidate {
+                        provider: name.to_string(),
+                        text: outputs[index].text.clone(),
+                        score: None,
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let judgements = expect_outputs(
+            self.judge.judge_candidates(&inputs, &candidates).await?,
+            inputs.len(),
+        )?;
+
+        Ok(candidates
+            .into_iter()
+            .zip(judgements)
+            .enumerate()
+            .map(|(index, (mut candidates, judgement))| {
+                let source_language = member_outputs[0].1[index].source_language.clone();
+                let (text, decision) = match judgement {
+                    Some(judgement) => {
+                        for (candidate, score) in candidates.iter_mut().zip(judgement.scores) {
+                            candidate.score = Some(score);
+                        }
+                        let winner = judgement
+                            .winner
+                            .and_then(|winner| candidates.get(winner))
+                            .map(|candidate| candidate.provider.clone());
+                        let decision = EnsembleDecision {
+                            merged: winner.is_none(),
+                            winner,
+                            justification: judgement.justification,
+                            candidates,
+                        };
+                        (judgement.text, decision)
+                    }
+                    None => {
+                        let decision = EnsembleDecision {
+                            candidates,
+                            winner: None,
+                            merged: false,
+                            justification: None,
+                        };
+                        (decision.candidates[0].text.clone(), decision)
+                    }
+                };
+                TranslationOutput {
+                    text,
+                    source_language,
+                    match_score: None,
+                    alternatives: Vec::new(),
+                    quality: None,
+                    tier: None,
+                    ensemble: Some(decision),
+                }
+            })
+            .collect_vec())
+    }
+}
+
+#[async_trait]
+impl<T: HealthCheck + Send + Sync> HealthCheck for Ensemble<T> {
+    async fn is_healthy(&self) -> Option<bool> {
+        let members = join_all(self.members.iter().map(|(_, member)| member.is_healthy())).await;
+        members
+            .into_iter()
+            .flatten()
+            .reduce(|healthy, member_healthy| healthy && member_healthy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ensemble::{Ensemble, EnsembleCandidate, EnsembleJudge, EnsembleJudgement};
+    use crate::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+    use futures::executor::block_on;
+    use itertools::Itertools;
+    use std::collections::HashMap;
+
+    /// Translates with a dictionary, judging the longest candidate the best. A lossy member
+    /// loses its last output.
+    struct Member(MockTranslationProvider, bool);
+
+    impl Member {
+        fn new(translations: &[(&str, &str)]) -> Self {
+            let dictionary = HashMap::from([(
+                "fr".to_owned(),
+                translations
+                    .iter()
+                    .map(|(text, translation)| (text.to_string(), translation.to_string()))
+                    .collect(),
+            )]);
+            Member(
+                MockTranslationProvider::new(
+                    MockMode::Dictionary(dictionary),
+                    MockFaultInjection::default(),
+                    true,
+                ),
+                false,
+            )
+        }
+
+        fn lossy(translations: &[(&str, &str)]) -> Self {
+            Member(Member::new(translations).0, true)
+        }
+    }
+
+    impl Translation for Member {
+        async fn translate(
+            &self,
+            inputs: Vec<TranslationInput>,
+        ) -> Result<Vec<TranslationOutput>, TranslationError> {
+            let mut outputs = self.0.translate(inputs).await?;
+            if self.1 {
+                outputs.pop();
+            }
+            Ok(outputs)
+        }
+    }
+
+    impl EnsembleJudge for Member {
+        fn supports_ensemble_judging(&self) -> bool {
+            true
+        }
+
+        async fn judge_candidates(
+            &self,
+            _inputs: &[TranslationInput],
+            candidates: &[Vec<EnsembleCandidate>],
+        ) -> Result<Vec<Option<EnsembleJudgement>>, TranslationError> {
+            Ok(candidates
+                .iter()
+                .map(|candidates| {
+                    let winner = candidates
+                        .iter()
+                        .position_max_by_key(|candidate| candidate.text.len())?;
+                    Some(EnsembleJudgement {
+                        scores: candidates
+                            .iter()
+                            .map(|candidate| candidate.text.len() as u8)
+                            .collect_vec(),
+                        winner: Some(winner),
+                        text: candidates[winner].text.clone(),
+                        justification: Some("Longest".to_owned()),
+                    })
+                })
+                .collect_vec())
+        }
+    }
+
+    #[test]
+    fn it_should_choose_the_judged_best_candidate() {
+        let ensemble = Ensemble::new(
+            vec![
+                ("first".to_owned(), Member::new(&[("Save", "Sauver")])),
+                ("second".to_owned(), Member::new(&[("Save", "Enregistrer")])),
+            ],
+            Member::new(&[]),
+        )
+        .unwrap();
+        let input = TranslationInput::test("Save", "fr");
+
+        let outputs = block_on(ensemble.translate(vec![input])).unwrap();
+
+        assert_eq!(outputs[0].text(), "Enregistrer");
+        let decision = outputs[0].ensemble().unwrap();
+        assert_eq!(decision.winner.as_deref(), Some("second"));
+        assert!(!decision.merged);
+        assert_eq!(
+            decision
+                .candidates
+                .iter()
+                .map(|candidate| (candidate.provider.as_str(), candidate.score))
+                .collect_vec(),
+            vec![("first", Some(6)), ("second", Some(11))]
+        );
+    }
+
+    #[test]
+    fn it_should_leave_out_members_that_lose_outputs() {
+        let ensemble = Ensemble::new(
+            vec![
+                ("first".to_owned(), Member::new(&[("Save", "Sauver")])),
+                (
+                    "second".to_owned(),
+                    Member::lossy(&[("Save", "Enregistrer")]),
+                ),
+            ],
+            Member::new(&[]),
+        )
+        .unwrap();
+        let input = TranslationInput::test("Save", "fr");
+
+        let outputs = block_on(ensemble.translate(vec![input.clone()])).unwrap();
+        assert_eq!(outputs[0].text(), "Sauver");
+
+        let lossy = Ensemble::new(
+            vec![(
+                "second".to_owned(),
+                Member::lossy(&[("Save", "Enregistrer")]),
+            )],
+            Member::new(&[]),
+        )
+        .unwrap();
+        assert!(matches!(
+            block_on(lossy.translate(vec![input])),
+            Err(TranslationError::OutputCountMismatch {
+                expected: 1,
+                received: 0
+            })
+        ));
+    }
+}
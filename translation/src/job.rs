@@ -0,0 +1,645 @@
+use crate::memory::now;
+use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+use itertools::Itertools;
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+
+const JOB_FILE_EXTENSION: &str = "jsonl";
+
+#[derive(Error, Debug)]
+pub enum JobError {
+    #[error("IoError {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("SerdeJsonError {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("TranslationError {0}")]
+    TranslationError(#[from] TranslationError),
+    #[error("Invalid chunk size config")]
+    InvalidChunkSizeConfig,
+    #[error("Job lock poisoned")]
+    LockPoisoned,
+    #[error("No job {0}")]
+    NotFound(String),
+    #[error("Job {0} already finished")]
+    AlreadyFinished(String),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// What a job has done so far, and its result once it's completed.
+#[derive(Clone, Debug, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub status: JobStatus,
+    /// Seconds since the unix epoch.
+    pub created: u64,
+    /// Inputs to translate, known once the job starts running.
+    pub total: Option<usize>,
+    pub translated: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+}
+
+/// One attempt at delivering a webhook, each retry is an attempt of its own.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeliveryAttempt {
+    pub url: String,
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Each change to a job is appended to its file, so a job can be picked up where it left off
+/// after a restart.
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JobEvent<Spec> {
+    Created {
+        spec: Spec,
+        timestamp: u64,
+    },
+    Started {
+        total: usize,
+    },
+    Translated {
+        outputs: Vec<TranslationOutput>,
+    },
+    Completed {
+        result: Value,
+        #[serde(default)]
+        timestamp: u64,
+    },
+    Failed {
+        error: String,
+        #[serde(default)]
+        timestamp: u64,
+    },
+    Cancelled {
+        #[serde(default)]
+        timestamp: u64,
+    },
+    Delivered {
+        attempt: DeliveryAttempt,
+    },
+}
+
+struct JobState<Spec> {
+    spec: Arc<Spec>,
+    created: u64,
+    /// Seconds since the unix epoch, once the job has finished.
+    finished: Option<u64>,
+    status: JobStatus,
+    total: Option<usize>,
+    outputs: Vec<TranslationOutput>,
+    error: Option<String>,
+    result: Option<Value>,
+    deliveries: Vec<DeliveryAttempt>,
+}
+
+impl<Spec> JobState<Spec> {
+    fn new(spec: Spec, created: u64) -> Self {
+        JobState {
+            spec: Arc::new(spec),
+            created,
+            finished: None,
+            status: JobStatus::Queued,
+            total: None,
+            outputs: Vec::new(),
+            error: None,
+            result: None,
+            deliveries: Vec::new(),
+        }
+    }
+
+    fn apply(&mut self, event: JobEvent<Spec>) {
+        match event {
+            JobEvent::Created { spec, timestamp } => *self = JobState::new(spec, timestamp),
+            JobEvent::Started { total } => {
+                self.status = JobStatus::Running;
+                self.total = Some(total);
+            }
+            JobEvent::Translated { outputs } => self.outputs.extend(outputs),
+            JobEvent::Completed { result, timestamp } => {
+                self.status = JobStatus::Completed;
+                self.finished = Some(timestamp);
+                self.result = Some(result);
+            }
+            JobEvent::Failed { error, timestamp } => {
+                self.status = JobStatus::Failed;
+                self.finished = Some(timestamp);
+                self.error = Some(error);
+            }
+            JobEvent::Cancelled { timestamp } => {
+                self.status = JobStatus::Cancelled;
+                self.finished = Some(timestamp);
+            }
+            JobEvent::Delivered { attempt } => self.deliveries.push(attempt),
+        }
+    }
+
+    fn is_expired(&self, retention: Option<Duration>, now: u64) -> bool {
+        match (self.finished, retention) {
+            (Some(finished), Some(retention)) => {
+                finished.saturating_add(retention.as_secs()) <= now
+            }
+            _ => false,
+        }
+    }
+
+    fn job(&self, id: &str) -> Job {
+        Job {
+            id: id.to_owned(),
+            status: self.status,
+            created: self.created,
+            total: self.total,
+            translated: self.outputs.len(),
+            error: self.error.clone(),
+            result: self.result.clone(),
+        }
+    }
+}
+
+fn new_job_id() -> String {
+    (0..16)
+        .map(|_| format!("{:02x}", rand::random::<u8>()))
+        .collect()
+}
+
+fn job_path(directory: &Path, id: &str) -> PathBuf {
+    directory.join(id).with_extension(JOB_FILE_EXTENSION)
+}
+
+/// Translation jobs that run in the background, described by a `Spec` of what to translate.
+/// Jobs are kept in memory, and in a file per job when given a directory. Finished jobs are
+/// evicted once they're older than the retention, if there is one.
+pub struct JobStore<Spec> {
+    directory: Option<PathBuf>,
+    chunk_size: usize,
+    retention: Option<Duration>,
+    jobs: Mutex<HashMap<String, JobState<Spec>>>,
+    /// Held while an event is written, so the events of a job are written in the order
+    /// they're applied, without holding the jobs while the file is written.
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl<Spec: Serialize + DeserializeOwned> JobStore<Spec> {
+    pub fn open(
+        directory: Option<&Path>,
+        chunk_size: usize,
+        retention: Option<Duration>,
+    ) -> Result<Self, JobError> {
+        if chunk_size == 0 {
+            return Err(JobError::InvalidChunkSizeConfig);
+        }
+
+        let mut jobs = HashMap::new();
+        if let Some(directory) = directory {
+            fs::create_dir_all(directory)?;
+            let now = now();
+            for entry in fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path.extension().and_then(|extension| extension.to_str())
+                    != Some(JOB_FILE_EXTENSION)
+                {
+                    continue;
+                }
+                let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+                match Self::read_job(&path)? {
+                    Some(state) if state.is_expired(retention, now) => fs::remove_file(&path)?,
+                    Some(state) => {
+                        jobs.insert(id.to_owned(), state);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        Ok(JobStore {
+            directory: directory.map(Path::to_path_buf),
+            chunk_size,
+            retention,
+            jobs: Mutex::new(jobs),
+            writer: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Replay the events of a job file. An event cut off by a crash while it was written is
+    /// truncated, a job with any other unreadable event is skipped.
+    fn read_job(path: &Path) -> Result<Option<JobState<Spec>>, JobError> {
+        let contents = fs::read_to_string(path)?;
+        let mut state = None::<JobState<Spec>>;
+        let mut position = 0;
+        for line in contents.split_inclusive('\n') {
+            let end = position + line.len();
+            if line.trim().is_empty() {
+                position = end;
+                continue;
+            }
+            let event = match serde_json::from_str::<JobEvent<Spec>>(line) {
+                Ok(event) => event,
+                Err(error) if end == contents.len() => {
+                    warn!(
+                        "Truncating unreadable last event of {}: {error}",
+                        path.display()
+                    );
+                    OpenOptions::new()
+                        .write(true)
+                        .open(path)?
+                        .set_len(position as u64)?;
+                    break;
+                }
+                Err(error) => {
+                    warn!(
+                        "Skipping job {} with an unreadable event: {error}",
+                        path.display()
+                    );
+                    return Ok(None);
+                }
+            };
+            match (event, state.as_mut()) {
+                (JobEvent::Created { spec, timestamp }, _) => {
+                    state = Some(JobState::new(spec, timestamp))
+                }
+                (event, Some(state)) => state.apply(event),
+                (_, None) => {}
+            }
+            position = end;
+        }
+        // a job that was running when the service stopped is queued to run again
+        if let Some(state) = state.as_mut().filter(|state| !state.status.is_finished()) {
+            state.status = JobStatus::Queued;
+        }
+        Ok(state)
+    }
+
+    async fn append<S: Serialize>(&self, id: &str, event: &JobEvent<S>) -> Result<(), JobError> {
+        if let Some(directory) = &self.directory {
+            let line = serde_json::to_string(event)? + "\n";
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(job_path(directory, id))
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            file.flush().await?;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, JobState<Spec>>>, JobError> {
+        self.jobs.lock().map_err(|_| JobError::LockPoisoned)
+    }
+
+    /// Record an event and apply it to the job. Finished jobs don't change, unless
+    /// `allow_finished` as for deliveries.
+    async fn update(
+        &self,
+        id: &str,
+        event: JobEvent<Spec>,
+        allow_finished: bool,
+    ) -> Result<Job, JobError> {
+        let _writer = self.writer.lock().await;
+        match self.lock()?.get(id) {
+            None => return Err(JobError::NotFound(id.to_owned())),
+            Some(state) if state.status.is_finished() && !allow_finished => {
+                return Err(JobError::AlreadyFinished(id.to_owned()))
+            }
+            Some(_) => {}
+        }
+        self.append(id, &event).await?;
+        let mut jobs = self.lock()?;
+        let state = jobs.get_mut(id).ok_or(JobError::NotFound(id.to_owned()))?;
+        state.apply(event);
+        Ok(state.job(id))
+    }
+
+    /// Remove the finished jobs that are older than the retention, and their files.
+    pub async fn evict_expired(&self) -> Result<usize, JobError> {
+        let _writer = self.writer.lock().await;
+        let now = now();
+        let expired = {
+            let mut jobs = self.lock()?;
+            let expired = jobs
+                .iter()
+                .filter(|(_, state)| state.is_expired(self.retention, now))
+                .map(|(id, _)| id.clone())
+                .collect_vec();
+            for id in &expired {
+                jobs.remove(id);
+            }
+            expired
+        };
+        if let Some(directory) = &self.directory {
+            for id in &expired {
+                tokio::fs::remove_file(job_path(directory, id)).await?;
+            }
+        }
+        Ok(expired.len())
+    }
+
+    /// Queue a job, evicting the expired ones.
+    pub async fn create(&self, spec: Spec) -> Result<Job, JobError> {
+        self.evict_expired().await?;
+        let id = new_job_id();
+        let timestamp = now();
+        let event = JobEvent::Created {
+            spec: &spec,
+            timestamp,
+        };
+        let _writer = self.writer.lock().await;
+        self.append(&id, &event).await?;
+        let state = JobState::new(spec, timestamp);
+        let job = state.job(&id);
+        self.lock()?.insert(id, state);
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Job, JobError> {
+        self.lock()?
+            .get(id)
+            .map(|state| state.job(id))
+            .ok_or(JobError::NotFound(id.to_owned()))
+    }
+
+    pub fn spec(&self, id: &str) -> Result<Arc<Spec>, JobError> {
+        self.lock()?
+            .get(id)
+            .map(|state| state.spec.clone())
+            .ok_or(JobError::NotFound(id.to_owned()))
+    }
+
+    /// Jobs waiting to run, oldest first.
+    pub fn queued(&self) -> Result<Vec<String>, JobError> {
+        Ok(self
+            .lock()?
+            .iter()
+            .filter(|(_, state)| state.status == JobStatus::Queued)
+            .sorted_by_key(|(_, state)| state.created)
+            .map(|(id, _)| id.clone())
+            .collect_vec())
+    }
+
+    /// Cancel a job that hasn't finished. A running job stops after its current chunk.
+    pub async fn cancel(&self, id: &str) -> Result<Job, JobError> {
+        let timestamp = now();
+        self.update(id, JobEvent::Cancelled { timestamp }, false)
+            .await
+    }
+
+    pub async fn complete(&self, id: &str, result: Value) -> Result<Job, JobError> {
+        let timestamp = now();
+        self.update(id, JobEvent::Completed { result, timestamp }, false)
+            .await
+    }
+
+    pub async fn fail(&self, id: &str, error: String) -> Result<Job, JobError> {
+        let timestamp = now();
+        self.update(id, JobEvent::Failed { error, timestamp }, false)
+            .await
+    }
+
+    /// Record an attempt at notifying the job's callback that it finished.
+    pub async fn record_delivery(
+        &self,
+        id: &str,
+        attempt: DeliveryAttempt,
+    ) -> Result<(), JobError> {
+        self.update(id, JobEvent::Delivered { attempt }, true)
+            .await?;
+        Ok(())
+    }
+
+    pub fn deliveries(&self, id: &str) -> Result<Vec<DeliveryAttempt>, JobError> {
+        self.lock()?
+            .get(id)
+            .map(|state| state.deliveries.clone())
+            .ok_or(JobError::NotFound(id.to_owned()))
+    }
+
+    /// Translate the inputs of a job a chunk at a time, recording progress after each chunk.
+    /// Chunks translated before a restart aren't translated again. Returns [None] if the job
+    /// was cancelled.
+    pub async fn translate<TP: Translation>(
+        &self,
+        id: &str,
+        translator: &TP,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Option<Vec<TranslationOutput>>, JobError> {
+        let total = inputs.len();
+        let translated = match self.update(id, JobEvent::Started { total }, false).await {
+            Ok(job) => job.translated,
+            Err(JobError::AlreadyFinished(_)) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        for chunk in &inputs.into_iter().skip(translated).chunks(self.chunk_size) {
+            let outputs = translator.translate(chunk.collect_vec()).await?;
+            match self
+                .update(id, JobEvent::Translated { outputs }, false)
+                .await
+            {
+                Ok(_) => {}
+                Err(JobError::AlreadyFinished(_)) => return Ok(None),
+                Err(error) => return Err(error),
+            }
+        }
+
+        let jobs = self.lock()?;
+        let state = jobs.get(id).ok_or(JobError::NotFound(id.to_owned()))?;
+        Ok(Some(state.outputs.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::job::{DeliveryAttempt, JobStatus, JobStore};
+    use crate::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::TranslationInput;
+    use futures::executor::block_on;
+    use itertools::Itertools;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn echo() -> MockTranslationProvider {
+        MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true)
+    }
+
+    #[test]
+    fn it_should_translate_in_chunks() {
+        let store = JobStore::<String>::open(None, 2, None).unwrap();
+        let job = block_on(store.create("spec".to_owned())).unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(store.queued().unwrap(), vec![job.id.clone()]);
+
+        let inputs = ["one", "two", "three"]
+            .map(|text| TranslationInput::test(text, "fr"))
+            .to_vec();
+        let outputs = block_on(store.translate(&job.id, &echo(), inputs))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            outputs.iter().map(|output| output.text()).collect_vec(),
+            vec!["one", "two", "three"]
+        );
+        let job = block_on(store.complete(&job.id, json!("done"))).unwrap();
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!((job.total, job.translated), (Some(3), 3));
+        assert!(block_on(store.cancel(&job.id)).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_should_resume_jobs_after_a_restart() {
+        let directory = directory("translation-jobs");
+        let store = JobStore::<String>::open(Some(&directory), 2, None).unwrap();
+        let job = store.create("spec".to_owned()).await.unwrap();
+        store
+            .translate(
+                &job.id,
+                &echo(),
+                ["one", "two"]
+                    .map(|text| TranslationInput::test(text, "fr"))
+                    .to_vec(),
+            )
+            .await
+            .unwrap();
+
+        store
+            .record_delivery(
+                &job.id,
+                DeliveryAttempt {
+                    url: "http://localhost/callback".to_owned(),
+                    timestamp: 0,
+                    status: Some(500),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let reopened = JobStore::<String>::open(Some(&directory), 2, None).unwrap();
+        assert_eq!(reopened.queued().unwrap(), vec![job.id.clone()]);
+        assert_eq!(reopened.deliveries(&job.id).unwrap()[0].status, Some(500));
+        assert_eq!(*reopened.spec(&job.id).unwrap(), "spec");
+        let outputs = reopened
+            .translate(
+                &job.id,
+                &echo(),
+                ["ignored", "ignored", "three"]
+                    .map(|text| TranslationInput::test(text, "fr"))
+                    .to_vec(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            outputs.iter().map(|output| output.text()).collect_vec(),
+            vec!["one", "two", "three"]
+        );
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_should_truncate_an_event_cut_off_by_a_crash() {
+        let directory = directory("translation-jobs-truncated");
+        let store = JobStore::<String>::open(Some(&directory), 2, None).unwrap();
+        let job = store.create("spec".to_owned()).await.unwrap();
+        store
+            .translate(&job.id, &echo(), vec![TranslationInput::test("one", "fr")])
+            .await
+            .unwrap();
+        let path = directory.join(&job.id).with_extension("jsonl");
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, format!("{contents}{{\"event\":\"transl")).unwrap();
+        std::fs::write(
+            directory.join("corrupt.jsonl"),
+            "{\n{\"event\":\"cancelled\"}\n",
+        )
+        .unwrap();
+
+        let reopened = JobStore::<String>::open(Some(&directory), 2, None).unwrap();
+        assert_eq!(reopened.get(&job.id).unwrap().translated, 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+        assert!(reopened.get("corrupt").is_err());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_should_evict_expired_jobs() {
+        let directory = directory("translation-jobs-expired");
+        let store = JobStore::<String>::open(Some(&directory), 2, Some(Duration::ZERO)).unwrap();
+        let finished = store.create("spec".to_owned()).await.unwrap();
+        store.cancel(&finished.id).await.unwrap();
+        let queued = store.create("spec".to_owned()).await.unwrap();
+
+        assert!(store.get(&finished.id).is_err());
+        assert!(!directory
+            .join(&finished.id)
+            .with_extension("jsonl")
+            .exists());
+        assert_eq!(store.queued().unwrap(), vec![queued.id.clone()]);
+
+        store.cancel(&queued.id).await.unwrap();
+        let reopened = JobStore::<String>::open(Some(&directory), 2, Some(Duration::ZERO)).unwrap();
+        assert!(reopened.get(&queued.id).is_err());
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 0);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn it_should_stop_cancelled_jobs() {
+        let store = JobStore::<String>::open(None, 1, None).unwrap();
+        let job = block_on(store.create("spec".to_owned())).unwrap();
+        assert_eq!(
+            block_on(store.cancel(&job.id)).unwrap().status,
+            JobStatus::Cancelled
+        );
+        assert!(block_on(store.translate(
+            &job.id,
+            &echo(),
+            vec![TranslationInput::test("one", "fr")]
+        ))
+        .unwrap()
+        .is_none());
+        assert!(store.queued().unwrap().is_empty());
+    }
+}
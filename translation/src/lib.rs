@@ -1,14 +1,37 @@
-#![feature(trait_alias)]
-
+pub mod alternatives;
+pub mod cascade;
 pub mod claude;
 pub mod deepl;
+pub mod document;
+pub mod ensemble;
+pub mod gemini;
+pub mod job;
+mod llm;
+pub mod memory;
+pub mod mistral;
+pub mod mock;
+pub mod multi;
 pub mod openai;
+pub mod placeholder;
+pub mod pseudo;
+pub mod quality;
+pub mod segment;
+pub mod stream;
+pub mod validation;
 
+use crate::alternatives::Alternative;
+use crate::ensemble::EnsembleDecision;
+use crate::memory::{TranslationMemoryError, TranslationReference};
+use crate::quality::QualityEstimate;
+use crate::validation::InvalidTranslation;
 use ::claude::error::ClaudeError;
 use ::deepl::error::DeepLError;
+use ::gemini::error::GeminiError;
+use ::mistral::error::MistralError;
 use ::openai::error::OpenAIError;
 use async_trait::async_trait;
 use derive_more::Display;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_with::DeserializeFromStr;
 use std::fmt::{Display, Formatter};
@@ -51,6 +74,100 @@ pub struct TranslationInput {
     source_language: Option<Language>,
     #[serde(rename = "target")]
     target_language: Language,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    /// What the text is for, such as "Button that publishes a post", to tell apart short
+    /// texts that would otherwise be ambiguous. It isn't translated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<String>,
+    /// Longer notes about the text for translators.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Where in the UI the text appears.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screen: Option<String>,
+    #[serde(skip)]
+    references: Vec<TranslationReference>,
+    /// Notes about the text for translators, such as where it's used. They aren't translated.
+    #[serde(skip)]
+    hints: Vec<String>,
+    /// The texts just before this one, such as earlier messages of a chat or paragraphs of a
+    /// document, with their translations.
+    #[serde(skip)]
+    preceding: Vec<TranslationReference>,
+    /// The texts just after this one, which are translated separately.
+    #[serde(skip)]
+    following: Vec<String>,
+}
+
+impl TranslationInput {
+    pub fn new(text: String, source_language: Option<Language>, target_language: Language) -> Self {
+        TranslationInput {
+            text,
+            source_language,
+            target_language,
+            project: None,
+            context: None,
+            description: None,
+            screen: None,
+            references: Vec::new(),
+            hints: Vec::new(),
+            preceding: Vec::new(),
+            following: Vec::new(),
+        }
+    }
+
+    pub fn with_hints(mut self, hints: Vec<String>) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    pub fn with_context(mut self, context: Option<String>) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn with_neighbours(
+        mut self,
+        preceding: Vec<TranslationReference>,
+        following: Vec<String>,
+    ) -> Self {
+        self.preceding = preceding;
+        self.following = following;
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn source_language(&self) -> Option<&Language> {
+        self.source_language.as_ref()
+    }
+
+    pub fn target_language(&self) -> &Language {
+        &self.target_language
+    }
+
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    pub fn hints(&self) -> &[String] {
+        &self.hints
+    }
+}
+
+#[cfg(test)]
+impl TranslationInput {
+    /// An input to translate `text` from English to `target`.
+    pub(crate) fn test(text: &str, target: &str) -> Self {
+        TranslationInput::new(
+            text.to_owned(),
+            Some(Language::from_str("en").unwrap()),
+            Language::from_str(target).unwrap(),
+        )
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -60,6 +177,58 @@ pub struct TranslationOutput {
     #[serde(rename = "source")]
     #[serde(skip_serializing_if = "Option::is_none")]
     source_language: Option<Language>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_score: Option<f32>,
+    /// Candidate translations, best first, when they were asked for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    alternatives: Vec<Alternative>,
+    /// How good the translation is estimated to be, when it was asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<QualityEstimate>,
+    /// The tier of a cascade that produced the translation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tier: Option<String>,
+    /// The candidates of an ensemble and how the translation was chosen from them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ensemble: Option<EnsembleDecision>,
+}
+
+impl TranslationOutput {
+    pub fn new(text: String, source_language: Option<Language>) -> Self {
+        TranslationOutput {
+            text,
+            source_language,
+            match_score: None,
+            alternatives: Vec::new(),
+            quality: None,
+            tier: None,
+            ensemble: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn source_language(&self) -> Option<&Language> {
+        self.source_language.as_ref()
+    }
+
+    pub fn alternatives(&self) -> &[Alternative] {
+        &self.alternatives
+    }
+
+    pub fn quality(&self) -> Option<&QualityEstimate> {
+        self.quality.as_ref()
+    }
+
+    pub fn tier(&self) -> Option<&str> {
+        self.tier.as_deref()
+    }
+
+    pub fn ensemble(&self) -> Option<&EnsembleDecision> {
+        self.ensemble.as_ref()
+    }
 }
 
 #[derive(Error, Debug)]
@@ -68,10 +237,34 @@ pub enum TranslationError {
     ClaudeError(#[from] ClaudeError),
     #[error("DeepLError {0}")]
     DeepLError(#[from] DeepLError),
+    #[error("GeminiError {0}")]
+    GeminiError(#[from] GeminiError),
+    #[error("MistralError {0}")]
+    MistralError(#[from] MistralError),
     #[error("OpenAIError {0}")]
     OpenAIError(#[from] OpenAIError),
+    #[error("TranslationMemoryError {0}")]
+    TranslationMemoryError(#[from] TranslationMemoryError),
+    #[error("Unexpected response format {0}")]
+    UnexpectedResponseFormat(String),
+    #[error("{0}")]
+    InvalidTranslation(#[from] InvalidTranslation),
+    /// Some of the translations weren't usable. The outputs of the others are kept, in the
+    /// order of the inputs with `None` for those that have an error.
+    #[error("Invalid translations of inputs {}", .errors.iter().map(|error| error.index).join(", "))]
+    PartialTranslation {
+        outputs: Vec<Option<TranslationOutput>>,
+        errors: Vec<InvalidTranslation>,
+    },
+    /// A provider gave a different number of outputs than it was given inputs.
+    #[error("Expected {expected} outputs but got {received}")]
+    OutputCountMismatch { expected: usize, received: usize },
+    /// The response was still cut off at the maximum number of tokens after being continued.
+    #[error("Truncated response after {} characters", .0.chars().count())]
+    Truncated(String),
 }
 
+#[allow(async_fn_in_trait)]
 pub trait Translation {
     async fn translate(
         &self,
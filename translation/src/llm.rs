@@ -0,0 +1,1051 @@
+use crate::alternatives::Alternative;
+use crate::ensemble::{EnsembleCandidate, EnsembleJudgement};
+use crate::quality::{QualityError, QualityEstimate};
+use crate::stream::TranslationEvents;
+use crate::validation::{
+    clean_response, collect_outputs, strict_instructions, validate, InvalidTranslation,
+};
+use crate::{Language, TranslationError, TranslationInput, TranslationOutput};
+use futures::future::{join_all, select, Either};
+use itertools::Itertools;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::pin::pin;
+
+/// How many times a truncated response is continued before giving up on it.
+const MAX_CONTINUATIONS: usize = 2;
+
+#[derive(Clone)]
+pub(crate) struct LLMPrompt {
+    pub system_prompt: String,
+    pub prompt: String,
+    /// Roughly how many characters the response has, to allow it enough tokens.
+    pub expected_characters: usize,
+    /// The JSON schema of the response, for clients that can be made to follow one.
+    pub response_schema: Option<LLMResponseSchema>,
+}
+
+#[derive(Clone)]
+pub(crate) struct LLMResponseSchema {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub schema: serde_json::Value,
+}
+
+fn from_source(input: &TranslationInput) -> String {
+    input
+        .source_language
+        .as_ref()
+        .map(|source| format!(" from {}", Language::to_string(source)))
+        .unwrap_or_default()
+}
+
+/// Add what the LLM should know about the text of `input`, besides the text itself.
+fn push_notes(system_prompt: &mut String, input: &TranslationInput) {
+    let about = [
+        ("Context", &input.context),
+        ("Description", &input.description),
+        ("Screen", &input.screen),
+    ];
+    if about.iter().any(|(_, value)| value.is_some()) {
+        system_prompt.push_str(
+            "\nAbout the text, to help choose the right translation, don't translate this or include it in the response:",
+        );
+        for (label, value) in about {
+            if let Some(value) = value {
+                system_prompt.push_str(&format!("\n{label}: {value}"));
+            }
+        }
+    }
+    if !input.hints.is_empty() {
+        system_prompt.push_str("\nNotes about the text, don't translate them:");
+        for hint in &input.hints {
+            system_prompt.push_str(&format!("\n- {hint}"));
+        }
+    }
+    if !input.preceding.is_empty() {
+        system_prompt.push_str(
+            "\nThe text comes after these texts, shown with their translations, keep the terminology and pronouns consistent with them:",
+        );
+        for preceding in &input.preceding {
+            system_prompt.push_str(&format!(
+                "\n{} => {}",
+                preceding.source_text, preceding.target_text
+            ));
+        }
+    }
+    if !input.following.is_empty() {
+        system_prompt.push_str("\nThe text comes before these texts, don't translate them:");
+        for following in &input.following {
+            system_prompt.push_str(&format!("\n{following}"));
+        }
+    }
+    if !input.references.is_empty() {
+        system_prompt
+            .push_str("\nFor consistency, here are previous translations of similar text:");
+        for reference in &input.references {
+            system_prompt.push_str(&format!(
+                "\n{} => {}",
+                reference.source_text, reference.target_text
+            ));
+        }
+    }
+}
+
+/// Roughly how many characters of translations an LLM is asked for in one response, well
+/// within its output tokens.
+const MULTI_TARGET_BUDGET_CHARACTERS: usize = 8000;
+
+/// Roughly how many characters a JSON response has besides its translations, such as keys,
+/// rationales, a judge's errors or justification.
+const JSON_RESPONSE_CHARACTERS: usize = 1000;
+
+impl From<TranslationInput> for LLMPrompt {
+    fn from(input: TranslationInput) -> Self {
+        let mut system_prompt = format!(
+            "Please translate the user's text{} to {}, only respond with the translation",
+            from_source(&input),
+            input.target_language
+        );
+        push_notes(&mut system_prompt, &input);
+        LLMPrompt {
+            system_prompt,
+            expected_characters: input.text.chars().count(),
+            prompt: input.text,
+            response_schema: None,
+        }
+    }
+}
+
+impl LLMPrompt {
+    /// A prompt for the translations of `inputs`, the same text in several languages, as a
+    /// JSON object keyed by language. There's none when the translations might not fit in
+    /// one response, or when the inputs have notes for a single language such as references.
+    fn for_targets(inputs: &[TranslationInput]) -> Option<Self> {
+        let [first, ..] = inputs else {
+            return None;
+        };
+        // translations are rarely more than twice as long as their text
+        let expected_characters = first.text.chars().count() * 2 * inputs.len();
+        if inputs.len() < 2
+            || expected_characters > MULTI_TARGET_BUDGET_CHARACTERS
+            || inputs
+                .iter()
+                .any(|input| !input.references.is_empty() || !input.preceding.is_empty())
+        {
+            return None;
+        }
+
+        let targets = inputs
+            .iter()
+            .map(|input| input.target_language.to_string())
+            .join(", ");
+        let mut system_prompt = format!(
+            "Please translate the user's text{} to each of these languages: {targets}. Only respond with a JSON object that has each language code as a key and the translation as its value",
+            from_source(first)
+        );
+        push_notes(&mut system_prompt, first);
+        Some(LLMPrompt {
+            system_prompt,
+            prompt: first.text.clone(),
+            expected_characters: expected_characters + JSON_RESPONSE_CHARACTERS,
+            response_schema: None,
+        })
+    }
+
+    /// A prompt for up to `count` distinct translations of `input`, as a JSON object with an
+    /// array of them.
+    fn for_alternatives(input: TranslationInput, count: usize) -> Self {
+        let mut system_prompt = format!(
+            "Please translate the user's text{} to {}, giving up to {count} distinct translations, best first. Only respond with a JSON object that has the translations as `translations`, an array of objects that have the translation as `translation` and a few words on why to choose it as `rationale`, such as \"more formal\" or \"literal\"",
+            from_source(&input),
+            input.target_language
+        );
+        push_notes(&mut system_prompt, &input);
+        LLMPrompt {
+            system_prompt,
+            expected_characters: input.text.chars().count() * 2 * count + JSON_RESPONSE_CHARACTERS,
+            prompt: input.text,
+            response_schema: Some(LLMResponseSchema {
+                name: "alternatives",
+                description: "The distinct translations of the text, best first",
+                schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "translations": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "translation": { "type": "string" },
+                                    "rationale": { "type": ["string", "null"] },
+                                },
+                                "required": ["translation", "rationale"],
+                                "additionalProperties": false,
+                            },
+                        },
+                    },
+                    "required": ["translations"],
+                    "additionalProperties": false,
+                }),
+            }),
+        }
+    }
+}
+
+impl LLMPrompt {
+    /// A prompt to score how well `output` translates `input` and list its errors, as a JSON
+    /// object.
+    fn for_quality(input: &TranslationInput, output: &TranslationOutput) -> Self {
+        let source = input
+            .source_language
+            .as_ref()
+            .or(output.source_language.as_ref())
+            .map(|source| format!(" in {source}"))
+            .unwrap_or_default();
+        let mut system_prompt = format!(
+            "Please judge the user's translation to {} of a text{source}. Score its adequacy and fluency together from 0, unusable, to 100, perfect, and list its errors with the category accuracy, fluency, terminology, style, omission, addition, untranslated or other. Only respond with a JSON object that has the score as `score` and the errors as `errors`, an array of objects with a `category` and a short `description`",
+            input.target_language
+        );
+        push_notes(&mut system_prompt, input);
+        LLMPrompt {
+            system_prompt,
+            prompt: format!("Text:\n{}\n\nTranslation:\n{}", input.text, output.text),
+            expected_characters: JSON_RESPONSE_CHARACTERS,
+            response_schema: None,
+        }
+    }
+}
+
+impl LLMPrompt {
+    /// A prompt to score the `candidates` translations of `input` and choose the best of them
+    /// or merge them, as a JSON object.
+    fn for_candidates(input: &TranslationInput, candidates: &[EnsembleCandidate]) -> Self {
+        let mut system_prompt = format!(
+            "Please judge the numbered candidate translations{} to {} of the user's text, then choose the best of them or merge them into a better translation. Only respond with a JSON object that has a score from 0 to 100 for each candidate, in order, as `scores`, the number of the chosen candidate as `winner` or null when they're merged, the final translation as `translation` and a brief justification as `justification`",
+            from_source(input),
+            input.target_language
+        );
+        push_notes(&mut system_prompt, input);
+        let candidates = candidates
+            .iter()
+            .enumerate()
+            .map(|(index, candidate)| format!("{}. {}", index + 1, candidate.text))
+            .join("\n");
+        LLMPrompt {
+            system_prompt,
+            prompt: format!("Text:\n{}\n\nCandidates:\n{candidates}", input.text),
+            // a merged translation is rarely more than twice as long as the text
+            expected_characters: input.text.chars().count() * 2 + JSON_RESPONSE_CHARACTERS,
+            response_schema: None,
+        }
+    }
+}
+
+/// The JSON in a response, without the markdown code fence LLMs tend to put around it.
+fn response_json(response: &str) -> &str {
+    response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_matches('`')
+        .trim()
+}
+
+/// The translations for each of `inputs` in a response to [LLMPrompt::for_targets].
+fn parse_target_translations(response: &str, inputs: &[TranslationInput]) -> Option<Vec<String>> {
+    let translations =
+        serde_json::from_str::<HashMap<String, String>>(response_json(response)).ok()?;
+    inputs
+        .iter()
+        .map(|input| {
+            translations
+                .get(&input.target_language.to_string())
+                .cloned()
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct LLMAlternative {
+    translation: String,
+    rationale: Option<String>,
+}
+
+/// Clients that can't follow a schema may respond with the bare array.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LLMAlternatives {
+    Object { translations: Vec<LLMAlternative> },
+    Array(Vec<LLMAlternative>),
+}
+
+impl From<LLMAlternatives> for Vec<LLMAlternative> {
+    fn from(alternatives: LLMAlternatives) -> Self {
+        match alternatives {
+            LLMAlternatives::Object { translations } => translations,
+            LLMAlternatives::Array(translations) => translations,
+        }
+    }
+}
+
+/// The distinct translations in a response to [LLMPrompt::for_alternatives], at most `count`.
+fn parse_alternatives(response: &str, count: usize) -> Option<Vec<Alternative>> {
+    let alternatives = serde_json::from_str::<LLMAlternatives>(response_json(response))
+        .map(Vec::<LLMAlternative>::from)
+        .ok()?
+        .into_iter()
+        .filter(|alternative| !alternative.translation.trim().is_empty())
+        .unique_by(|alternative| alternative.translation.trim().to_owned())
+        .take(count)
+        .map(|alternative| Alternative {
+            text: alternative.translation,
+            rationale: alternative
+                .rationale
+                .filter(|rationale| !rationale.trim().is_empty()),
+        })
+        .collect_vec();
+    (!alternatives.is_empty()).then_some(alternatives)
+}
+
+#[derive(Deserialize)]
+struct LLMQualityEstimate {
+    score: f32,
+    #[serde(default)]
+    errors: Vec<QualityError>,
+}
+
+/// The estimate in a response to [LLMPrompt::for_quality], its score within 0 to 100.
+fn parse_quality_estimate(response: &str) -> Option<QualityEstimate> {
+    let estimate = serde_json::from_str::<LLMQualityEstimate>(response_json(response)).ok()?;
+    Some(QualityEstimate {
+        score: estimate.score.clamp(0.0, 100.0).round() as u8,
+        errors: estimate.errors,
+        low_confidence: false,
+    })
+}
+
+#[derive(Deserialize)]
+struct LLMEnsembleJudgement {
+    scores: Vec<f32>,
+    winner: Option<usize>,
+    translation: Option<String>,
+    justification: Option<String>,
+}
+
+/// The judgement in a response to [LLMPrompt::for_candidates], with a score for each of the
+/// `candidates` and a winner among them.
+fn parse_ensemble_judgement(
+    response: &str,
+    candidates: &[EnsembleCandidate],
+) -> Option<EnsembleJudgement> {
+    let judgement = serde_json::from_str::<LLMEnsembleJudgement>(response_json(response)).ok()?;
+    if judgement.scores.len() != candidates.len() {
+        return None;
+    }
+    // candidates are numbered from 1
+    let winner = match judgement.winner {
+        Some(winner) => Some(
+            winner
+                .checked_sub(1)
+                .filter(|winner| *winner < candidates.len())?,
+        ),
+        None => None,
+    };
+    let text = judgement
+        .translation
+        .filter(|translation| !translation.trim().is_empty())
+        .or(winner.map(|winner| candidates[winner].text.clone()))?;
+    Some(EnsembleJudgement {
+        scores: judgement
+            .scores
+            .into_iter()
+            .map(|score| score.clamp(0.0, 100.0).round() as u8)
+            .collect_vec(),
+        winner,
+        text,
+        justification: judgement
+            .justification
+            .filter(|justification| !justification.trim().is_empty()),
+    })
+}
+
+/// `judgement` with its translation cleaned, or with its best candidate chosen instead when
+/// that isn't a usable translation, as a merged translation isn't one of the validated
+/// candidates.
+fn validated_judgement(
+    input: &TranslationInput,
+    candidates: &[EnsembleCandidate],
+    judgement: EnsembleJudgement,
+) -> EnsembleJudgement {
+    let text = clean_response(&judgement.text, input);
+    if validate(&text, input).is_ok() {
+        return EnsembleJudgement { text, ..judgement };
+    }
+    let winner = judgement
+        .winner
+        .unwrap_or_else(|| judgement.scores.iter().position_max().unwrap_or_default());
+    EnsembleJudgement {
+        winner: Some(winner),
+        text: candidates[winner].text.clone(),
+        ..judgement
+    }
+}
+
+pub(crate) struct LLMResponse {
+    pub text: String,
+    /// Whether the response was cut off at the maximum number of tokens.
+    pub truncated: bool,
+}
+
+pub(crate) trait LLMClient {
+    type Error;
+
+    async fn respond_to(&self, prompt: LLMPrompt) -> Result<LLMResponse, Self::Error>;
+
+    /// Like [Self::respond_to], calling `on_delta` with each part of the response as it's
+    /// generated. Clients that can't stream call it once with the whole response.
+    async fn respond_to_streaming(
+        &self,
+        prompt: LLMPrompt,
+        mut on_delta: impl FnMut(&str),
+    ) -> Result<LLMResponse, Self::Error> {
+        let response = self.respond_to(prompt).await?;
+        on_delta(&response.text);
+        Ok(response)
+    }
+
+    /// The rest of a `partial` response to `prompt` that was truncated, or `None` when the
+    /// client can't continue responses.
+    async fn continue_response(
+        &self,
+        _prompt: LLMPrompt,
+        _partial: &str,
+    ) -> Result<Option<LLMResponse>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// Continue `response` to `prompt` while it's truncated, calling `on_delta` with each
+/// continuation, and give up when it still is after [MAX_CONTINUATIONS].
+async fn complete_response<Client>(
+    client: &Client,
+    prompt: LLMPrompt,
+    mut response: LLMResponse,
+    mut on_delta: impl FnMut(&str),
+) -> Result<String, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    for _ in 0..MAX_CONTINUATIONS {
+        if !response.truncated {
+            break;
+        }
+        let Some(continuation) = client
+            .continue_response(prompt.clone(), &response.text)
+            .await?
+        else {
+            break;
+        };
+        // a continuation may carry on from the partial response without its trailing whitespace
+        if continuation.text.starts_with(char::is_whitespace) {
+            response.text.truncate(response.text.trim_end().len());
+        }
+        on_delta(&continuation.text);
+        response.text.push_str(&continuation.text);
+        response.truncated = continuation.truncated;
+    }
+    if response.truncated {
+        return Err(TranslationError::Truncated(response.text));
+    }
+    Ok(response.text)
+}
+
+/// The whole response of `client` to `prompt`, continued when it's truncated.
+async fn respond<Client>(client: &Client, prompt: LLMPrompt) -> Result<String, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    let response = client.respond_to(prompt.clone()).await?;
+    complete_response(client, prompt, response, |_| {}).await
+}
+
+/// Like [respond], calling `on_delta` with each part of the response as it's generated.
+async fn respond_streaming<Client>(
+    client: &Client,
+    prompt: LLMPrompt,
+    mut on_delta: impl FnMut(&str),
+) -> Result<String, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    let response = client
+        .respond_to_streaming(prompt.clone(), &mut on_delta)
+        .await?;
+    complete_response(client, prompt, response, on_delta).await
+}
+
+/// The translation of `input`, the input at `index`, in an LLM's `response`, asking again with
+/// stricter instructions when it isn't usable.
+async fn validated_translation<Client>(
+    client: &Client,
+    index: usize,
+    input: &TranslationInput,
+    response: String,
+) -> Result<String, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    let translation = clean_response(&response, input);
+    if validate(&translation, input).is_ok() {
+        return Ok(translation);
+    }
+    let mut prompt = LLMPrompt::from(input.clone());
+    prompt.system_prompt.push_str(&strict_instructions(input));
+    let translation = clean_response(&respond(client, prompt).await?, input);
+    validate(&translation, input).map_err(|error| InvalidTranslation { index, error })?;
+    Ok(translation)
+}
+
+pub(crate) async fn translate_with_llm<Client>(
+    client: &Client,
+    inputs: Vec<TranslationInput>,
+) -> Result<Vec<TranslationOutput>, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    collect_outputs(
+        join_all(
+            inputs
+                .into_iter()
+                .enumerate()
+                .map(|(index, input)| async move {
+                    let response = respond(client, LLMPrompt::from(input.clone())).await?;
+                    let translation =
+                        validated_translation(client, index, &input, response).await?;
+                    Ok(TranslationOutput::new(translation, None))
+                })
+                .collect_vec(),
+        )
+        .await,
+    )
+}
+
+/// Translate `inputs` in parallel, sending the partial responses of each as they're generated
+/// and its validated output as soon as it's done.
+pub(crate) async fn translate_streaming_with_llm<Client>(
+    client: &Client,
+    inputs: Vec<TranslationInput>,
+    events: &TranslationEvents,
+) -> Vec<Option<TranslationOutput>>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    join_all(
+        inputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, input)| async move {
+                let translation = pin!(async {
+                    let response =
+                        respond_streaming(client, LLMPrompt::from(input.clone()), |delta| {
+                            events.delta(index, delta)
+                        })
+                        .await?;
+                    validated_translation(client, index, &input, response).await
+                });
+                let translation = match select(translation, pin!(events.closed())).await {
+                    Either::Left((translation, _)) => translation,
+                    // nobody is listening any more, so the response isn't waited for
+                    Either::Right(_) => return None,
+                };
+                match translation {
+                    Ok(translation) => {
+                        let output = TranslationOutput::new(translation, None);
+                        events.output(index, &output).await;
+                        Some(output)
+                    }
+                    Err(error) => {
+                        events.error(index, &error).await;
+                        None
+                    }
+                }
+            })
+            .collect_vec(),
+    )
+    .await
+}
+
+/// Translate each group of inputs, the same text in several languages, with one response
+/// when the translations fit, otherwise with one response for each language.
+pub(crate) async fn translate_targets_with_llm<Client>(
+    client: &Client,
+    groups: Vec<Vec<TranslationInput>>,
+) -> Result<Vec<Vec<TranslationOutput>>, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    join_all(
+        groups
+            .into_iter()
+            .map(|inputs| async move {
+                if let Some(prompt) = LLMPrompt::for_targets(&inputs) {
+                    let response = respond(client, prompt).await?;
+                    // unusable translations are asked for again one language at a time
+                    let texts = parse_target_translations(&response, &inputs).filter(|texts| {
+                        texts
+                            .iter()
+                            .zip(&inputs)
+                            .all(|(text, input)| validate(text, input).is_ok())
+                    });
+                    if let Some(texts) = texts {
+                        return Ok(texts
+                            .into_iter()
+                            .map(|text| TranslationOutput::new(text, None))
+                            .collect_vec());
+                    }
+                }
+                translate_with_llm(client, inputs).await
+            })
+            .collect_vec(),
+    )
+    .await
+    .into_iter()
+    .collect()
+}
+
+/// Translate each input with up to `count` alternatives, see [AlternativeTranslation].
+///
+/// [AlternativeTranslation]: crate::alternatives::AlternativeTranslation
+pub(crate) async fn translate_alternatives_with_llm<Client>(
+    client: &Client,
+    inputs: Vec<TranslationInput>,
+    count: usize,
+) -> Result<Vec<TranslationOutput>, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    collect_outputs(
+        join_all(
+            inputs
+                .into_iter()
+                .enumerate()
+                .map(|(index, input)| async move {
+                    let response =
+                        respond(client, LLMPrompt::for_alternatives(input.clone(), count)).await?;
+                    let alternatives = parse_alternatives(&response, count)
+                        .ok_or(TranslationError::UnexpectedResponseFormat(response))?;
+                    let mut alternatives = alternatives
+                        .into_iter()
+                        .map(|alternative| Alternative {
+                            text: clean_response(&alternative.text, &input),
+                            ..alternative
+                        })
+                        .collect_vec();
+                    let first = alternatives[0].text.clone();
+                    alternatives.retain(|alternative| validate(&alternative.text, &input).is_ok());
+                    // without a usable candidate, the input is translated again on its own
+                    if alternatives.is_empty() {
+                        let translation =
+                            validated_translation(client, index, &input, first).await?;
+                        alternatives.push(Alternative {
+                            text: translation,
+                            rationale: None,
+                        });
+                    }
+                    Ok(TranslationOutput {
+                        text: alternatives[0].text.clone(),
+                        alternatives,
+                        ..TranslationOutput::new(String::new(), None)
+                    })
+                })
+                .collect_vec(),
+        )
+        .await,
+    )
+}
+
+/// Score each of `outputs`, see [QualityJudge]. An output is left without an estimate when the
+/// response can't be read, as its translation is still usable.
+///
+/// [QualityJudge]: crate::quality::QualityJudge
+pub(crate) async fn judge_quality_with_llm<Client>(
+    client: &Client,
+    inputs: &[TranslationInput],
+    outputs: &[TranslationOutput],
+) -> Result<Vec<Option<QualityEstimate>>, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    join_all(
+        inputs
+            .iter()
+            .zip(outputs)
+            .map(|(input, output)| async move {
+                let response = respond(client, LLMPrompt::for_quality(input, output)).await?;
+                Ok(parse_quality_estimate(&response))
+            })
+            .collect_vec(),
+    )
+    .await
+    .into_iter()
+    .collect()
+}
+
+/// Judge the candidate translations of each input, see [EnsembleJudge].
+///
+/// [EnsembleJudge]: crate::ensemble::EnsembleJudge
+pub(crate) async fn judge_candidates_with_llm<Client>(
+    client: &Client,
+    inputs: &[TranslationInput],
+    candidates: &[Vec<EnsembleCandidate>],
+) -> Result<Vec<Option<EnsembleJudgement>>, TranslationError>
+where
+    Client: LLMClient,
+    TranslationError: From<Client::Error>,
+{
+    join_all(
+        inputs
+            .iter()
+            .zip(candidates)
+            .map(|(input, candidates)| async move {
+                let response =
+                    respond(client, LLMPrompt::for_candidates(input, candidates)).await?;
+                parse_ensemble_judgement(&response, candidates)
+                    .map(|judgement| Some(validated_judgement(input, candidates, judgement)))
+                    .ok_or(TranslationError::UnexpectedResponseFormat(response))
+            })
+            .collect_vec(),
+    )
+    .await
+    .into_iter()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alternatives::Alternative;
+    use crate::ensemble::{EnsembleCandidate, EnsembleJudgement};
+    use crate::llm::{
+        parse_alternatives, parse_ensemble_judgement, parse_quality_estimate,
+        parse_target_translations, translate_streaming_with_llm, translate_with_llm,
+        validated_judgement, LLMClient, LLMPrompt, LLMResponse,
+    };
+    use crate::quality::{QualityError, QualityErrorCategory};
+    use crate::stream::TranslationEvents;
+    use crate::validation::ValidationError;
+    use crate::{TranslationError, TranslationInput};
+    use futures::executor::block_on;
+    use futures::future::pending;
+    use itertools::Itertools;
+    use std::sync::Mutex;
+    use tokio::sync::mpsc::channel;
+
+    /// Responds with each of its responses in turn, remembering the system prompts.
+    struct ScriptedClient {
+        responses: Mutex<Vec<(&'static str, bool)>>,
+        system_prompts: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedClient {
+        fn new(responses: &[&'static str]) -> Self {
+            Self::with_truncation(
+                &responses
+                    .iter()
+                    .map(|response| (*response, false))
+                    .collect_vec(),
+            )
+        }
+
+        /// Responds with each response and whether it's truncated, continuing responses too.
+        fn with_truncation(responses: &[(&'static str, bool)]) -> Self {
+            ScriptedClient {
+                responses: Mutex::new(responses.iter().rev().copied().collect_vec()),
+                system_prompts: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn next_response(&self) -> LLMResponse {
+            let (text, truncated) = self.responses.lock().unwrap().pop().unwrap();
+            LLMResponse {
+                text: text.to_owned(),
+                truncated,
+            }
+        }
+    }
+
+    impl LLMClient for ScriptedClient {
+        type Error = TranslationError;
+
+        async fn respond_to(&self, prompt: LLMPrompt) -> Result<LLMResponse, Self::Error> {
+            self.system_prompts
+                .lock()
+                .unwrap()
+                .push(prompt.system_prompt);
+            Ok(self.next_response())
+        }
+
+        async fn continue_response(
+            &self,
+            _prompt: LLMPrompt,
+            _partial: &str,
+        ) -> Result<Option<LLMResponse>, Self::Error> {
+            Ok(Some(self.next_response()))
+        }
+    }
+
+    /// Never responds.
+    struct SilentClient;
+
+    impl LLMClient for SilentClient {
+        type Error = TranslationError;
+
+        async fn respond_to(&self, _prompt: LLMPrompt) -> Result<LLMResponse, Self::Error> {
+            pending().await
+        }
+    }
+
+    #[test]
+    fn it_should_ask_for_several_targets_in_one_response() {
+        let inputs = ["fr", "de"].map(|target| TranslationInput::test("Save", target));
+        let prompt = LLMPrompt::for_targets(&inputs).unwrap();
+        assert!(prompt.system_prompt.contains("fr, de"));
+        assert!(LLMPrompt::for_targets(&inputs[..1]).is_none());
+        let long = ["fr", "de"].map(|target| TranslationInput::test(&"Save ".repeat(1000), target));
+        assert!(LLMPrompt::for_targets(&long).is_none());
+
+        let response = "```json\n{ \"de\": \"Speichern\", \"fr\": \"Enregistrer\" }\n```";
+        assert_eq!(
+            parse_target_translations(response, &inputs).unwrap(),
+            vec!["Enregistrer", "Speichern"]
+        );
+        let partial = inputs
+            .iter()
+            .cloned()
+            .chain([TranslationInput::test("Save", "es")])
+            .collect_vec();
+        assert!(parse_target_translations(response, &partial).is_none());
+    }
+
+    #[test]
+    fn it_should_parse_distinct_alternatives() {
+        let response = r#"[
+            { "translation": "Enregistrer", "rationale": "standard" },
+            { "translation": "Enregistrer " },
+            { "translation": "Sauvegarder", "rationale": "" },
+            { "translation": "Sauver" }
+        ]"#;
+        assert_eq!(
+            parse_alternatives(response, 2).unwrap(),
+            vec![
+                Alternative {
+                    text: "Enregistrer".to_owned(),
+                    rationale: Some("standard".to_owned()),
+                },
+                Alternative {
+                    text: "Sauvegarder".to_owned(),
+                    rationale: None,
+                },
+            ]
+        );
+        assert!(parse_alternatives("Enregistrer", 2).is_none());
+    }
+
+    #[test]
+    fn it_should_parse_alternatives_that_follow_the_schema() {
+        let response = r#"{ "translations": [
+            { "translation": "Enregistrer", "rationale": null },
+            { "translation": "Sauvegarder", "rationale": "more formal" }
+        ] }"#;
+        assert_eq!(
+            parse_alternatives(response, 3).unwrap(),
+            vec![
+                Alternative {
+                    text: "Enregistrer".to_owned(),
+                    rationale: None,
+                },
+                Alternative {
+                    text: "Sauvegarder".to_owned(),
+                    rationale: Some("more formal".to_owned()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_parse_quality_estimates() {
+        let response = r#"```json
+        {
+            "score": 62.4,
+            "errors": [
+                { "category": "terminology", "description": "\"Sauver\" is unusual for files" },
+                { "category": "register" }
+            ]
+        }
+        ```"#;
+        let estimate = parse_quality_estimate(response).unwrap();
+        assert_eq!(estimate.score, 62);
+        assert_eq!(
+            estimate.errors,
+            vec![
+                QualityError {
+                    category: QualityErrorCategory::Terminology,
+                    description: Some("\"Sauver\" is unusual for files".to_owned()),
+                },
+                QualityError {
+                    category: QualityErrorCategory::Other,
+                    description: None,
+                },
+            ]
+        );
+        assert_eq!(
+            parse_quality_estimate(r#"{ "score": 140 }"#).unwrap().score,
+            100
+        );
+        assert!(parse_quality_estimate("Good translation").is_none());
+    }
+
+    #[test]
+    fn it_should_parse_ensemble_judgements() {
+        let candidates = ["Sauver", "Enregistrer"]
+            .map(|text| EnsembleCandidate {
+                provider: "deepl".to_owned(),
+                text: text.to_owned(),
+                score: None,
+            })
+            .to_vec();
+        let response =
+            r#"{ "scores": [40, 95.2], "winner": 2, "justification": "Usual for files" }"#;
+        let judgement = parse_ensemble_judgement(response, &candidates).unwrap();
+        assert_eq!(judgement.scores, vec![40, 95]);
+        assert_eq!(judgement.winner, Some(1));
+        assert_eq!(judgement.text, "Enregistrer");
+
+        let merged =
+            r#"{ "scores": [40, 90], "winner": null, "translation": "Enregistrer le fichier" }"#;
+        let judgement = parse_ensemble_judgement(merged, &candidates).unwrap();
+        assert_eq!(judgement.winner, None);
+        assert_eq!(judgement.text, "Enregistrer le fichier");
+
+        assert!(
+            parse_ensemble_judgement(r#"{ "scores": [40, 90], "winner": 3 }"#, &candidates)
+                .is_none()
+        );
+        assert!(
+            parse_ensemble_judgement(r#"{ "scores": [40], "winner": 1 }"#, &candidates).is_none()
+        );
+    }
+
+    #[test]
+    fn it_should_ask_again_for_unusable_translations() {
+        let client = ScriptedClient::new(&[
+            "I'm sorry, but I can't assist with that request.",
+            "Here is the translation:\n\"Enregistrer\"",
+        ]);
+        let outputs = block_on(translate_with_llm(
+            &client,
+            vec![TranslationInput::test("Save", "fr")],
+        ))
+        .unwrap();
+        assert_eq!(outputs[0].text(), "Enregistrer");
+        let system_prompts = client.system_prompts.into_inner().unwrap();
+        assert!(system_prompts[1].contains("without any introduction"));
+
+        let client = ScriptedClient::new(&[
+            "I'm sorry, but I can't assist with that request.",
+            "I'm sorry, I can't translate that.",
+            "Ouvrir",
+        ]);
+        let Err(TranslationError::PartialTranslation { outputs, errors }) =
+            block_on(translate_with_llm(
+                &client,
+                vec![
+                    TranslationInput::test("Save", "fr"),
+                    TranslationInput::test("Open", "fr"),
+                ],
+            ))
+        else {
+            panic!("Expected a partial translation");
+        };
+        assert!(outputs[0].is_none());
+        assert_eq!(outputs[1].as_ref().unwrap().text(), "Ouvrir");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].index, 0);
+        assert!(matches!(errors[0].error, ValidationError::Refusal(_)));
+    }
+
+    #[test]
+    fn it_should_replace_unusable_merged_translations() {
+        let candidates = ["Enregistrer", "Sauvegarder"].map(|text| EnsembleCandidate {
+            provider: text.to_owned(),
+            text: text.to_owned(),
+            score: None,
+        });
+        let judgement = EnsembleJudgement {
+            scores: vec![60, 90],
+            winner: None,
+            text: "I'm sorry, I can't translate that request.".to_owned(),
+            justification: None,
+        };
+        let judgement = validated_judgement(
+            &TranslationInput::test("Save", "fr"),
+            &candidates,
+            judgement,
+        );
+        assert_eq!(judgement.winner, Some(1));
+        assert_eq!(judgement.text, "Sauvegarder");
+    }
+
+    #[test]
+    fn it_should_continue_truncated_responses() {
+        let client = ScriptedClient::with_truncation(&[
+            ("Il fait très beau aujourd'hui, ", true),
+            (" alors nous allons à la plage", false),
+        ]);
+        let outputs = block_on(translate_with_llm(
+            &client,
+            vec![TranslationInput::test(
+                "The weather is lovely today, so we are going to the beach",
+                "fr",
+            )],
+        ))
+        .unwrap();
+        assert_eq!(
+            outputs[0].text(),
+            "Il fait très beau aujourd'hui, alors nous allons à la plage"
+        );
+
+        let client = ScriptedClient::with_truncation(&[
+            ("Il fait", true),
+            (" très beau", true),
+            (" aujourd'hui", true),
+        ]);
+        assert!(matches!(
+            block_on(translate_with_llm(&client, vec![TranslationInput::test("Save", "fr")])),
+            Err(TranslationError::Truncated(partial)) if partial == "Il fait très beau aujourd'hui"
+        ));
+    }
+
+    #[test]
+    fn it_should_stop_streaming_once_nobody_listens() {
+        let (sender, receiver) = channel(4);
+        drop(receiver);
+
+        let outputs = block_on(translate_streaming_with_llm(
+            &SilentClient,
+            vec![TranslationInput::test("Save", "fr")],
+            &TranslationEvents::new(sender),
+        ));
+
+        assert!(outputs.iter().all(Option::is_none));
+    }
+}
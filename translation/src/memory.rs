@@ -0,0 +1,794 @@
+use crate::multi::{regroup, MultiTargetTranslation};
+use crate::stream::{StreamingTranslation, TranslationEvents};
+use crate::validation::{complete_outputs, expect_outputs, partial_outputs, InvalidTranslation};
+use crate::{Language, Translation, TranslationError, TranslationInput, TranslationOutput};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TranslationMemoryError {
+    #[error("IoError {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("SerdeJsonError {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("Invalid fuzzy threshold config")]
+    InvalidFuzzyThresholdConfig,
+    #[error("Translation memory lock poisoned")]
+    LockPoisoned,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Segment {
+    pub source_text: String,
+    pub target_text: String,
+    pub source_language: Option<Language>,
+    /// Whether the source language was detected by the provider rather than given.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub source_detected: bool,
+    pub target_language: Language,
+    pub provider: String,
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Only reused for inputs with the same context.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+#[derive(Default)]
+pub struct SegmentFilter {
+    pub source_language: Option<Language>,
+    pub target_language: Option<Language>,
+    pub project: Option<String>,
+    /// Inclusive lower bound in seconds since the unix epoch.
+    pub from: Option<u64>,
+    /// Exclusive upper bound in seconds since the unix epoch.
+    pub to: Option<u64>,
+}
+
+impl SegmentFilter {
+    pub fn matches(&self, segment: &Segment) -> bool {
+        let source_matches = match (&self.source_language, &segment.source_language) {
+            (Some(filter), Some(source)) => filter.0 == source.0,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        let target_matches = !matches!(
+            &self.target_language,
+            Some(target) if target.0 != segment.target_language.0
+        );
+        let project_matches = !matches!(
+            &self.project,
+            Some(project) if segment.project.as_ref() != Some(project)
+        );
+        let from_matches = !matches!(self.from, Some(from) if segment.timestamp < from);
+        let to_matches = !matches!(self.to, Some(to) if segment.timestamp >= to);
+        source_matches && target_matches && project_matches && from_matches && to_matches
+    }
+}
+
+/// A previous translation of similar text, given to LLM providers as an example.
+#[derive(Clone, Debug)]
+pub struct TranslationReference {
+    pub source_text: String,
+    pub target_text: String,
+}
+
+/// What to do with a fuzzy, rather than exact, match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuzzyMatchMode {
+    /// Return the stored translation along with its match score.
+    Return,
+    /// Translate as usual, giving the stored translation to LLM providers as a reference.
+    Reference,
+}
+
+/// A stored segment along with its normalized source text, shared between the lists of the
+/// index.
+struct StoredSegment {
+    segment: Segment,
+    normalized: Vec<char>,
+}
+
+/// The languages of a segment, its source language being unknown when it wasn't given or
+/// detected.
+type LanguagePair = (Option<isolang::Language>, isolang::Language);
+
+/// The segments in the order they were added, and by language pair then by the length of their
+/// normalized source text, so matches are only scored against segments that can be similar.
+#[derive(Default)]
+struct SegmentIndex {
+    segments: Vec<Arc<StoredSegment>>,
+    by_languages: HashMap<LanguagePair, BTreeMap<usize, Vec<Arc<StoredSegment>>>>,
+}
+
+impl SegmentIndex {
+    /// Add `segment`, which matches inputs with its source language and, when that was
+    /// detected, inputs without one too.
+    fn insert(&mut self, segment: Segment) {
+        let source = segment.source_language.as_ref().map(|language| language.0);
+        let target = segment.target_language.0;
+        let mut sources = vec![source];
+        if segment.source_detected && source.is_some() {
+            sources.push(None);
+        }
+        let normalized = normalize(&segment.source_text).chars().collect_vec();
+        let stored = Arc::new(StoredSegment {
+            segment,
+            normalized,
+        });
+        for source in sources {
+            self.by_languages
+                .entry((source, target))
+                .or_default()
+                .entry(stored.normalized.len())
+                .or_default()
+                .push(stored.clone());
+        }
+        self.segments.push(stored);
+    }
+
+    /// The segments translated between `languages` whose normalized source text has a length
+    /// within `lengths`.
+    fn candidates(
+        &self,
+        languages: &LanguagePair,
+        lengths: RangeInclusive<usize>,
+    ) -> Vec<Arc<StoredSegment>> {
+        self.by_languages
+            .get(languages)
+            .into_iter()
+            .flat_map(|by_length| by_length.range(lengths.clone()))
+            .flat_map(|(_, segments)| segments.iter().cloned())
+            .collect_vec()
+    }
+}
+
+pub struct TranslationMemory {
+    segments: RwLock<SegmentIndex>,
+    file: Mutex<File>,
+    fuzzy_threshold: f32,
+    fuzzy_match_mode: FuzzyMatchMode,
+}
+
+struct MemoryMatch {
+    segment: Segment,
+    score: f32,
+}
+
+/// Seconds since the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// The highest score of a segment whose source text isn't exactly the text, such as one that
+/// only differs in case or whitespace, which can't stand in for its translation as is.
+const MAX_FUZZY_SCORE: f32 = 0.99;
+
+pub(crate) fn normalize(text: &str) -> String {
+    text.split_whitespace().join(" ").to_lowercase()
+}
+
+fn edit_distance(first: &[char], second: &[char]) -> usize {
+    let mut previous = (0..=second.len()).collect_vec();
+    let mut current = vec![0; second.len() + 1];
+    for (i, first_char) in first.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, second_char) in second.iter().enumerate() {
+            let substitution = previous[j] + usize::from(first_char != second_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[second.len()]
+}
+
+/// Similarity between 0 and 1 of two normalized texts, based on their edit distance.
+pub(crate) fn similarity(first: &[char], second: &[char]) -> f32 {
+    let longest = first.len().max(second.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(first, second) as f32 / longest as f32
+}
+
+/// The lengths a text of `length` characters can be compared to and reach `threshold`, as the
+/// length difference alone bounds the similarity.
+fn similar_lengths(length: usize, threshold: f32) -> RangeInclusive<usize> {
+    if threshold <= 0.0 {
+        return 0..=usize::MAX;
+    }
+    // rounded outwards, the similarity itself is checked anyway
+    let min = (length as f32 * threshold).floor() as usize;
+    let max = (length.max(1) as f32 / threshold).ceil() as usize;
+    min..=max
+}
+
+impl TranslationMemory {
+    pub fn open(
+        path: &Path,
+        fuzzy_threshold: f32,
+        fuzzy_match_mode: FuzzyMatchMode,
+    ) -> Result<Self, TranslationMemoryError> {
+        if !(0.0..=1.0).contains(&fuzzy_threshold) {
+            return Err(TranslationMemoryError::InvalidFuzzyThresholdConfig);
+        }
+
+        let mut segments = SegmentIndex::default();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    segments.insert(serde_json::from_str::<Segment>(&line)?);
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(TranslationMemory {
+            segments: RwLock::new(segments),
+            file: Mutex::new(file),
+            fuzzy_threshold,
+            fuzzy_match_mode,
+        })
+    }
+
+    pub fn add(&self, segments: Vec<Segment>) -> Result<(), TranslationMemoryError> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        for segment in &segments {
+            lines.push_str(&serde_json::to_string(segment)?);
+            lines.push('\n');
+        }
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| TranslationMemoryError::LockPoisoned)?;
+        file.write_all(lines.as_bytes())?;
+        let mut index = self
+            .segments
+            .write()
+            .map_err(|_| TranslationMemoryError::LockPoisoned)?;
+        for segment in segments {
+            index.insert(segment);
+        }
+        Ok(())
+    }
+
+    pub fn segments(&self, filter: &SegmentFilter) -> Result<Vec<Segment>, TranslationMemoryError> {
+        Ok(self
+            .segments
+            .read()
+            .map_err(|_| TranslationMemoryError::LockPoisoned)?
+            .segments
+            .iter()
+            .filter(|stored| filter.matches(&stored.segment))
+            .map(|stored| stored.segment.clone())
+            .collect_vec())
+    }
+
+    /// The most similar segment to `input` with the same languages and context. Only a segment
+    /// with exactly the same source text scores 1. Segments are only scored once the lock is
+    /// released, so new translations can be added meanwhile.
+    fn find_best_match(&self, input: &TranslationInput) -> Option<MemoryMatch> {
+        let text = normalize(&input.text).chars().collect_vec();
+        let languages = (
+            input.source_language.as_ref().map(|language| language.0),
+            input.target_language.0,
+        );
+        let candidates = self.segments.read().ok()?.candidates(
+            &languages,
+            similar_lengths(text.len(), self.fuzzy_threshold),
+        );
+        candidates
+            .iter()
+            .filter(|stored| stored.segment.context == input.context)
+            .filter_map(|stored| {
+                let score = match stored.segment.source_text == input.text {
+                    true => 1.0,
+                    false => similarity(&text, &stored.normalized).min(MAX_FUZZY_SCORE),
+                };
+                (score >= self.fuzzy_threshold).then_some((&stored.segment, score))
+            })
+            .max_by(|(first, first_score), (second, second_score)| {
+                let first_same_project = first.project == input.project;
+                let second_same_project = second.project == input.project;
+                first_score
+                    .total_cmp(second_score)
+                    .then(first_same_project.cmp(&second_same_project))
+                    .then(first.timestamp.cmp(&second.timestamp))
+            })
+            .map(|(segment, score)| MemoryMatch {
+                segment: segment.clone(),
+                score,
+            })
+    }
+
+    /// Translate `inputs`, answering from memory where possible and using `provider` for the
+    /// rest. New translations from `provider` are added to the memory.
+    pub async fn translate_with<TP: Translation>(
+        &self,
+        provider: &TP,
+        provider_name: &str,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let (mut outputs, misses) = self.look_up(inputs);
+        let mut errors = Vec::new();
+
+        if !misses.is_empty() {
+            let (indexes, miss_inputs): (Vec<_>, Vec<_>) = misses.into_iter().unzip();
+            // the usable translations of a partial translation are kept too
+            let (translations, miss_errors) =
+                partial_outputs(provider.translate(miss_inputs.clone()).await)?;
+            self.add(Self::new_segments(
+                provider_name,
+                miss_inputs
+                    .into_iter()
+                    .zip(translations.iter())
+                    .filter_map(|(input, output)| Some((input, output.as_ref()?))),
+            ))?;
+
+            errors = miss_errors
+                .into_iter()
+                .map(|error| InvalidTranslation {
+                    index: indexes[error.index],
+                    ..error
+                })
+                .collect_vec();
+            for (index, translation) in indexes.into_iter().zip(translations) {
+                outputs[index] = translation;
+            }
+        }
+
+        complete_outputs(outputs, errors)
+    }
+
+    /// Like [Self::translate_with], sending each output as soon as it's done. Answers from
+    /// memory are sent straight away.
+    pub async fn translate_streaming_with<TP: StreamingTranslation>(
+        &self,
+        provider: &TP,
+        provider_name: &str,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Result<Vec<Option<TranslationOutput>>, TranslationError> {
+        let (mut outputs, misses) = self.look_up(inputs);
+        for (index, output) in outputs.iter().enumerate() {
+            if let Some(output) = output {
+                events.output(index, output).await;
+            }
+        }
+
+        if !misses.is_empty() {
+            let (indexes, miss_inputs): (Vec<_>, Vec<_>) = misses.into_iter().unzip();
+            let translations = provider
+                .translate_streaming(miss_inputs.clone(), &events.for_indexes(indexes.clone()))
+                .await;
+            self.add(Self::new_segments(
+                provider_name,
+                miss_inputs
+                    .into_iter()
+                    .zip(translations.iter())
+                    .filter_map(|(input, output)| Some((input, output.as_ref()?))),
+            ))?;
+
+            for (index, translation) in indexes.into_iter().zip(translations) {
+                outputs[index] = translation;
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Like [Self::translate_with], for groups of inputs that are each the same text in
+    /// several languages, see [MultiTargetTranslation].
+    pub async fn translate_targets_with<TP: MultiTargetTranslation>(
+        &self,
+        provider: &TP,
+        provider_name: &str,
+        groups: Vec<Vec<TranslationInput>>,
+    ) -> Result<Vec<Vec<TranslationOutput>>, TranslationError> {
+        let sizes = groups.iter().map(Vec::len).collect_vec();
+        let group_of_index = sizes
+            .iter()
+            .enumerate()
+            .flat_map(|(group, size)| std::iter::repeat_n(group, *size))
+            .collect_vec();
+        let (mut outputs, misses) = self.look_up(groups.into_iter().flatten().collect_vec());
+
+        if !misses.is_empty() {
+            let (indexes, miss_groups): (Vec<Vec<_>>, Vec<Vec<_>>) = misses
+                .into_iter()
+                .chunk_by(|(index, _)| group_of_index[*index])
+                .into_iter()
+                .map(|(_, misses)| misses.unzip())
+                .unzip();
+            let indexes = indexes.into_iter().flatten().collect_vec();
+            let translations = provider.translate_targets(miss_groups.clone()).await?;
+            let translations = expect_outputs(
+                translations.into_iter().flatten().collect_vec(),
+                indexes.len(),
+            )?;
+            self.add(Self::new_segments(
+                provider_name,
+                miss_groups.into_iter().flatten().zip(translations.iter()),
+            ))?;
+
+            for (index, translation) in indexes.into_iter().zip(translations) {
+                outputs[index] = Some(translation);
+            }
+        }
+
+        Ok(regroup(outputs.into_iter().flatten().collect_vec(), &sizes))
+    }
+
+    /// The outputs of `inputs` that can be answered from memory, and the rest along with
+    /// their index.
+    fn look_up(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> (
+        Vec<Option<TranslationOutput>>,
+        Vec<(usize, TranslationInput)>,
+    ) {
+        let mut outputs = Vec::<Option<TranslationOutput>>::with_capacity(inputs.len());
+        let mut misses = Vec::<(usize, TranslationInput)>::new();
+        for (index, mut input) in inputs.into_iter().enumerate() {
+            let memory_match = self.find_best_match(&input);
+            match memory_match {
+                Some(memory_match)
+                    if memory_match.score >= 1.0
+                        || self.fuzzy_match_mode == FuzzyMatchMode::Return =>
+                {
+                    outputs.push(Some(TranslationOutput {
+                        text: memory_match.segment.target_text,
+                        source_language: memory_match.segment.source_language,
+                        match_score: Some(memory_match.score),
+                        alternatives: Vec::new(),
+                        quality: None,
+                        tier: None,
+                        ensemble: None,
+                    }));
+                }
+                Some(memory_match) => {
+                    input.references.push(TranslationReference {
+                        source_text: memory_match.segment.source_text,
+                        target_text: memory_match.segment.target_text,
+                    });
+                    outputs.push(None);
+                    misses.push((index, input));
+                }
+                None => {
+                    outputs.push(None);
+                    misses.push((index, input));
+                }
+            }
+        }
+        (outputs, misses)
+    }
+
+    fn new_segments<'a>(
+        provider_name: &str,
+        translations: impl Iterator<Item = (TranslationInput, &'a TranslationOutput)>,
+    ) -> Vec<Segment> {
+        let timestamp = now();
+        translations
+            .map(|(input, output)| Segment {
+                source_text: input.text,
+                target_text: output.text.clone(),
+                source_detected: input.source_language.is_none()
+                    && output.source_language.is_some(),
+                source_language: input.source_language.or(output.source_language.clone()),
+                target_language: input.target_language,
+                provider: provider_name.to_owned(),
+                timestamp,
+                project: input.project,
+                context: input.context,
+            })
+            .collect_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::{
+        edit_distance, normalize, similar_lengths, similarity, FuzzyMatchMode, TranslationMemory,
+    };
+    use crate::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::stream::{TranslationEvent, TranslationEvents};
+    use crate::{TranslationInput, TranslationOutput};
+    use futures::executor::block_on;
+    use itertools::Itertools;
+    use std::collections::HashMap;
+    use tokio::sync::mpsc::channel;
+
+    #[test]
+    fn it_should_calculate_edit_distance() {
+        let kitten = "kitten".chars().collect_vec();
+        let sitting = "sitting".chars().collect_vec();
+        assert_eq!(edit_distance(&kitten, &sitting), 3);
+        assert_eq!(edit_distance(&kitten, &kitten), 0);
+        assert_eq!(edit_distance(&[], &sitting), 7);
+    }
+
+    #[test]
+    fn it_should_score_normalized_similarity() {
+        let first = normalize("Save  the file").chars().collect_vec();
+        let second = normalize("save the file").chars().collect_vec();
+        assert_eq!(similarity(&first, &second), 1.0);
+
+        let third = normalize("Save the files").chars().collect_vec();
+        assert!(similarity(&first, &third) > 0.9);
+    }
+
+    #[test]
+    fn it_should_answer_from_memory() {
+        let path = std::env::temp_dir().join(format!("memory-{}.jsonl", std::process::id()));
+        let memory = TranslationMemory::open(&path, 0.8, FuzzyMatchMode::Return).unwrap();
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            HashMap::from([(
+                "Save the file".to_owned(),
+                "Enregistrer le fichier".to_owned(),
+            )]),
+        )]);
+        let provider = MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        );
+
+        let first = block_on(memory.translate_with(
+            &provider,
+            "mock",
+            vec![TranslationInput::test("Save the file", "fr")],
+        ))
+        .unwrap();
+        assert_eq!(first[0].match_score, None);
+
+        let echo =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        let reopened = TranslationMemory::open(&path, 0.8, FuzzyMatchMode::Return).unwrap();
+        let second = block_on(reopened.translate_with(
+            &echo,
+            "mock",
+            vec![
+                TranslationInput::test("save the file", "fr"),
+                TranslationInput::test("Save the files", "fr"),
+                TranslationInput::test("Open", "fr"),
+            ],
+        ))
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(second[0].text, "Enregistrer le fichier");
+        assert!(second[0].match_score.unwrap() < 1.0);
+        assert_eq!(second[1].text, "Enregistrer le fichier");
+        assert!(second[1].match_score.unwrap() < 1.0);
+        assert_eq!(second[2].text, "Open");
+        assert_eq!(second[2].match_score, None);
+    }
+
+    #[test]
+    fn it_should_stream_answers_from_memory_and_provider() {
+        let path = std::env::temp_dir().join(format!("memory-stream-{}.jsonl", std::process::id()));
+        let memory = TranslationMemory::open(&path, 1.0, FuzzyMatchMode::Return).unwrap();
+        let echo =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        block_on(memory.translate_with(&echo, "mock", vec![TranslationInput::test("Open", "fr")]))
+            .unwrap();
+
+        let (sender, mut receiver) = channel(16);
+        let outputs = block_on(memory.translate_streaming_with(
+            &echo,
+            "mock",
+            vec![
+                TranslationInput::test("Close", "fr"),
+                TranslationInput::test("Open", "fr"),
+            ],
+            &TranslationEvents::new(sender),
+        ))
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(outputs[1].as_ref().unwrap().match_score, Some(1.0));
+        let indexes = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|event| match event {
+                TranslationEvent::Output { index, .. } => index,
+                _ => panic!("Unexpected event {event:?}"),
+            })
+            .collect_vec();
+        assert_eq!(indexes, vec![1, 0]);
+    }
+
+    #[test]
+    fn it_should_only_answer_from_memory_with_the_same_context() {
+        let path =
+            std::env::temp_dir().join(format!("memory-context-{}.jsonl", std::process::id()));
+        let memory = TranslationMemory::open(&path, 0.8, FuzzyMatchMode::Return).unwrap();
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            HashMap::from([("Post".to_owned(), "Publier".to_owned())]),
+        )]);
+        let provider = MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        );
+        let verb = TranslationInput::test("Post", "fr").with_context(Some("verb".to_owned()));
+        block_on(memory.translate_with(&provider, "mock", vec![verb.clone()])).unwrap();
+
+        let echo =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        let noun = TranslationInput::test("Post", "fr").with_context(Some("noun".to_owned()));
+        let outputs = block_on(memory.translate_with(&echo, "mock", vec![verb, noun])).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(outputs[0].text, "Publier");
+        assert_eq!(outputs[0].match_score, Some(1.0));
+        assert_eq!(outputs[1].text, "Post");
+        assert_eq!(outputs[1].match_score, None);
+    }
+
+    #[test]
+    fn it_should_only_compare_texts_of_similar_length() {
+        assert_eq!(similar_lengths(10, 0.8), 8..=13);
+        assert_eq!(similar_lengths(0, 0.5), 0..=2);
+        assert_eq!(similar_lengths(10, 0.0), 0..=usize::MAX);
+    }
+
+    #[test]
+    fn it_should_only_answer_from_memory_with_the_same_languages() {
+        let path =
+            std::env::temp_dir().join(format!("memory-languages-{}.jsonl", std::process::id()));
+        let memory = TranslationMemory::open(&path, 0.8, FuzzyMatchMode::Return).unwrap();
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            HashMap::from([("Save".to_owned(), "Enregistrer".to_owned())]),
+        )]);
+        let provider = MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        );
+        block_on(memory.translate_with(
+            &provider,
+            "mock",
+            vec![TranslationInput::test("Save", "fr")],
+        ))
+        .unwrap();
+
+        let echo =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        let unknown_source = serde_json::from_value::<TranslationInput>(
+            serde_json::json!({ "input": "Save", "target": "fr" }),
+        )
+        .unwrap();
+        let other_source = serde_json::from_value::<TranslationInput>(
+            serde_json::json!({ "input": "Save", "source": "de", "target": "fr" }),
+        )
+        .unwrap();
+        let outputs = block_on(memory.translate_with(
+            &echo,
+            "mock",
+            vec![
+                TranslationInput::test("Save", "fr"),
+                unknown_source,
+                other_source,
+            ],
+        ))
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            outputs
+                .iter()
+                .map(|output| (output.text.as_str(), output.match_score))
+                .collect_vec(),
+            vec![("Enregistrer", Some(1.0)), ("Save", None), ("Save", None)]
+        );
+    }
+
+    #[test]
+    fn it_should_answer_inputs_without_a_source_from_detected_translations() {
+        let path =
+            std::env::temp_dir().join(format!("memory-detected-{}.jsonl", std::process::id()));
+        let memory = TranslationMemory::open(&path, 0.8, FuzzyMatchMode::Return).unwrap();
+        let unknown_source = serde_json::from_value::<TranslationInput>(
+            serde_json::json!({ "input": "Save", "target": "fr" }),
+        )
+        .unwrap();
+        let detected = serde_json::from_value::<TranslationOutput>(
+            serde_json::json!({ "output": "Enregistrer", "source": "en" }),
+        )
+        .unwrap();
+        memory
+            .add(TranslationMemory::new_segments(
+                "mock",
+                [(unknown_source.clone(), &detected)].into_iter(),
+            ))
+            .unwrap();
+
+        let echo =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        let outputs = block_on(memory.translate_with(
+            &echo,
+            "mock",
+            vec![unknown_source, TranslationInput::test("Save", "fr")],
+        ))
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(outputs[0].text, "Enregistrer");
+        assert_eq!(outputs[1].text, "Enregistrer");
+    }
+
+    #[test]
+    fn it_should_only_answer_exact_matches_of_the_same_text() {
+        let path = std::env::temp_dir().join(format!("memory-exact-{}.jsonl", std::process::id()));
+        let memory = TranslationMemory::open(&path, 0.5, FuzzyMatchMode::Reference).unwrap();
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            HashMap::from([
+                ("Save".to_owned(), "Enregistrer".to_owned()),
+                ("{count} files".to_owned(), "{count} fichiers".to_owned()),
+            ]),
+        )]);
+        let provider = MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        );
+        block_on(memory.translate_with(
+            &provider,
+            "mock",
+            vec![
+                TranslationInput::test("Save", "fr"),
+                TranslationInput::test("{count} files", "fr"),
+            ],
+        ))
+        .unwrap();
+
+        let echo =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        let outputs = block_on(memory.translate_with(
+            &echo,
+            "mock",
+            vec![
+                TranslationInput::test("Save", "fr"),
+                TranslationInput::test("SAVE", "fr"),
+                TranslationInput::test("{COUNT} files", "fr"),
+            ],
+        ))
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            outputs
+                .iter()
+                .map(|output| (output.text.as_str(), output.match_score))
+                .collect_vec(),
+            vec![
+                ("Enregistrer", Some(1.0)),
+                ("SAVE", None),
+                ("{COUNT} files", None)
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::alternatives::AlternativeTranslation;
+use crate::ensemble::{EnsembleCandidate, EnsembleJudge, EnsembleJudgement};
+use crate::llm::{
+    judge_candidates_with_llm, judge_quality_with_llm, translate_alternatives_with_llm,
+    translate_streaming_with_llm, translate_targets_with_llm, translate_with_llm, LLMClient,
+    LLMPrompt, LLMResponse,
+};
+use crate::multi::MultiTargetTranslation;
+use crate::quality::{QualityEstimate, QualityJudge};
+use crate::stream::{StreamingTranslation, TranslationEvents};
+use crate::{
+    HealthCheck, Translation, TranslationError, TranslationInput, TranslationOutput,
+    TranslationProvider,
+};
+use async_trait::async_trait;
+use mistral::client::{MistralClient, MistralCompletion};
+use mistral::error::MistralError;
+use mistral::model::MistralModel;
+
+#[derive(Clone)]
+pub struct MistralTranslationProvider {
+    mistral_client: MistralClient,
+}
+
+impl MistralTranslationProvider {
+    pub fn new(mistral_client: MistralClient) -> Self {
+        MistralTranslationProvider { mistral_client }
+    }
+
+    /// A provider of `model` that shares the limit on parallel requests of this one.
+    pub fn with_model(&self, model: MistralModel) -> Self {
+        MistralTranslationProvider::new(self.mistral_client.with_model(model))
+    }
+}
+
+impl From<MistralCompletion> for LLMResponse {
+    fn from(response: MistralCompletion) -> Self {
+        LLMResponse {
+            truncated: response.is_truncated(),
+            text: response.text,
+        }
+    }
+}
+
+impl LLMClient for MistralClient {
+    type Error = MistralError;
+
+    async fn respond_to(&self, prompt: LLMPrompt) -> Result<LLMResponse, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        MistralClient::respond_to(self, prompt.system_prompt, prompt.prompt, max_tokens)
+            .await
+            .map(LLMResponse::from)
+    }
+
+    async fn continue_response(
+        &self,
+        prompt: LLMPrompt,
+        partial: &str,
+    ) -> Result<Option<LLMResponse>, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        MistralClient::continue_response(
+            self,
+            prompt.system_prompt,
+            prompt.prompt,
+            partial,
+            max_tokens,
+        )
+        .await
+        .map(|response| Some(LLMResponse::from(response)))
+    }
+}
+
+#[async_trait]
+impl HealthCheck for MistralTranslationProvider {
+    async fn is_healthy(&self) -> Option<bool> {
+        Some(self.mistral_client.get_models().await.is_ok())
+    }
+}
+
+impl Translation for MistralTranslationProvider {
+    async fn translate(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        translate_with_llm(&self.mistral_client, inputs).await
+    }
+}
+
+impl StreamingTranslation for MistralTranslationProvider {
+    async fn translate_streaming(
+        &self,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Vec<Option<TranslationOutput>> {
+        translate_streaming_with_llm(&self.mistral_client, inputs, events).await
+    }
+}
+
+impl MultiTargetTranslation for MistralTranslationProvider {
+    async fn translate_targets(
+        &self,
+        groups: Vec<Vec<TranslationInput>>,
+    ) -> Result<Vec<Vec<TranslationOutput>>, TranslationError> {
+        translate_targets_with_llm(&self.mistral_client, groups).await
+    }
+}
+
+impl AlternativeTranslation for MistralTranslationProvider {
+    fn supports_alternatives(&self) -> bool {
+        true
+    }
+
+    async fn translate_alternatives(
+        &self,
+        inputs: Vec<TranslationInput>,
+        count: usize,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        translate_alternatives_with_llm(&self.mistral_client, inputs, count).await
+    }
+}
+
+impl QualityJudge for MistralTranslationProvider {
+    fn supports_quality_judging(&self) -> bool {
+        true
+    }
+
+    async fn judge_quality(
+        &self,
+        inputs: &[TranslationInput],
+        outputs: &[TranslationOutput],
+    ) -> Result<Vec<Option<QualityEstimate>>, TranslationError> {
+        judge_quality_with_llm(&self.mistral_client, inputs, outputs).await
+    }
+}
+
+impl EnsembleJudge for MistralTranslationProvider {
+    fn supports_ensemble_judging(&self) -> bool {
+        true
+    }
+
+    async fn judge_candidates(
+        &self,
+        inputs: &[TranslationInput],
+        candidates: &[Vec<EnsembleCandidate>],
+    ) -> Result<Vec<Option<EnsembleJudgement>>, TranslationError> {
+        judge_candidates_with_llm(&self.mistral_client, inputs, candidates).await
+    }
+}
+
+impl TranslationProvider for MistralTranslationProvider {}
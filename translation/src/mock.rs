@@ -0,0 +1,242 @@
+use crate::alternatives::AlternativeTranslation;
+use crate::ensemble::EnsembleJudge;
+use crate::multi::MultiTargetTranslation;
+use crate::pseudo::{pseudolocalize, PseudolocalizationOptions};
+use crate::quality::QualityJudge;
+use crate::stream::StreamingTranslation;
+use crate::{
+    HealthCheck, Translation, TranslationError, TranslationInput, TranslationOutput,
+    TranslationProvider,
+};
+use ::claude::error::ClaudeError;
+use ::deepl::error::DeepLError;
+use ::gemini::error::GeminiError;
+use ::mistral::error::MistralError;
+use ::openai::error::OpenAIError;
+use async_trait::async_trait;
+use itertools::Itertools;
+use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MockError {
+    #[error("IoError {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("SerdeJsonError {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+}
+
+/// Target language code to source text to translated text.
+pub type MockDictionary = HashMap<String, HashMap<String, String>>;
+
+pub enum MockMode {
+    Echo,
+    Pseudolocalize,
+    Dictionary(MockDictionary),
+}
+
+impl MockMode {
+    pub fn dictionary_from_file(path: &Path) -> Result<Self, MockError> {
+        let dictionary = serde_json::from_str::<MockDictionary>(&fs::read_to_string(path)?)?;
+        Ok(MockMode::Dictionary(dictionary))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
+pub enum MockFault {
+    #[serde(rename = "claude")]
+    Claude,
+    #[serde(rename = "deepl")]
+    DeepL,
+    #[serde(rename = "gemini")]
+    Gemini,
+    #[serde(rename = "mistral")]
+    Mistral,
+    #[serde(rename = "openai")]
+    OpenAI,
+}
+
+const INJECTED_FAULT: &str = "Injected fault";
+
+impl From<MockFault> for TranslationError {
+    fn from(fault: MockFault) -> Self {
+        let message = INJECTED_FAULT.to_owned();
+        match fault {
+            MockFault::Claude => ClaudeError::UnexpectedApiResponse(message).into(),
+            MockFault::DeepL => DeepLError::UnexpectedApiResponse(message).into(),
+            MockFault::Gemini => GeminiError::UnexpectedApiResponse(message).into(),
+            MockFault::Mistral => MistralError::UnexpectedApiResponse(message).into(),
+            MockFault::OpenAI => OpenAIError::UnexpectedApiResponse(message).into(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MockFaultInjection {
+    pub latency: Duration,
+    pub error_rate: f64,
+    pub faults: Vec<MockFault>,
+}
+
+pub struct MockTranslationProvider {
+    mode: MockMode,
+    fault_injection: MockFaultInjection,
+    healthy: AtomicBool,
+}
+
+impl MockTranslationProvider {
+    pub fn new(mode: MockMode, fault_injection: MockFaultInjection, healthy: bool) -> Self {
+        MockTranslationProvider {
+            mode,
+            fault_injection,
+            healthy: AtomicBool::new(healthy),
+        }
+    }
+
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::Relaxed);
+    }
+
+    /// Fail at the error rate with one of the faults, or with an error of no provider in
+    /// particular when there are none.
+    fn maybe_inject_fault(&self) -> Result<(), TranslationError> {
+        if rand::random::<f64>() >= self.fault_injection.error_rate {
+            return Ok(());
+        }
+        if self.fault_injection.faults.is_empty() {
+            return Err(TranslationError::UnexpectedResponseFormat(
+                INJECTED_FAULT.to_owned(),
+            ));
+        }
+        let fault_index = rand::random::<usize>() % self.fault_injection.faults.len();
+        Err(self.fault_injection.faults[fault_index].clone().into())
+    }
+
+    fn translate_text(&self, input: &TranslationInput) -> String {
+        match &self.mode {
+            MockMode::Echo => input.text.clone(),
+            MockMode::Pseudolocalize => {
+                pseudolocalize(&input.text, &PseudolocalizationOptions::default())
+            }
+            MockMode::Dictionary(dictionary) => dictionary
+                .get(&input.target_language.to_string())
+                .and_then(|translations| translations.get(&input.text))
+                .cloned()
+                .unwrap_or(input.text.clone()),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for MockTranslationProvider {
+    async fn is_healthy(&self) -> Option<bool> {
+        Some(self.healthy.load(Ordering::Relaxed))
+    }
+}
+
+impl Translation for MockTranslationProvider {
+    async fn translate(
+        &self,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        if !self.fault_injection.latency.is_zero() {
+            tokio::time::sleep(self.fault_injection.latency).await;
+        }
+        self.maybe_inject_fault()?;
+
+        Ok(inputs
+            .iter()
+            .map(|input| {
+                TranslationOutput::new(self.translate_text(input), input.source_language.clone())
+            })
+            .collect_vec())
+    }
+}
+
+impl StreamingTranslation for MockTranslationProvider {}
+
+impl MultiTargetTranslation for MockTranslationProvider {}
+
+impl AlternativeTranslation for MockTranslationProvider {}
+
+impl QualityJudge for MockTranslationProvider {}
+
+impl EnsembleJudge for MockTranslationProvider {}
+
+impl TranslationProvider for MockTranslationProvider {}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{MockFault, MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::{HealthCheck, Translation, TranslationError, TranslationInput};
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_should_look_up_dictionary_translations() {
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            HashMap::from([("Hello".to_owned(), "Bonjour".to_owned())]),
+        )]);
+        let provider = MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        );
+
+        let outputs = block_on(provider.translate(vec![
+            TranslationInput::test("Hello", "fr"),
+            TranslationInput::test("Goodbye", "fr"),
+        ]))
+        .unwrap();
+        assert_eq!(outputs[0].text, "Bonjour");
+        assert_eq!(outputs[1].text, "Goodbye");
+    }
+
+    #[test]
+    fn it_should_inject_faults() {
+        let provider = MockTranslationProvider::new(
+            MockMode::Echo,
+            MockFaultInjection {
+                error_rate: 1.0,
+                faults: vec![MockFault::DeepL],
+                ..MockFaultInjection::default()
+            },
+            true,
+        );
+
+        let result = block_on(provider.translate(vec![TranslationInput::test("Hello", "fr")]));
+        assert!(matches!(result, Err(TranslationError::DeepLError(_))));
+    }
+
+    #[test]
+    fn it_should_inject_faults_at_the_error_rate_alone() {
+        let provider = MockTranslationProvider::new(
+            MockMode::Echo,
+            MockFaultInjection {
+                error_rate: 1.0,
+                ..MockFaultInjection::default()
+            },
+            true,
+        );
+
+        let result = block_on(provider.translate(vec![TranslationInput::test("Hello", "fr")]));
+        assert!(matches!(
+            result,
+            Err(TranslationError::UnexpectedResponseFormat(_))
+        ));
+    }
+
+    #[test]
+    fn it_should_control_health() {
+        let provider =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        provider.set_healthy(false);
+        assert_eq!(block_on(provider.is_healthy()), Some(false));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{Language, Translation, TranslationError, TranslationInput, TranslationOutput};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A text to translate into several languages.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MultiTargetInput {
+    #[serde(rename = "input")]
+    text: String,
+    #[serde(rename = "source")]
+    source_language: Option<Language>,
+    /// Falls back to the targets of the whole request when empty.
+    #[serde(rename = "targets", default)]
+    target_languages: Vec<Language>,
+    project: Option<String>,
+    context: Option<String>,
+    description: Option<String>,
+    screen: Option<String>,
+}
+
+impl MultiTargetInput {
+    /// An input for each of the target languages, or of `default_targets` when the input
+    /// has none.
+    pub fn into_inputs(self, default_targets: &[Language]) -> Vec<TranslationInput> {
+        let targets = if self.target_languages.is_empty() {
+            default_targets
+        } else {
+            &self.target_languages
+        };
+        targets
+            .iter()
+            .map(|target| TranslationInput {
+                project: self.project.clone(),
+                context: self.context.clone(),
+                description: self.description.clone(),
+                screen: self.screen.clone(),
+                ..TranslationInput::new(
+                    self.text.clone(),
+                    self.source_language.clone(),
+                    target.clone(),
+                )
+            })
+            .collect_vec()
+    }
+}
+
+pub(crate) fn regroup<T>(items: Vec<T>, sizes: &[usize]) -> Vec<Vec<T>> {
+    let mut items = items.into_iter();
+    sizes
+        .iter()
+        .map(|size| items.by_ref().take(*size).collect_vec())
+        .collect_vec()
+}
+
+#[allow(async_fn_in_trait)]
+pub trait MultiTargetTranslation: Translation {
+    /// Translate groups of inputs, each group the same text in different target languages,
+    /// returning the outputs in the same groups.
+    ///
+    /// By default all the inputs are translated together, which suits providers that batch
+    /// the inputs of each target language, like DeepL.
+    async fn translate_targets(
+        &self,
+        groups: Vec<Vec<TranslationInput>>,
+    ) -> Result<Vec<Vec<TranslationOutput>>, TranslationError> {
+        let sizes = groups.iter().map(Vec::len).collect_vec();
+        let outputs = self
+            .translate(groups.into_iter().flatten().collect_vec())
+            .await?;
+        Ok(regroup(outputs, &sizes))
+    }
+}
+
+/// The output of one target language of a [MultiTargetInput].
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetOutput {
+    /// Of the input that was translated, as not every input has every target.
+    pub index: usize,
+    #[serde(flatten)]
+    pub output: TranslationOutput,
+}
+
+/// The outputs of each target language, in the order of the inputs that asked for it.
+pub fn group_by_target(
+    groups: &[Vec<TranslationInput>],
+    outputs: Vec<Vec<TranslationOutput>>,
+) -> BTreeMap<String, Vec<TargetOutput>> {
+    let mut by_target = BTreeMap::<String, Vec<TargetOutput>>::new();
+    for (index, (inputs, outputs)) in groups.iter().zip(outputs).enumerate() {
+        for (input, output) in inputs.iter().zip(outputs) {
+            by_target
+                .entry(input.target_language.to_string())
+                .or_default()
+                .push(TargetOutput { index, output });
+        }
+    }
+    by_target
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::multi::{group_by_target, MultiTargetInput, MultiTargetTranslation};
+    use crate::Language;
+    use futures::executor::block_on;
+    use itertools::Itertools;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_should_give_the_input_of_each_output_when_targets_differ() {
+        let inputs = serde_json::from_value::<Vec<MultiTargetInput>>(serde_json::json!([
+            { "input": "Yes", "source": "en", "targets": ["es"] },
+            { "input": "No", "source": "en" },
+            { "input": "Maybe", "source": "en", "targets": ["fr", "es"] }
+        ]))
+        .unwrap();
+        let default_targets = [Language::from_str("fr").unwrap()];
+        let groups = inputs
+            .into_iter()
+            .map(|input| input.into_inputs(&default_targets))
+            .collect_vec();
+
+        let provider =
+            MockTranslationProvider::new(MockMode::Echo, MockFaultInjection::default(), true);
+        let outputs = block_on(provider.translate_targets(groups.clone())).unwrap();
+        let by_target = group_by_target(&groups, outputs);
+
+        let indexes = |target: &str| {
+            by_target[target]
+                .iter()
+                .map(|output| (output.index, output.output.text().to_owned()))
+                .collect_vec()
+        };
+        assert_eq!(
+            indexes("es"),
+            vec![(0, "Yes".to_owned()), (2, "Maybe".to_owned())]
+        );
+        assert_eq!(
+            indexes("fr"),
+            vec![(1, "No".to_owned()), (2, "Maybe".to_owned())]
+        );
+    }
+
+    #[test]
+    fn it_should_translate_into_each_target() {
+        let inputs = serde_json::from_value::<Vec<MultiTargetInput>>(serde_json::json!([
+            { "input": "Hello", "source": "en", "context": "greeting" },
+            { "input": "Bye", "source": "en", "targets": ["de"] }
+        ]))
+        .unwrap();
+        let default_targets = ["fr", "de"].map(|code| Language::from_str(code).unwrap());
+        let groups = inputs
+            .into_iter()
+            .map(|input| input.into_inputs(&default_targets))
+            .collect_vec();
+        assert_eq!(groups[0][1].context(), Some("greeting"));
+
+        let dictionary = HashMap::from([
+            (
+                "fr".to_owned(),
+                HashMap::from([("Hello".to_owned(), "Bonjour".to_owned())]),
+            ),
+            (
+                "de".to_owned(),
+                HashMap::from([
+                    ("Hello".to_owned(), "Hallo".to_owned()),
+                    ("Bye".to_owned(), "Tschüss".to_owned()),
+                ]),
+            ),
+        ]);
+        let provider = MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        );
+        let outputs = block_on(provider.translate_targets(groups.clone())).unwrap();
+        let by_target = group_by_target(&groups, outputs);
+
+        let texts = |target: &str| {
+            by_target[target]
+                .iter()
+                .map(|output| (output.index, output.output.text().to_owned()))
+                .collect_vec()
+        };
+        assert_eq!(texts("fr"), vec![(0, "Bonjour".to_owned())]);
+        assert_eq!(
+            texts("de"),
+            vec![(0, "Hallo".to_owned()), (1, "Tschüss".to_owned())]
+        );
+    }
+}
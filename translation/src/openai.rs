@@ -1,13 +1,23 @@
+use crate::alternatives::AlternativeTranslation;
+use crate::ensemble::{EnsembleCandidate, EnsembleJudge, EnsembleJudgement};
+use crate::llm::{
+    judge_candidates_with_llm, judge_quality_with_llm, translate_alternatives_with_llm,
+    translate_streaming_with_llm, translate_targets_with_llm, translate_with_llm, LLMClient,
+    LLMPrompt, LLMResponse,
+};
+use crate::multi::MultiTargetTranslation;
+use crate::quality::{QualityEstimate, QualityJudge};
+use crate::stream::{StreamingTranslation, TranslationEvents};
 use crate::{
-    HealthCheck, Language, Translation, TranslationError, TranslationInput, TranslationOutput,
+    HealthCheck, Translation, TranslationError, TranslationInput, TranslationOutput,
     TranslationProvider,
 };
 use async_trait::async_trait;
-use futures::future::join_all;
-use itertools::Itertools;
-use openai::client::OpenAIClient;
+use openai::client::{OpenAIClient, OpenAICompletion, OpenAIResponseSchema};
 use openai::error::OpenAIError;
+use openai::model::OpenAIModel;
 
+#[derive(Clone)]
 pub struct OpenAITranslationProvider {
     open_ai_client: OpenAIClient,
 }
@@ -16,6 +26,83 @@ impl OpenAITranslationProvider {
     pub fn new(open_ai_client: OpenAIClient) -> Self {
         OpenAITranslationProvider { open_ai_client }
     }
+
+    /// A provider of `model` that shares the limit on parallel requests of this one.
+    pub fn with_model(&self, model: OpenAIModel) -> Self {
+        OpenAITranslationProvider::new(self.open_ai_client.with_model(model))
+    }
+}
+
+impl From<OpenAICompletion> for LLMResponse {
+    fn from(response: OpenAICompletion) -> Self {
+        LLMResponse {
+            truncated: response.is_truncated(),
+            text: response.text,
+        }
+    }
+}
+
+impl LLMClient for OpenAIClient {
+    type Error = OpenAIError;
+
+    async fn respond_to(&self, prompt: LLMPrompt) -> Result<LLMResponse, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        match prompt.response_schema {
+            Some(schema) => {
+                let schema = OpenAIResponseSchema {
+                    name: schema.name.to_owned(),
+                    schema: schema.schema,
+                };
+                OpenAIClient::respond_with_schema(
+                    self,
+                    prompt.system_prompt,
+                    prompt.prompt,
+                    schema,
+                    max_tokens,
+                )
+                .await
+            }
+            None => {
+                OpenAIClient::respond_to(self, prompt.system_prompt, prompt.prompt, max_tokens)
+                    .await
+            }
+        }
+        .map(LLMResponse::from)
+    }
+
+    async fn respond_to_streaming(
+        &self,
+        prompt: LLMPrompt,
+        on_delta: impl FnMut(&str),
+    ) -> Result<LLMResponse, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        OpenAIClient::respond_to_streaming(
+            self,
+            prompt.system_prompt,
+            prompt.prompt,
+            max_tokens,
+            on_delta,
+        )
+        .await
+        .map(LLMResponse::from)
+    }
+
+    async fn continue_response(
+        &self,
+        prompt: LLMPrompt,
+        partial: &str,
+    ) -> Result<Option<LLMResponse>, Self::Error> {
+        let max_tokens = Some(self.max_tokens_for(prompt.expected_characters));
+        OpenAIClient::continue_response(
+            self,
+            prompt.system_prompt,
+            prompt.prompt,
+            partial,
+            max_tokens,
+        )
+        .await
+        .map(|response| Some(LLMResponse::from(response)))
+    }
 }
 
 #[async_trait]
@@ -30,31 +117,68 @@ impl Translation for OpenAITranslationProvider {
         &self,
         inputs: Vec<TranslationInput>,
     ) -> Result<Vec<TranslationOutput>, TranslationError> {
-        Ok(join_all(
-            inputs
-                .into_iter()
-                .map(|input| {
-                    let from_source = format!(
-                        " from {}",
-                        input.source_language.map(|source| Language::to_string(&source)).unwrap_or("".to_owned())
-                    );
-                    let system_prompt = format!(
-                        "Please translate the user's text{from_source} to {}, only respond with the translation",
-                        input.target_language
-                    );
-                    self.open_ai_client.respond_to(system_prompt, input.text, None)
-                })
-                .collect_vec(),
-        )
-        .await
-        .into_iter()
-        .collect::<Result<Vec<String>, OpenAIError>>()?
-        .into_iter()
-        .map(|response| TranslationOutput {
-            text: response,
-            source_language: None,
-        })
-        .collect())
+        translate_with_llm(&self.open_ai_client, inputs).await
+    }
+}
+
+impl StreamingTranslation for OpenAITranslationProvider {
+    async fn translate_streaming(
+        &self,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Vec<Option<TranslationOutput>> {
+        translate_streaming_with_llm(&self.open_ai_client, inputs, events).await
+    }
+}
+
+impl MultiTargetTranslation for OpenAITranslationProvider {
+    async fn translate_targets(
+        &self,
+        groups: Vec<Vec<TranslationInput>>,
+    ) -> Result<Vec<Vec<TranslationOutput>>, TranslationError> {
+        translate_targets_with_llm(&self.open_ai_client, groups).await
+    }
+}
+
+impl AlternativeTranslation for OpenAITranslationProvider {
+    fn supports_alternatives(&self) -> bool {
+        true
+    }
+
+    async fn translate_alternatives(
+        &self,
+        inputs: Vec<TranslationInput>,
+        count: usize,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        translate_alternatives_with_llm(&self.open_ai_client, inputs, count).await
+    }
+}
+
+impl QualityJudge for OpenAITranslationProvider {
+    fn supports_quality_judging(&self) -> bool {
+        true
+    }
+
+    async fn judge_quality(
+        &self,
+        inputs: &[TranslationInput],
+        outputs: &[TranslationOutput],
+    ) -> Result<Vec<Option<QualityEstimate>>, TranslationError> {
+        judge_quality_with_llm(&self.open_ai_client, inputs, outputs).await
+    }
+}
+
+impl EnsembleJudge for OpenAITranslationProvider {
+    fn supports_ensemble_judging(&self) -> bool {
+        true
+    }
+
+    async fn judge_candidates(
+        &self,
+        inputs: &[TranslationInput],
+        candidates: &[Vec<EnsembleCandidate>],
+    ) -> Result<Vec<Option<EnsembleJudgement>>, TranslationError> {
+        judge_candidates_with_llm(&self.open_ai_client, inputs, candidates).await
     }
 }
 
@@ -0,0 +1,161 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Length in bytes of a placeholder or markup at the start of `text`, if there is one.
+/// Recognises HTML tags and entities, brace placeholders (`{name}`, `{{name}}`) and printf
+/// specifiers (`%s`, `%1$d`, `%@`), including Apple's stringsdict variables (`%#@files@`).
+fn placeholder_length(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    match bytes.first()? {
+        b'<' => {
+            let is_tag = matches!(bytes.get(1), Some(next) if next.is_ascii_alphabetic() || *next == b'/' || *next == b'!');
+            if is_tag {
+                text.find('>').map(|end| end + 1)
+            } else {
+                None
+            }
+        }
+        b'&' => {
+            let end = text.find(';')?;
+            let entity = &text[1..end];
+            let is_entity = !entity.is_empty()
+                && entity.len() <= 10
+                && entity
+                    .chars()
+                    .enumerate()
+                    .all(|(index, c)| c.is_ascii_alphanumeric() || (index == 0 && c == '#'));
+            is_entity.then_some(end + 1)
+        }
+        b'{' => {
+            let mut depth = 0;
+            for (index, character) in text.char_indices() {
+                match character {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(index + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            None
+        }
+        b'%' => {
+            if let Some(variable) = text.strip_prefix("%#@") {
+                let end = variable.find('@')?;
+                let is_variable = end > 0
+                    && variable[..end]
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if is_variable {
+                    return Some(end + 4);
+                }
+            }
+            let mut index = 1;
+            if bytes.get(index) == Some(&b'%') {
+                return Some(2);
+            }
+            while matches!(bytes.get(index), Some(b'0'..=b'9')) {
+                index += 1;
+            }
+            if index > 1 && bytes.get(index) == Some(&b'$') {
+                index += 1;
+            } else {
+                index = 1;
+            }
+            while matches!(
+                bytes.get(index),
+                Some(b'-' | b'+' | b'#' | b'0'..=b'9' | b'.')
+            ) {
+                index += 1;
+            }
+            while matches!(bytes.get(index), Some(b'l' | b'h' | b'z')) {
+                index += 1;
+            }
+            match bytes.get(index) {
+                Some(
+                    b'@' | b'd' | b'i' | b'u' | b'f' | b'e' | b'g' | b'x' | b'X' | b'o' | b'c'
+                    | b's' | b'S' | b'p',
+                ) => Some(index + 1),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Split `text` into translatable text and placeholders or markup that must be preserved.
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut index = 0;
+    while index < text.len() {
+        match placeholder_length(&text[index..]) {
+            Some(length) => {
+                if text_start < index {
+                    tokens.push(Token::Text(&text[text_start..index]));
+                }
+                tokens.push(Token::Placeholder(&text[index..index + length]));
+                index += length;
+                text_start = index;
+            }
+            None => {
+                index += text[index..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+    }
+    if text_start < text.len() {
+        tokens.push(Token::Text(&text[text_start..]));
+    }
+    tokens
+}
+
+/// The placeholders and markup in `text`, in order.
+pub fn placeholders(text: &str) -> Vec<&str> {
+    tokenize(text)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Placeholder(placeholder) => Some(placeholder),
+            Token::Text(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::placeholder::{placeholders, tokenize, Token};
+
+    #[test]
+    fn it_should_tokenize_placeholders_and_markup() {
+        assert_eq!(
+            tokenize("Hi <b>{name}</b>, you have %1$d new &amp; %@ 100% done %#@files@"),
+            vec![
+                Token::Text("Hi "),
+                Token::Placeholder("<b>"),
+                Token::Placeholder("{name}"),
+                Token::Placeholder("</b>"),
+                Token::Text(", you have "),
+                Token::Placeholder("%1$d"),
+                Token::Text(" new "),
+                Token::Placeholder("&amp;"),
+                Token::Text(" "),
+                Token::Placeholder("%@"),
+                Token::Text(" 100% done "),
+                Token::Placeholder("%#@files@"),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_find_nested_brace_placeholders() {
+        assert_eq!(
+            placeholders("{{count}} items, a < b & c"),
+            vec!["{{count}}"]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use crate::placeholder::{tokenize, Token};
+use crate::{TranslationInput, TranslationOutput};
+use itertools::Itertools;
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct PseudolocalizationOptions {
+    pub expansion_ratio: f32,
+    pub accents: bool,
+    pub brackets: bool,
+    pub rtl: bool,
+}
+
+impl Default for PseudolocalizationOptions {
+    fn default() -> Self {
+        PseudolocalizationOptions {
+            expansion_ratio: 0.3,
+            accents: true,
+            brackets: true,
+            rtl: false,
+        }
+    }
+}
+
+const RIGHT_TO_LEFT_OVERRIDE: char = '\u{202E}';
+const POP_DIRECTIONAL_FORMATTING: char = '\u{202C}';
+
+fn accent(character: char) -> char {
+    match character {
+        'a' => 'å',
+        'b' => 'ƀ',
+        'c' => 'ç',
+        'd' => 'ð',
+        'e' => 'é',
+        'f' => 'ƒ',
+        'g' => 'ĝ',
+        'h' => 'ĥ',
+        'i' => 'î',
+        'j' => 'ĵ',
+        'k' => 'ķ',
+        'l' => 'ļ',
+        'm' => 'ɱ',
+        'n' => 'ñ',
+        'o' => 'ö',
+        'p' => 'þ',
+        'q' => 'ǫ',
+        'r' => 'ŕ',
+        's' => 'š',
+        't' => 'ţ',
+        'u' => 'û',
+        'v' => 'ṽ',
+        'w' => 'ŵ',
+        'x' => 'ẋ',
+        'y' => 'ý',
+        'z' => 'ž',
+        'A' => 'Å',
+        'B' => 'Ɓ',
+        'C' => 'Ç',
+        'D' => 'Ð',
+        'E' => 'É',
+        'F' => 'Ƒ',
+        'G' => 'Ĝ',
+        'H' => 'Ĥ',
+        'I' => 'Î',
+        'J' => 'Ĵ',
+        'K' => 'Ķ',
+        'L' => 'Ļ',
+        'M' => 'Ṁ',
+        'N' => 'Ñ',
+        'O' => 'Ö',
+        'P' => 'Þ',
+        'Q' => 'Ǫ',
+        'R' => 'Ŕ',
+        'S' => 'Š',
+        'T' => 'Ţ',
+        'U' => 'Û',
+        'V' => 'Ṽ',
+        'W' => 'Ŵ',
+        'X' => 'Ẋ',
+        'Y' => 'Ý',
+        'Z' => 'Ž',
+        other => other,
+    }
+}
+
+fn pseudolocalize_text(text: &str, options: &PseudolocalizationOptions) -> String {
+    let text = if options.accents {
+        text.chars().map(accent).collect::<String>()
+    } else {
+        text.to_owned()
+    };
+
+    if options.rtl && !text.trim().is_empty() {
+        format!("{RIGHT_TO_LEFT_OVERRIDE}{text}{POP_DIRECTIONAL_FORMATTING}")
+    } else {
+        text
+    }
+}
+
+/// Pseudolocalize `text`, leaving placeholders and markup untouched so that the result still
+/// renders in the UI it came from.
+pub fn pseudolocalize(text: &str, options: &PseudolocalizationOptions) -> String {
+    let mut length = 0;
+    let mut pseudolocalized = tokenize(text)
+        .into_iter()
+        .map(|token| match token {
+            Token::Text(text) => {
+                length += text.chars().count();
+                pseudolocalize_text(text, options)
+            }
+            Token::Placeholder(placeholder) => placeholder.to_owned(),
+        })
+        .collect::<String>();
+
+    let expansion = (length as f32 * options.expansion_ratio).ceil() as usize;
+    if expansion > 0 {
+        pseudolocalized.push(' ');
+        pseudolocalized.push_str(&"!".repeat(expansion));
+    }
+
+    if options.brackets {
+        format!("[{pseudolocalized}]")
+    } else {
+        pseudolocalized
+    }
+}
+
+pub fn pseudolocalize_inputs(
+    inputs: Vec<TranslationInput>,
+    options: &PseudolocalizationOptions,
+) -> Vec<TranslationOutput> {
+    inputs
+        .into_iter()
+        .map(|input| {
+            TranslationOutput::new(pseudolocalize(&input.text, options), input.source_language)
+        })
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pseudo::{pseudolocalize, PseudolocalizationOptions};
+
+    #[test]
+    fn it_should_accent_expand_and_bracket_text() {
+        assert_eq!(
+            pseudolocalize("Hello", &PseudolocalizationOptions::default()),
+            "[Ĥéļļö !!]"
+        );
+    }
+
+    #[test]
+    fn it_should_respect_disabled_options() {
+        let options = PseudolocalizationOptions {
+            expansion_ratio: 0.0,
+            accents: false,
+            brackets: false,
+            rtl: false,
+        };
+        assert_eq!(pseudolocalize("Hello", &options), "Hello");
+    }
+
+    #[test]
+    fn it_should_preserve_placeholders_and_markup() {
+        let options = PseudolocalizationOptions {
+            expansion_ratio: 0.0,
+            ..PseudolocalizationOptions::default()
+        };
+        assert_eq!(
+            pseudolocalize("Hi <b>{name}</b>, %1$s", &options),
+            "[Ĥî <b>{name}</b>, %1$s]"
+        );
+    }
+
+    #[test]
+    fn it_should_simulate_right_to_left_text() {
+        let options = PseudolocalizationOptions {
+            expansion_ratio: 0.0,
+            accents: false,
+            brackets: false,
+            rtl: true,
+        };
+        assert_eq!(
+            pseudolocalize("Hi {name}", &options),
+            "\u{202E}Hi \u{202C}{name}"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use crate::memory::{normalize, similarity};
+use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
+
+/// What kind of mistake a translation makes.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityErrorCategory {
+    /// The meaning is changed.
+    Accuracy,
+    /// The translation is ungrammatical or unnatural.
+    Fluency,
+    Terminology,
+    /// The register or tone doesn't fit.
+    Style,
+    Omission,
+    Addition,
+    Untranslated,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct QualityError {
+    pub category: QualityErrorCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// How good a translation is estimated to be, without a reference translation to compare it
+/// with.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct QualityEstimate {
+    /// From 0, unusable, to 100, perfect.
+    pub score: u8,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<QualityError>,
+    /// Whether the score is below the threshold, so the translation should be reviewed.
+    #[serde(default)]
+    pub low_confidence: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize_enum_str, Serialize_enum_str, PartialEq, Eq)]
+pub enum QualityEstimationMethod {
+    /// Translate the translation back into the source language and compare it with the text.
+    #[serde(rename = "back-translation")]
+    BackTranslation,
+    /// Ask an LLM to score the translation and list its errors.
+    #[serde(rename = "judge")]
+    Judge,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait QualityJudge: Translation {
+    /// Whether [Self::judge_quality] gives estimates.
+    fn supports_quality_judging(&self) -> bool {
+        false
+    }
+
+    /// Score how well each of `outputs` translates the input at the same index, or `None`
+    /// when it can't be judged.
+    async fn judge_quality(
+        &self,
+        _inputs: &[TranslationInput],
+        outputs: &[TranslationOutput],
+    ) -> Result<Vec<Option<QualityEstimate>>, TranslationError> {
+        Ok(vec![None; outputs.len()])
+    }
+}
+
+/// Estimate the quality of each of `outputs` by translating it back into the language of its
+/// input with `translation` and scoring how similar the result is to the input's text. The
+/// score drops for paraphrases as well as for mistakes, so it's best used to rank outputs.
+///
+/// Outputs are only estimated when the language of their input is given or was detected.
+pub async fn estimate_by_back_translation<T: Translation>(
+    translation: &T,
+    inputs: &[TranslationInput],
+    outputs: &[TranslationOutput],
+) -> Result<Vec<Option<QualityEstimate>>, TranslationError> {
+    let back_inputs = inputs
+        .iter()
+        .zip(outputs)
+        .map(|(input, output)| {
+            let source_language = input
+                .source_language
+                .clone()
+                .or(output.source_language.clone())?;
+            Some(TranslationInput::new(
+                output.text.clone(),
+                Some(input.target_language.clone()),
+                source_language,
+            ))
+        })
+        .collect_vec();
+    let mut back_outputs = translation
+        .translate(back_inputs.iter().flatten().cloned().collect_vec())
+        .await?
+        .into_iter();
+
+    Ok(inputs
+        .iter()
+        .zip(back_inputs)
+        .map(|(input, back_input)| {
+            back_input?;
+            let back_output = back_outputs.next()?;
+            let text = normalize(&input.text).chars().collect_vec();
+            let back_text = normalize(&back_output.text).chars().collect_vec();
+            Some(QualityEstimate {
+                score: (similarity(&text, &back_text) * 100.0).round() as u8,
+                errors: Vec::new(),
+                low_confidence: false,
+            })
+        })
+        .collect_vec())
+}
+
+/// Adds quality estimates to translations, flagging those scored below a threshold.
+pub struct QualityEstimator<T> {
+    translation: T,
+    method: QualityEstimationMethod,
+    threshold: u8,
+}
+
+impl<T: QualityJudge> QualityEstimator<T> {
+    /// An estimator that uses `translation` to back-translate or to judge, or none when it's
+    /// to judge but can't.
+    pub fn new(translation: T, method: QualityEstimationMethod, threshold: u8) -> Option<Self> {
+        if method == QualityEstimationMethod::Judge && !translation.supports_quality_judging() {
+            return None;
+        }
+        Some(QualityEstimator {
+            translation,
+            method,
+            threshold,
+        })
+    }
+
+    pub fn method(&self) -> QualityEstimationMethod {
+        self.method
+    }
+
+    /// Add a quality estimate to each of `outputs`, the translations of `inputs`. Exact
+    /// matches from the translation memory are left without one as they've been translated
+    /// before.
+    pub async fn estimate(
+        &self,
+        inputs: &[TranslationInput],
+        mut outputs: Vec<TranslationOutput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let indexes = outputs
+            .iter()
+            .positions(|output| output.match_score.is_none_or(|score| score < 1.0))
+            .collect_vec();
+        let inputs = indexes
+            .iter()
+            .map(|index| inputs[*index].clone())
+            .collect_vec();
+        let estimated_outputs = indexes
+            .iter()
+            .map(|index| outputs[*index].clone())
+            .collect_vec();
+
+        let estimates = match self.method {
+            QualityEstimationMethod::BackTranslation => {
+                estimate_by_back_translation(&self.translation, &inputs, &estimated_outputs).await?
+            }
+            QualityEstimationMethod::Judge => {
+                self.translation
+                    .judge_quality(&inputs, &estimated_outputs)
+                    .await?
+            }
+        };
+        for (index, estimate) in indexes.into_iter().zip(estimates) {
+            outputs[index].quality = estimate.map(|estimate| QualityEstimate {
+                low_confidence: estimate.score < self.threshold,
+                ..estimate
+            });
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::quality::{QualityEstimationMethod, QualityEstimator};
+    use crate::{Translation, TranslationInput};
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+
+    fn provider() -> MockTranslationProvider {
+        let dictionary = HashMap::from([
+            (
+                "fr".to_owned(),
+                HashMap::from([
+                    ("Hello".to_owned(), "Bonjour".to_owned()),
+                    ("Save the file".to_owned(), "Sauver".to_owned()),
+                ]),
+            ),
+            (
+                "en".to_owned(),
+                HashMap::from([
+                    ("Bonjour".to_owned(), "Hello".to_owned()),
+                    ("Sauver".to_owned(), "Save".to_owned()),
+                ]),
+            ),
+        ]);
+        MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        )
+    }
+
+    #[test]
+    fn it_should_flag_translations_that_dont_back_translate() {
+        let inputs = vec![
+            TranslationInput::test("Hello", "fr"),
+            TranslationInput::test("Save the file", "fr"),
+        ];
+        let outputs = block_on(provider().translate(inputs.clone())).unwrap();
+
+        // the mock can't judge
+        assert!(QualityEstimator::new(provider(), QualityEstimationMethod::Judge, 70).is_none());
+        let estimator =
+            QualityEstimator::new(provider(), QualityEstimationMethod::BackTranslation, 70)
+                .unwrap();
+        let outputs = block_on(estimator.estimate(&inputs, outputs)).unwrap();
+
+        let first = outputs[0].quality().unwrap();
+        assert_eq!(first.score, 100);
+        assert!(!first.low_confidence);
+        let second = outputs[1].quality().unwrap();
+        assert!(second.score < 70);
+        assert!(second.low_confidence);
+    }
+}
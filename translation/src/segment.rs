@@ -0,0 +1,562 @@
+use crate::validation::{
+    complete_outputs, detect_language, expect_outputs, partial_outputs, InvalidTranslation,
+};
+use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+use itertools::Itertools;
+use std::ops::Range;
+
+/// How many characters a segment has at most by default, well within DeepL's request limit
+/// and the context windows of LLMs, while long enough to keep whole paragraphs together.
+pub const DEFAULT_MAX_SEGMENT_CHARACTERS: usize = 4000;
+
+/// Characters that end a sentence whatever follows them.
+const SENTENCE_TERMINATORS: [char; 14] = [
+    '!', '?', '‼', '⁇', '؟', '۔', '।', '॥', '։', '።', '。', '！', '？', ';',
+];
+
+/// Terminators of scripts without spaces between sentences, which end one even when the next
+/// follows straight after.
+const UNSPACED_SENTENCE_TERMINATORS: [char; 3] = ['。', '！', '？'];
+
+/// Characters that can close a sentence after its terminator, such as a closing quote.
+const CLOSING_CHARACTERS: [char; 11] = ['"', '\'', '”', '’', '»', ')', ']', '」', '』', '）', '】'];
+
+/// Words that end with a period without ending the sentence, lower case and without their
+/// last period, by the ISO 639-3 code of their language.
+const ABBREVIATIONS: [(&str, &[&str]); 7] = [
+    (
+        "eng",
+        &[
+            "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "etc", "e.g", "i.e", "inc",
+            "ltd", "co", "no", "approx", "dept", "fig",
+        ],
+    ),
+    (
+        "deu",
+        &[
+            "z.b", "bzw", "usw", "vgl", "ca", "dr", "nr", "hr", "fr", "str", "prof", "evtl", "ggf",
+            "inkl", "d.h", "u.a",
+        ],
+    ),
+    (
+        "fra",
+        &[
+            "m", "mme", "mlle", "mm", "dr", "etc", "p.ex", "cf", "env", "av", "bd",
+        ],
+    ),
+    (
+        "spa",
+        &[
+            "sr", "sra", "srta", "dr", "dra", "etc", "p.ej", "ud", "uds", "av",
+        ],
+    ),
+    ("ita", &["sig", "sig.ra", "dott", "ecc", "es", "prof"]),
+    ("por", &["sr", "sra", "dr", "dra", "etc", "ex", "av"]),
+    ("nld", &["dhr", "mevr", "dr", "bijv", "enz", "o.a", "d.w.z"]),
+];
+
+/// Languages that write ordinal numbers with a period, such as "1. Mai" in German.
+const ORDINAL_PERIOD_LANGUAGES: [&str; 14] = [
+    "deu", "dan", "nob", "nno", "fin", "ces", "slk", "pol", "hun", "hrv", "slv", "srp", "est",
+    "isl",
+];
+
+/// Languages that separate sentences with a space rather than punctuation.
+const SPACE_SEPARATED_SENTENCE_LANGUAGES: [&str; 2] = ["tha", "lao"];
+
+/// How sentences end in a language.
+struct SentenceRules {
+    abbreviations: &'static [&'static str],
+    ordinal_periods: bool,
+    space_separated: bool,
+    /// Whether `;` is a question mark, as in Greek.
+    semicolon_question_mark: bool,
+}
+
+impl SentenceRules {
+    /// The rules of the language with the ISO 639-3 `code`, or those shared by all languages
+    /// when it isn't known.
+    fn for_language(code: Option<&str>) -> Self {
+        let code = code.unwrap_or_default();
+        SentenceRules {
+            abbreviations: ABBREVIATIONS
+                .iter()
+                .find(|(language, _)| *language == code)
+                .map_or(&[], |(_, abbreviations)| abbreviations),
+            ordinal_periods: ORDINAL_PERIOD_LANGUAGES.contains(&code),
+            space_separated: SPACE_SEPARATED_SENTENCE_LANGUAGES.contains(&code),
+            semicolon_question_mark: code == "ell",
+        }
+    }
+
+    /// Whether a sentence ends with `before`, when `next` is the character after the
+    /// whitespace that follows it.
+    fn is_sentence_end(&self, before: &str, next: char) -> bool {
+        let before = before.trim_end_matches(CLOSING_CHARACTERS);
+        let Some(last) = before.chars().last() else {
+            return false;
+        };
+        match last {
+            ';' => self.semicolon_question_mark,
+            '.' | '…' => {
+                let word = before
+                    .trim_end_matches(['.', '…'])
+                    .rsplit(char::is_whitespace)
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches(['"', '\'', '“', '‘', '«', '(', '['])
+                    .to_lowercase();
+                // initials such as the J. of J. Smith
+                let is_initial = word.chars().count() == 1 && word.chars().all(char::is_alphabetic);
+                let is_ordinal = self.ordinal_periods
+                    && !word.is_empty()
+                    && word.chars().all(|char| char.is_ascii_digit());
+                !(last == '.' && (self.abbreviations.contains(&word.as_str()) || is_initial))
+                    && !is_ordinal
+                    && !next.is_lowercase()
+            }
+            _ if SENTENCE_TERMINATORS.contains(&last) => true,
+            _ => self.space_separated,
+        }
+    }
+}
+
+/// How finely a text is split, from the coarsest.
+#[derive(Clone, Copy)]
+enum Boundary {
+    Paragraph,
+    Sentence,
+    Word,
+    Character,
+}
+
+fn character_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// The ranges of the runs of whitespace in `text`, offset by `offset`.
+fn whitespace_runs(text: &str, offset: usize) -> Vec<Range<usize>> {
+    let mut runs = Vec::<Range<usize>>::new();
+    for (index, char) in text.char_indices() {
+        if !char.is_whitespace() {
+            continue;
+        }
+        let index = offset + index;
+        match runs.last_mut() {
+            Some(run) if run.end == index => run.end = index + char.len_utf8(),
+            _ => runs.push(index..index + char.len_utf8()),
+        }
+    }
+    runs
+}
+
+/// The parts of `text` within `range` between its `boundary`, without the whitespace between
+/// them.
+fn split(
+    text: &str,
+    range: Range<usize>,
+    boundary: Boundary,
+    rules: &SentenceRules,
+) -> Vec<Range<usize>> {
+    let slice = &text[range.clone()];
+    let mut separators = whitespace_runs(slice, range.start)
+        .into_iter()
+        .filter(|run| {
+            let whitespace = &text[run.clone()];
+            match boundary {
+                Boundary::Paragraph => whitespace.matches('\n').count() >= 2,
+                Boundary::Sentence => {
+                    whitespace.contains('\n')
+                        || text[run.end..].chars().next().is_some_and(|next| {
+                            rules.is_sentence_end(&text[range.start..run.start], next)
+                        })
+                }
+                Boundary::Word => true,
+                // split by [pack] instead
+                Boundary::Character => false,
+            }
+        })
+        .collect_vec();
+    if let Boundary::Sentence = boundary {
+        // sentences of scripts such as Chinese follow each other without whitespace
+        let unspaced = slice
+            .char_indices()
+            .map(|(index, char)| (range.start + index + char.len_utf8(), char))
+            .filter(|(end, char)| {
+                UNSPACED_SENTENCE_TERMINATORS.contains(char)
+                    && *end < range.end
+                    && !text[*end..].starts_with(char::is_whitespace)
+                    && !text[*end..].starts_with(CLOSING_CHARACTERS)
+            })
+            .map(|(end, _)| end..end);
+        separators = separators
+            .into_iter()
+            .chain(unspaced)
+            .sorted_by_key(|run| run.start)
+            .collect_vec();
+    }
+
+    let mut start = range.start;
+    let mut parts = Vec::new();
+    for separator in separators {
+        parts.push(start..separator.start);
+        start = separator.end;
+    }
+    parts.push(start..range.end);
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect_vec()
+}
+
+/// Add the ranges of `text` within `range` of at most `max_characters` to `segments`, keeping
+/// as many parts between `boundary` together as fit and splitting those that don't at finer
+/// boundaries.
+fn pack(
+    text: &str,
+    range: Range<usize>,
+    boundary: Boundary,
+    rules: &SentenceRules,
+    max_characters: usize,
+    segments: &mut Vec<Range<usize>>,
+) {
+    let finer = match boundary {
+        Boundary::Paragraph => Boundary::Sentence,
+        Boundary::Sentence => Boundary::Word,
+        Boundary::Word | Boundary::Character => Boundary::Character,
+    };
+    if let Boundary::Character = boundary {
+        let starts = text[range.clone()]
+            .char_indices()
+            .map(|(index, _)| range.start + index)
+            .step_by(max_characters)
+            .chain([range.end])
+            .collect_vec();
+        segments.extend(
+            starts
+                .into_iter()
+                .tuple_windows()
+                .map(|(start, end)| start..end),
+        );
+        return;
+    }
+
+    // the segment being packed with how many characters it has
+    let mut current: Option<(Range<usize>, usize)> = None;
+    for part in split(text, range, boundary, rules) {
+        if let Some((segment, count)) = current.take() {
+            let count = count + character_count(&text[segment.end..part.end]);
+            if count <= max_characters {
+                current = Some((segment.start..part.end, count));
+                continue;
+            }
+            segments.push(segment);
+        }
+        let count = character_count(&text[part.clone()]);
+        if count <= max_characters {
+            current = Some((part, count));
+        } else {
+            pack(text, part, finer, rules, max_characters, segments);
+        }
+    }
+    segments.extend(current.map(|(segment, _)| segment));
+}
+
+/// The ranges of the segments of `text`, in the language with the ISO 639-3 `language` code
+/// when it's known.
+fn segments(text: &str, language: Option<&str>, max_characters: usize) -> Vec<Range<usize>> {
+    let start = text.len() - text.trim_start().len();
+    let end = text.trim_end().len();
+    let mut segments = Vec::new();
+    if start < end {
+        let rules = SentenceRules::for_language(language);
+        pack(
+            text,
+            start..end,
+            Boundary::Paragraph,
+            &rules,
+            max_characters,
+            &mut segments,
+        );
+    }
+    segments
+}
+
+/// Splits texts too long to translate in one request into segments at paragraph, then
+/// sentence, then word boundaries, so each segment can be translated on its own.
+#[derive(Clone, Copy, Debug)]
+pub struct Segmenter {
+    max_characters: usize,
+}
+
+impl Default for Segmenter {
+    fn default() -> Self {
+        Segmenter::new(DEFAULT_MAX_SEGMENT_CHARACTERS)
+    }
+}
+
+impl Segmenter {
+    pub fn new(max_characters: usize) -> Self {
+        Segmenter {
+            max_characters: max_characters.max(1),
+        }
+    }
+
+    /// Whether the text of `input` is too long to translate as it is.
+    pub fn splits(&self, input: &TranslationInput) -> bool {
+        character_count(&input.text) > self.max_characters
+    }
+
+    /// The inputs to translate instead of `inputs`, with long texts split into segments, and
+    /// how to join their translations back together.
+    pub fn split(&self, inputs: Vec<TranslationInput>) -> (Vec<TranslationInput>, Segmentation) {
+        let mut segment_inputs = Vec::with_capacity(inputs.len());
+        let mut separators = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            if !self.splits(&input) {
+                segment_inputs.push(input);
+                separators.push(None);
+                continue;
+            }
+            let language = match &input.source_language {
+                Some(language) => Some(language.0.to_639_3()),
+                None => detect_language(&input.text),
+            };
+            let ranges = segments(&input.text, language, self.max_characters);
+            if ranges.is_empty() {
+                segment_inputs.push(input);
+                separators.push(None);
+                continue;
+            }
+            // the whitespace before, between and after the segments
+            let ends = [0]
+                .into_iter()
+                .chain(ranges.iter().map(|range| range.end))
+                .collect_vec();
+            let starts = ranges
+                .iter()
+                .map(|range| range.start)
+                .chain([input.text.len()])
+                .collect_vec();
+            separators.push(Some(
+                ends.into_iter()
+                    .zip(starts)
+                    .map(|(end, start)| input.text[end..start].to_owned())
+                    .collect_vec(),
+            ));
+            segment_inputs.extend(ranges.into_iter().map(|range| TranslationInput {
+                text: input.text[range].to_owned(),
+                ..input.clone()
+            }));
+        }
+        (segment_inputs, Segmentation { separators })
+    }
+
+    /// Translate `inputs` with `translation`, splitting long texts into segments and joining
+    /// the translations of their segments.
+    pub async fn translate<T: Translation>(
+        &self,
+        translation: &T,
+        inputs: Vec<TranslationInput>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let (inputs, segmentation) = self.split(inputs);
+        segmentation.join(translation.translate(inputs).await)
+    }
+}
+
+/// How the texts split by a [Segmenter] were split.
+pub struct Segmentation {
+    /// For each text that was split, the whitespace before, between and after its segments.
+    separators: Vec<Option<Vec<String>>>,
+}
+
+/// The translation of a text from the translations of its segments, with the whitespace
+/// and paragraph breaks of the text.
+fn join_segments(separators: &[String], outputs: Vec<TranslationOutput>) -> TranslationOutput {
+    let mut text = separators[0].clone();
+    for (output, separator) in outputs.iter().zip(&separators[1..]) {
+        text.push_str(output.text.trim());
+        text.push_str(separator);
+    }
+    let tier = outputs
+        .iter()
+        .map(|output| output.tier.clone())
+        .all_equal_value()
+        .ok()
+        .flatten();
+    TranslationOutput {
+        text,
+        source_language: outputs
+            .iter()
+            .find_map(|output| output.source_language.clone()),
+        // only as good a match as its worst segment
+        match_score: outputs
+            .iter()
+            .map(|output| output.match_score)
+            .collect::<Option<Vec<f32>>>()
+            .and_then(|scores| scores.into_iter().reduce(f32::min)),
+        alternatives: Vec::new(),
+        quality: None,
+        // segments escalated to different tiers have no one tier
+        tier,
+        ensemble: None,
+    }
+}
+
+impl Segmentation {
+    /// The translations of the texts that were split, from the `result` of translating
+    /// their segments. A text is left out of a partial translation when any of its segments
+    /// is, with the error of its first invalid segment.
+    pub fn join(
+        &self,
+        result: Result<Vec<TranslationOutput>, TranslationError>,
+    ) -> Result<Vec<TranslationOutput>, TranslationError> {
+        let (outputs, segment_errors) = partial_outputs(result)?;
+        let segment_count = self
+            .separators
+            .iter()
+            .map(|separators| {
+                separators
+                    .as_ref()
+                    .map_or(1, |separators| separators.len() - 1)
+            })
+            .sum();
+        let mut outputs = expect_outputs(outputs, segment_count)?.into_iter();
+        let mut segment_errors = segment_errors.into_iter().peekable();
+        let mut segment_index = 0;
+        let mut joined = Vec::with_capacity(self.separators.len());
+        let mut errors = Vec::new();
+        for (index, separators) in self.separators.iter().enumerate() {
+            let count = separators
+                .as_ref()
+                .map_or(1, |separators| separators.len() - 1);
+            segment_index += count;
+            let text_errors = segment_errors
+                .peeking_take_while(|error| error.index < segment_index)
+                .collect_vec();
+            if let Some(error) = text_errors.into_iter().next() {
+                errors.push(InvalidTranslation { index, ..error });
+            }
+            let segment_outputs = outputs.by_ref().take(count).collect::<Option<Vec<_>>>();
+            joined.push(match separators {
+                Some(separators) => segment_outputs
+                    .map(|segment_outputs| join_segments(separators, segment_outputs)),
+                None => segment_outputs.and_then(|mut outputs| outputs.pop()),
+            });
+        }
+        complete_outputs(joined, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{MockFaultInjection, MockMode, MockTranslationProvider};
+    use crate::segment::{segments, Segmenter};
+    use crate::{TranslationError, TranslationInput, TranslationOutput};
+    use futures::executor::block_on;
+    use itertools::Itertools;
+    use std::collections::HashMap;
+
+    fn texts<'a>(text: &'a str, language: Option<&str>, max_characters: usize) -> Vec<&'a str> {
+        segments(text, language, max_characters)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect_vec()
+    }
+
+    #[test]
+    fn it_should_split_at_paragraphs_then_sentences() {
+        let text = "First paragraph.\n\nSecond one. It has two sentences.";
+        assert_eq!(
+            texts(text, Some("eng"), 35),
+            vec!["First paragraph.", "Second one. It has two sentences."]
+        );
+        assert_eq!(
+            texts(text, Some("eng"), 21),
+            vec!["First paragraph.", "Second one.", "It has two sentences."]
+        );
+        assert_eq!(
+            texts(text, Some("eng"), 1000),
+            vec!["First paragraph.\n\nSecond one. It has two sentences."]
+        );
+    }
+
+    #[test]
+    fn it_should_follow_the_sentence_rules_of_the_language() {
+        assert_eq!(
+            texts("Mr. Smith met J. Doe. They spoke.", Some("eng"), 25),
+            vec!["Mr. Smith met J. Doe.", "They spoke."]
+        );
+        assert_eq!(
+            texts("Am 1. Mai ist frei. Wir feiern.", Some("deu"), 20),
+            vec!["Am 1. Mai ist frei.", "Wir feiern."]
+        );
+        assert_eq!(
+            texts("今天天气很好。我们去海边。", Some("cmn"), 8),
+            vec!["今天天气很好。", "我们去海边。"]
+        );
+        assert_eq!(
+            texts("Τι κάνεις; Είμαι καλά.", Some("ell"), 12),
+            vec!["Τι κάνεις;", "Είμαι καλά."]
+        );
+        // too long for a sentence
+        assert_eq!(
+            texts("one two three four", None, 9),
+            vec!["one two", "three", "four"]
+        );
+        assert_eq!(texts("abcdef", None, 4), vec!["abcd", "ef"]);
+    }
+
+    #[test]
+    fn it_should_join_translations_with_the_original_whitespace() {
+        let dictionary = HashMap::from([(
+            "fr".to_owned(),
+            HashMap::from([
+                ("First one.".to_owned(), "Premier.".to_owned()),
+                ("Second one.".to_owned(), "Deuxième.".to_owned()),
+                ("Short.".to_owned(), "Court.".to_owned()),
+            ]),
+        )]);
+        let provider = MockTranslationProvider::new(
+            MockMode::Dictionary(dictionary),
+            MockFaultInjection::default(),
+            true,
+        );
+        let inputs = ["  First one.\n\nSecond one.\n", "Short."]
+            .map(|text| TranslationInput::test(text, "fr"))
+            .to_vec();
+
+        let segmenter = Segmenter::new(12);
+        let (segment_inputs, _) = segmenter.split(inputs.clone());
+        assert_eq!(
+            segment_inputs
+                .iter()
+                .map(|input| input.text())
+                .collect_vec(),
+            vec!["First one.", "Second one.", "Short."]
+        );
+        let outputs = block_on(segmenter.translate(&provider, inputs)).unwrap();
+
+        assert_eq!(
+            outputs.iter().map(|output| output.text()).collect_vec(),
+            vec!["  Premier.\n\nDeuxième.\n", "Court."]
+        );
+    }
+
+    #[test]
+    fn it_should_fail_to_join_missing_segment_outputs() {
+        let inputs = ["Hello", "Bye"]
+            .map(|text| TranslationInput::test(text, "fr"))
+            .to_vec();
+        let (_, segmentation) = Segmenter::default().split(inputs);
+        let output = TranslationOutput::new("Bonjour".to_owned(), None);
+
+        assert!(matches!(
+            segmentation.join(Ok(vec![output])),
+            Err(TranslationError::OutputCountMismatch {
+                expected: 2,
+                received: 1
+            })
+        ));
+    }
+}
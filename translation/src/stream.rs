@@ -0,0 +1,158 @@
+use crate::{Translation, TranslationError, TranslationInput, TranslationOutput};
+use serde::Serialize;
+use tokio::sync::mpsc::Sender;
+
+/// Progress of a streamed translation, for the input at `index`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum TranslationEvent {
+    /// The next part of a translation that's still being generated.
+    Delta {
+        index: usize,
+        text: String,
+    },
+    Output {
+        index: usize,
+        #[serde(flatten)]
+        output: TranslationOutput,
+    },
+    Error {
+        index: usize,
+        error: String,
+    },
+}
+
+impl TranslationEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TranslationEvent::Delta { .. } => "delta",
+            TranslationEvent::Output { .. } => "output",
+            TranslationEvent::Error { .. } => "error",
+        }
+    }
+}
+
+/// Where a streamed translation sends its events. Events for a subset of the inputs can be
+/// sent with their indexes in that subset, see [Self::for_indexes].
+///
+/// Outputs and errors wait for room in the channel, while deltas are skipped when it's full as
+/// the output has the whole translation anyway.
+#[derive(Clone)]
+pub struct TranslationEvents {
+    sender: Sender<TranslationEvent>,
+    indexes: Option<Vec<usize>>,
+}
+
+impl TranslationEvents {
+    pub fn new(sender: Sender<TranslationEvent>) -> Self {
+        TranslationEvents {
+            sender,
+            indexes: None,
+        }
+    }
+
+    /// Events for the inputs at `indexes`, sent with their index in `indexes`.
+    pub fn for_indexes(&self, indexes: Vec<usize>) -> Self {
+        let indexes = indexes.into_iter().map(|index| self.index(index)).collect();
+        TranslationEvents {
+            sender: self.sender.clone(),
+            indexes: Some(indexes),
+        }
+    }
+
+    fn index(&self, index: usize) -> usize {
+        match &self.indexes {
+            Some(indexes) => indexes.get(index).copied().unwrap_or(index),
+            None => index,
+        }
+    }
+
+    /// Wait until nobody is listening any more, so what's left can be dropped unfinished.
+    pub async fn closed(&self) {
+        self.sender.closed().await
+    }
+
+    // a closed receiver only means nobody is listening any more
+    pub fn delta(&self, index: usize, text: &str) {
+        let _ = self.sender.try_send(TranslationEvent::Delta {
+            index: self.index(index),
+            text: text.to_owned(),
+        });
+    }
+
+    pub async fn output(&self, index: usize, output: &TranslationOutput) {
+        let _ = self
+            .sender
+            .send(TranslationEvent::Output {
+                index: self.index(index),
+                output: output.clone(),
+            })
+            .await;
+    }
+
+    pub async fn error(&self, index: usize, error: &TranslationError) {
+        let _ = self
+            .sender
+            .send(TranslationEvent::Error {
+                index: self.index(index),
+                error: error.to_string(),
+            })
+            .await;
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait StreamingTranslation: Translation {
+    /// Translate `inputs`, sending each output as soon as it's done rather than once all of
+    /// them are. Returns the outputs, with [None] for inputs that failed.
+    ///
+    /// By default the inputs are translated together and sent at once, for providers that
+    /// translate them in one request anyway.
+    async fn translate_streaming(
+        &self,
+        inputs: Vec<TranslationInput>,
+        events: &TranslationEvents,
+    ) -> Vec<Option<TranslationOutput>> {
+        let count = inputs.len();
+        match self.translate(inputs).await {
+            Ok(outputs) => {
+                for (index, output) in outputs.iter().enumerate() {
+                    events.output(index, output).await;
+                }
+                outputs.into_iter().map(Some).collect()
+            }
+            Err(error) => {
+                for index in 0..count {
+                    events.error(index, &error).await;
+                }
+                vec![None; count]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::{TranslationEvent, TranslationEvents};
+    use crate::TranslationOutput;
+    use futures::executor::block_on;
+    use tokio::sync::mpsc::channel;
+
+    #[test]
+    fn it_should_send_events_with_their_original_index() {
+        let (sender, mut receiver) = channel(4);
+        let events = TranslationEvents::new(sender);
+        let misses = events.for_indexes(vec![1, 3]).for_indexes(vec![1]);
+        let output = TranslationOutput::new("Bonjour".to_owned(), None);
+
+        misses.delta(0, "Bon");
+        block_on(misses.output(0, &output));
+        let delta = receiver.try_recv().unwrap();
+        assert_eq!(delta.name(), "delta");
+        assert!(matches!(delta, TranslationEvent::Delta { index: 3, .. }));
+        assert_eq!(
+            serde_json::to_string(&receiver.try_recv().unwrap()).unwrap(),
+            r#"{"index":3,"output":"Bonjour"}"#
+        );
+    }
+}